@@ -0,0 +1,118 @@
+//! Baselines for the parse and write hot paths over the files in `assets/`, run with
+//! `cargo bench`. Throughput is reported in bytes/sec of the input, so a regression shows up
+//! as a lower `thrpt`.
+
+use std::{collections::HashMap, io::Cursor};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+// The main crate is a binary, so the parsers are pulled in by path
+#[path = "../src/log.rs"]
+#[macro_use]
+#[allow(dead_code)]
+mod log;
+#[path = "../src/character_save_parameter.rs"]
+#[allow(dead_code)]
+mod character_save_parameter;
+#[path = "../src/group_guild.rs"]
+#[allow(dead_code)]
+mod group_guild;
+#[path = "../src/hex_dump.rs"]
+#[allow(dead_code)]
+mod hex_dump;
+#[path = "../src/sav.rs"]
+#[allow(dead_code)]
+mod sav;
+
+use character_save_parameter::read_raw_character_save_parameter;
+use group_guild::{read_group_guild_save, stream};
+use sav::{read_save_file, write_save_file};
+
+fn bench_save_file(c: &mut Criterion) {
+    let data = std::fs::read("assets/Level.sav").unwrap();
+    let pal_save = read_save_file(Cursor::new(&data)).unwrap();
+    let mut written = Vec::new();
+    write_save_file(&mut written, &pal_save).unwrap();
+
+    let mut group = c.benchmark_group("save_file");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("read_save_file", |b| {
+        b.iter(|| read_save_file(Cursor::new(&data)).unwrap())
+    });
+    group.throughput(Throughput::Bytes(written.len() as u64));
+    group.bench_function("write_save_file", |b| {
+        b.iter(|| {
+            let mut bytes = Vec::with_capacity(written.len());
+            write_save_file(&mut bytes, &pal_save).unwrap();
+            bytes
+        })
+    });
+    group.finish();
+}
+
+fn bench_raw_data(c: &mut Criterion) {
+    let guild = std::fs::read("assets/guild_0.bin").unwrap();
+    let character = std::fs::read("assets/character_save_parameter.bin").unwrap();
+    let header = uesave::Header {
+        magic: Default::default(),
+        save_game_version: Default::default(),
+        package_version: uesave::PackageVersion::Old(0),
+        engine_version_major: 5,
+        engine_version_minor: Default::default(),
+        engine_version_patch: Default::default(),
+        engine_version_build: Default::default(),
+        engine_version: Default::default(),
+        custom_format_version: Default::default(),
+        custom_format: Default::default(),
+    };
+
+    let mut group = c.benchmark_group("raw_data");
+    group.throughput(Throughput::Bytes(guild.len() as u64));
+    group.bench_function("read_group_guild_save", |b| {
+        b.iter(|| read_group_guild_save(&mut stream(&guild)).unwrap())
+    });
+    group.throughput(Throughput::Bytes(character.len() as u64));
+    group.bench_function("read_raw_character_save_parameter", |b| {
+        b.iter(|| read_raw_character_save_parameter(&header)(&character).unwrap())
+    });
+    group.finish();
+}
+
+/// `get_character_owners` builds its map with the capacity of the whole character map. This
+/// compares that with collecting the same `filter_map` on a server-sized number of characters.
+fn bench_character_owners(c: &mut Criterion) {
+    let keys: Vec<(uuid::Uuid, Option<uuid::Uuid>)> = (0..200_000u128)
+        .map(|index| {
+            let player_uid = (index % 10 == 0).then(|| uuid::Uuid::from_u128(index));
+            (uuid::Uuid::from_u128((index << 64) | 0xffff), player_uid)
+        })
+        .collect();
+    // Like keys that fail to decode, nil InstanceIds are skipped
+    let entries = || {
+        keys.iter().filter_map(|(instance_id, player_uid)| {
+            (!instance_id.is_nil()).then_some((*instance_id, *player_uid))
+        })
+    };
+
+    let mut group = c.benchmark_group("character_owners");
+    group.throughput(Throughput::Elements(keys.len() as u64));
+    group.bench_function("collect", |b| {
+        b.iter(|| entries().collect::<HashMap<_, _>>())
+    });
+    group.bench_function("with_capacity", |b| {
+        b.iter(|| {
+            let mut character_owners = HashMap::with_capacity(keys.len());
+            character_owners.extend(entries());
+            character_owners
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_save_file,
+    bench_raw_data,
+    bench_character_owners
+);
+criterion_main!(benches);
@@ -0,0 +1,21 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use winnow::Parser;
+
+// The main crate is a binary, so the parsers are pulled in by path
+#[path = "../../src/group_guild.rs"]
+#[allow(dead_code)]
+mod group_guild;
+
+use group_guild::{
+    read_fpal_guild_player_info, read_fstring, read_group_guild_save, read_uuid, stream,
+};
+
+// Every parser must return a value or an error on arbitrary input, never panic
+fuzz_target!(|data: &[u8]| {
+    let _ = read_fstring.parse_next(&mut stream(data));
+    let _ = read_uuid.parse_next(&mut stream(data));
+    let _ = read_fpal_guild_player_info.parse_next(&mut stream(data));
+    let _ = read_group_guild_save.parse_next(&mut stream(data));
+});
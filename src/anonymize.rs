@@ -0,0 +1,358 @@
+use std::path::Path;
+
+use anyhow::Context;
+use indexmap::IndexMap;
+use uuid::Uuid;
+
+use crate::{
+    character_save_parameter::write_raw_character_save_parameter,
+    group_guild::{write_group_guild_save, GameVersion},
+    level_meta::HOST_PLAYER_UID,
+    sav::{
+        decode_character_key, get_character_save_parameter_map_mut, get_group_save_data_map_mut,
+        get_guilds, get_map_entries, get_world_save_data, get_world_save_data_mut,
+        parse_raw_character_save_parameter, read_save_file_path, set_raw_data,
+        write_save_file_path, PalSave,
+    },
+    save_directory::{ensure_empty_output_dir, list_player_sav_paths},
+};
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher` it is the same on every platform and Rust version, so
+/// a save anonymized twice gets the same pseudonyms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A name for `id` that does not reveal it, e.g. `Player-1A2B3C4D`.
+pub fn pseudonym(kind: &str, id: &Uuid) -> String {
+    format!("{}-{:08X}", kind, fnv1a(id.as_bytes()) as u32)
+}
+
+/// Maps a PlayerUId to a pseudonymous one for `--remap-uids`. The nil UID of pals and the host
+/// UID keep their meaning, and 32-bit words that are zero stay zero, so the UIDs keep their shape.
+pub fn remap_uid(uid: &Uuid) -> Uuid {
+    if uid.is_nil() || *uid == HOST_PLAYER_UID {
+        return *uid;
+    }
+    let hash = fnv1a(uid.as_bytes());
+    let hash2 = fnv1a(&hash.to_le_bytes());
+    let words = [
+        hash as u32,
+        (hash >> 32) as u32,
+        hash2 as u32,
+        (hash2 >> 32) as u32,
+    ];
+    let mut bytes = *uid.as_bytes();
+    bytes
+        .chunks_exact_mut(4)
+        .zip(words)
+        .for_each(|(word, hash)| {
+            if word.iter().any(|byte| *byte != 0) {
+                word.copy_from_slice(&(hash | 1).to_be_bytes());
+            }
+        });
+    Uuid::from_bytes(bytes)
+}
+
+/// Remaps every `PlayerUId` and `OwnerPlayerUId` Guid in `properties`, in nested structs and in
+/// the keys and values of maps. Arrays of structs are not searched.
+fn remap_player_uids(properties: &mut IndexMap<String, uesave::Property>) {
+    properties
+        .iter_mut()
+        .for_each(|(name, property)| match property {
+            uesave::Property::Struct {
+                value: uesave::StructValue::Guid(uid),
+                ..
+            } if name == "PlayerUId" || name == "OwnerPlayerUId" => *uid = remap_uid(uid),
+            uesave::Property::Struct {
+                value: uesave::StructValue::Struct(properties),
+                ..
+            } => remap_player_uids(properties),
+            uesave::Property::Map { value, .. } => value.iter_mut().for_each(|entry| {
+                [&mut entry.key, &mut entry.value]
+                    .into_iter()
+                    .for_each(|value| {
+                        if let uesave::PropertyValue::Struct(uesave::StructValue::Struct(
+                            properties,
+                        )) = value
+                        {
+                            remap_player_uids(properties);
+                        }
+                    });
+            }),
+            _ => {}
+        });
+}
+
+/// Replaces the names in the guilds and characters of Level.sav with pseudonyms of their ids.
+/// Guild and character counts, InstanceIds and group ids are kept, so structural problems still
+/// reproduce. With `remap_uids` PlayerUIds are remapped as well (see [`remap_uid`]).
+pub fn anonymize_level(
+    level_save: &mut PalSave,
+    game_version: GameVersion,
+    remap_uids: bool,
+) -> anyhow::Result<()> {
+    // Every guild must parse, or its names would be left in the output
+    let guilds = get_guilds(level_save, game_version)?;
+    let guilds: Vec<(Uuid, Vec<u8>)> = guilds
+        .into_iter()
+        .map(|(guild_id, mut group)| {
+            group.GuildName = pseudonym("Guild", &guild_id);
+            group.MayBeOwner = pseudonym("Owner", &guild_id);
+            group.GuildPlayerInfo.iter_mut().for_each(|player_info| {
+                player_info.PlayerName = pseudonym("Player", &player_info.PlayerUId);
+            });
+            if remap_uids {
+                group.AdminPlayerUId = remap_uid(&group.AdminPlayerUId);
+                group.GuildPlayerInfo.iter_mut().for_each(|player_info| {
+                    player_info.PlayerUId = remap_uid(&player_info.PlayerUId);
+                });
+                group.InstanceIds.iter_mut().for_each(|instance_id| {
+                    instance_id.PlayerUId = remap_uid(&instance_id.PlayerUId);
+                });
+            }
+            (guild_id, write_group_guild_save(&group))
+        })
+        .collect();
+
+    let header = &level_save.save.header;
+    let characters: Vec<Vec<u8>> = get_map_entries(level_save, "CharacterSaveParameterMap")
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| -> anyhow::Result<Vec<u8>> {
+            let (player_uid, instance_id) = decode_character_key(entry)?;
+            let mut character_save_parameter = parse_raw_character_save_parameter(header)(entry)
+                .with_context(|| format!("Failed to parse character {}", index))?;
+            if let Some(uesave::Property::Struct {
+                value: uesave::StructValue::Struct(save_parameter),
+                ..
+            }) = character_save_parameter.properties.get_mut("SaveParameter")
+            {
+                if let Some(uesave::Property::Str { value, .. }) =
+                    save_parameter.get_mut("NickName")
+                {
+                    *value = match player_uid {
+                        Some(player_uid) => pseudonym("Player", &player_uid),
+                        // Pals only have a NickName when their owner named them
+                        None if value.is_empty() => String::new(),
+                        None => pseudonym("Pal", &instance_id),
+                    };
+                }
+            }
+            if remap_uids {
+                remap_player_uids(&mut character_save_parameter.properties);
+            }
+            Ok(write_raw_character_save_parameter(header)(
+                &character_save_parameter,
+            ))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    get_group_save_data_map_mut(level_save)
+        .iter_mut()
+        .for_each(|entry| {
+            let uesave::PropertyValue::Struct(uesave::StructValue::Guid(id)) = entry.key else {
+                return;
+            };
+            if let Some((_, raw_data)) = guilds.iter().find(|(guild_id, _)| *guild_id == id) {
+                set_raw_data(entry, raw_data.clone());
+            }
+        });
+    if get_world_save_data(level_save).contains_key("CharacterSaveParameterMap") {
+        get_character_save_parameter_map_mut(level_save)
+            .iter_mut()
+            .zip(characters)
+            .for_each(|(entry, raw_data)| set_raw_data(entry, raw_data));
+    }
+    if remap_uids {
+        remap_player_uids(&mut level_save.save.root.properties);
+    }
+    // The world save data is not needed to reproduce guild and character problems
+    get_world_save_data_mut(level_save)
+        .retain(|name, _| name == "GroupSaveDataMap" || name == "CharacterSaveParameterMap");
+    Ok(())
+}
+
+/// Anonymizes a player save, which has no names, for `--remap-uids`.
+pub fn anonymize_player(player_save: &mut PalSave, remap_uids: bool) {
+    if remap_uids {
+        remap_player_uids(&mut player_save.save.root.properties);
+    }
+}
+
+/// Replaces `HostPlayerName` of LevelMeta.sav with the host's pseudonym.
+pub fn anonymize_level_meta(level_meta: &mut PalSave) {
+    if let Some(uesave::Property::Struct {
+        value: uesave::StructValue::Struct(save_data),
+        ..
+    }) = level_meta.save.root.properties.get_mut("SaveData")
+    {
+        if let Some(uesave::Property::Str { value, .. }) = save_data.get_mut("HostPlayerName") {
+            *value = pseudonym("Player", &HOST_PLAYER_UID);
+        }
+    }
+}
+
+/// Writes an anonymized copy of the save in `input` to `output`. `level_save` is the Level.sav
+/// of `input`. With `remap_uids` player saves are renamed after their remapped PlayerUId.
+pub fn anonymize_save_directory(
+    input: &Path,
+    mut level_save: PalSave,
+    game_version: GameVersion,
+    output: &Path,
+    remap_uids: bool,
+    force: bool,
+) -> anyhow::Result<()> {
+    anonymize_level(&mut level_save, game_version, remap_uids)?;
+    let level_meta_sav = input.join("LevelMeta.sav");
+    let level_meta = if level_meta_sav.exists() {
+        let mut level_meta = read_save_file_path(&level_meta_sav)?;
+        anonymize_level_meta(&mut level_meta);
+        Some(level_meta)
+    } else {
+        None
+    };
+    let players = input.join("Players");
+    let player_saves: Vec<(String, PalSave)> = list_player_sav_paths(&players, false)?
+        .iter()
+        .map(|path| -> anyhow::Result<(String, PalSave)> {
+            let mut player_save = read_save_file_path(path)?;
+            anonymize_player(&mut player_save, remap_uids);
+            let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap();
+            let name = match (remap_uids, Uuid::parse_str(stem)) {
+                (true, Ok(player_uid)) => format!(
+                    "{}.sav",
+                    remap_uid(&player_uid).simple().to_string().to_uppercase()
+                ),
+                _ => format!("{}.sav", stem),
+            };
+            Ok((name, player_save))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    ensure_empty_output_dir(output, force)?;
+    std::fs::create_dir_all(output.join("Players"))
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+    write_save_file_path(
+        &output.join("Level.sav"),
+        &level_save,
+        level_save.compression_type,
+    )?;
+    if let Some(level_meta) = level_meta {
+        write_save_file_path(
+            &output.join("LevelMeta.sav"),
+            &level_meta,
+            level_meta.compression_type,
+        )?;
+    }
+    player_saves.iter().try_for_each(|(name, player_save)| {
+        write_save_file_path(
+            &output.join("Players").join(name),
+            player_save,
+            player_save.compression_type,
+        )
+    })?;
+    info!(
+        "Anonymized save with {} player saves written to {}",
+        player_saves.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+#[test]
+pub fn test_anonymize_save_directory() {
+    use crate::{
+        fixtures,
+        sav::{get_character_key_ids, get_character_save_parameter_map},
+    };
+
+    let input = fixtures::temp_dir("anonymize_input");
+    let output = fixtures::temp_dir("anonymize_output");
+    let (mut level_save, player_saves, broken_players) = fixtures::broken_save(2);
+    let (player_uid, instance_id) = broken_players[0];
+    fixtures::add_character_save(&mut level_save, player_uid, instance_id);
+    fixtures::add_character_save(&mut level_save, Uuid::nil(), Uuid::from_u128(3));
+    fixtures::write_save_directory(&input, &level_save, &player_saves);
+    let original = fixtures::read_guild();
+
+    anonymize_save_directory(
+        &input,
+        level_save.clone(),
+        GameVersion::V0_1_3,
+        &output,
+        false,
+        false,
+    )
+    .unwrap();
+    let anonymized = read_save_file_path(&output.join("Level.sav")).unwrap();
+    let guilds = get_guilds(&anonymized, GameVersion::V0_1_3).unwrap();
+    assert_eq!(guilds.len(), 1);
+    let (guild_id, group) = &guilds[0];
+    assert_eq!(group.GuildName, pseudonym("Guild", guild_id));
+    assert_ne!(group.GuildName, original.GuildName);
+    assert_eq!(group.GuildPlayerInfo.len(), original.GuildPlayerInfo.len());
+    assert_eq!(group.InstanceIds.len(), original.InstanceIds.len());
+    group
+        .GuildPlayerInfo
+        .iter()
+        .zip(&original.GuildPlayerInfo)
+        .for_each(|(player_info, original)| {
+            assert_eq!(player_info.PlayerUId, original.PlayerUId);
+            assert_eq!(
+                player_info.PlayerName,
+                pseudonym("Player", &original.PlayerUId)
+            );
+        });
+    assert_eq!(
+        fixtures::character_instance_ids(&anonymized),
+        vec![instance_id, Uuid::from_u128(3)]
+    );
+    assert_eq!(
+        fixtures::character_nickname(&anonymized, 0),
+        pseudonym("Player", &player_uid)
+    );
+    assert_eq!(
+        get_character_save_parameter_map(&anonymized).len(),
+        get_character_save_parameter_map(&level_save).len()
+    );
+    assert!(output
+        .join("Players")
+        .join(fixtures::player_sav_name(&player_uid))
+        .exists());
+
+    // Remapped UIDs are consistent between the guild, the characters and the player saves
+    let output = fixtures::temp_dir("anonymize_output_remapped");
+    anonymize_save_directory(
+        &input,
+        level_save,
+        GameVersion::V0_1_3,
+        &output,
+        true,
+        false,
+    )
+    .unwrap();
+    let anonymized = read_save_file_path(&output.join("Level.sav")).unwrap();
+    let guilds = get_guilds(&anonymized, GameVersion::V0_1_3).unwrap();
+    let group = &guilds[0].1;
+    assert_eq!(group.AdminPlayerUId, remap_uid(&original.AdminPlayerUId));
+    assert!(group
+        .GuildPlayerInfo
+        .iter()
+        .any(|player_info| player_info.PlayerUId == remap_uid(&player_uid)));
+    assert_eq!(
+        get_character_key_ids(&get_character_save_parameter_map(&anonymized)[0]),
+        Some((remap_uid(&player_uid), instance_id))
+    );
+    assert!(output
+        .join("Players")
+        .join(fixtures::player_sav_name(&remap_uid(&player_uid)))
+        .exists());
+    assert_eq!(remap_uid(&Uuid::nil()), Uuid::nil());
+    assert_eq!(
+        remap_uid(&Uuid::from_u128(0xcbc6d68d_0000_0000_0000_000000000000)).as_bytes()[4..],
+        [0; 12]
+    );
+}
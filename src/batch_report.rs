@@ -0,0 +1,228 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::sav::{describe_compression_byte, read_sav_prefix, read_uncompressed_length};
+
+/// Length of the .sav prefix: two u32 lengths, the `PlZ` magic and the compression type.
+const SAV_PREFIX_LEN: u64 = 4 + 4 + 3 + 1;
+
+/// Compression type and sizes of the Level.sav of a world.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct WorldSizes {
+    /// The byte as written, see [`CompressionType`](crate::sav::CompressionType)
+    pub compression_type: u8,
+    /// Length of the GVAS data
+    pub decompressed_size: usize,
+    /// Length of the file after the prefix
+    pub compressed_size: u64,
+    /// `decompressed_size / compressed_size`, or `None` for an empty file body
+    pub ratio: Option<f64>,
+}
+
+pub struct BatchReportRow {
+    /// Name of the world directory
+    pub world: String,
+    pub sizes: anyhow::Result<WorldSizes>,
+}
+
+/// Format of the printed batch report.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchReportFormat {
+    /// Aligned columns
+    Table,
+    /// Comma-separated values with a header row
+    Csv,
+}
+
+/// Reads the compression type and sizes of a Level.sav without parsing its GVAS data. The
+/// decompressed size is the recorded one, or that of the decompressed data when the writing tool
+/// zeroed it.
+pub fn read_world_sizes(level_sav_path: &Path) -> anyhow::Result<WorldSizes> {
+    let open = || {
+        std::fs::File::open(level_sav_path)
+            .with_context(|| format!("Failed to open {}", level_sav_path.display()))
+    };
+    let file = open()?;
+    let file_length = file
+        .metadata()
+        .with_context(|| format!("Failed to read {}", level_sav_path.display()))?
+        .len();
+    let prefix = read_sav_prefix(&mut std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to read {}", level_sav_path.display()))?;
+    let decompressed_size = read_uncompressed_length(open()?)
+        .with_context(|| format!("Failed to read {}", level_sav_path.display()))?;
+    let compressed_size = file_length.saturating_sub(SAV_PREFIX_LEN);
+    Ok(WorldSizes {
+        compression_type: prefix.compression_type,
+        decompressed_size,
+        compressed_size,
+        ratio: (compressed_size != 0).then(|| decompressed_size as f64 / compressed_size as f64),
+    })
+}
+
+/// Returns `dir` and its subdirectories that contain a Level.sav, sorted by path.
+pub fn find_world_dirs(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut world_dirs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .into_iter()
+        .filter(|path| path.join("Level.sav").is_file())
+        .collect();
+    if dir.join("Level.sav").is_file() {
+        world_dirs.push(dir.to_path_buf());
+    }
+    world_dirs.sort();
+    Ok(world_dirs)
+}
+
+/// Reads the sizes of every world in `dir`. A world whose Level.sav cannot be read gets a row
+/// with the error instead of failing the report.
+pub fn batch_report(dir: &Path) -> anyhow::Result<Vec<BatchReportRow>> {
+    Ok(find_world_dirs(dir)?
+        .into_iter()
+        .map(|world_dir| BatchReportRow {
+            world: world_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| world_dir.display().to_string()),
+            sizes: read_world_sizes(&world_dir.join("Level.sav")),
+        })
+        .collect())
+}
+
+pub fn write_batch_report<W: Write>(
+    writer: &mut W,
+    rows: &[BatchReportRow],
+    format: BatchReportFormat,
+) -> anyhow::Result<()> {
+    match format {
+        BatchReportFormat::Table => {
+            let world_width = rows
+                .iter()
+                .map(|row| row.world.chars().count())
+                .chain(["World".len()])
+                .max()
+                .unwrap_or_default();
+            writeln!(
+                writer,
+                "{:<world_width$}  {:>17}  {:>12}  {:>10}  {:>5}",
+                "World", "Compression", "Decompressed", "Compressed", "Ratio"
+            )?;
+            rows.iter().try_for_each(|row| match &row.sizes {
+                Ok(sizes) => writeln!(
+                    writer,
+                    "{:<world_width$}  {:>17}  {:>12}  {:>10}  {:>5}",
+                    row.world,
+                    describe_compression_byte(sizes.compression_type),
+                    sizes.decompressed_size,
+                    sizes.compressed_size,
+                    sizes
+                        .ratio
+                        .map(|ratio| format!("{:.2}", ratio))
+                        .unwrap_or_else(|| "-".to_string())
+                ),
+                Err(error) => writeln!(writer, "{:<world_width$}  error: {:#}", row.world, error),
+            })?;
+        }
+        BatchReportFormat::Csv => {
+            writeln!(
+                writer,
+                "world,compression_type,decompressed_size,compressed_size,ratio,error"
+            )?;
+            rows.iter().try_for_each(|row| match &row.sizes {
+                Ok(sizes) => writeln!(
+                    writer,
+                    "{},{:#x},{},{},{},",
+                    csv_field(&row.world),
+                    sizes.compression_type,
+                    sizes.decompressed_size,
+                    sizes.compressed_size,
+                    sizes
+                        .ratio
+                        .map(|ratio| format!("{:.4}", ratio))
+                        .unwrap_or_default()
+                ),
+                Err(error) => writeln!(
+                    writer,
+                    "{},,,,,{}",
+                    csv_field(&row.world),
+                    csv_field(&format!("{:#}", error))
+                ),
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// Quotes a CSV field when it contains a comma, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[test]
+pub fn test_read_world_sizes() {
+    use std::io::Read;
+
+    let data = std::fs::read("assets/Level.sav").unwrap();
+    let mut decompressed = Vec::new();
+    flate2::read::ZlibDecoder::new(flate2::read::ZlibDecoder::new(&data[12..]))
+        .read_to_end(&mut decompressed)
+        .unwrap();
+
+    let sizes = read_world_sizes(Path::new("assets/Level.sav")).unwrap();
+    assert_eq!(sizes.compression_type, 0x32);
+    assert_eq!(sizes.decompressed_size, decompressed.len());
+    assert_eq!(sizes.compressed_size, data.len() as u64 - 12);
+    assert_eq!(
+        sizes.ratio,
+        Some(decompressed.len() as f64 / (data.len() - 12) as f64)
+    );
+}
+
+#[test]
+pub fn test_batch_report() {
+    use crate::fixtures;
+
+    let dir = fixtures::temp_dir("batch_report");
+    std::fs::create_dir_all(dir.join("world_a")).unwrap();
+    std::fs::copy("assets/Level.sav", dir.join("world_a").join("Level.sav")).unwrap();
+    std::fs::create_dir_all(dir.join("world,b")).unwrap();
+    std::fs::write(dir.join("world,b").join("Level.sav"), b"tiny").unwrap();
+    std::fs::create_dir_all(dir.join("not_a_world")).unwrap();
+
+    let rows = batch_report(&dir).unwrap();
+    assert_eq!(
+        rows.iter()
+            .map(|row| row.world.as_str())
+            .collect::<Vec<_>>(),
+        ["world,b", "world_a"]
+    );
+    assert!(rows[0].sizes.is_err());
+
+    let mut csv = Vec::new();
+    write_batch_report(&mut csv, &rows, BatchReportFormat::Csv).unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[1].starts_with("\"world,b\",,,,,"));
+    assert!(lines[2].starts_with("world_a,0x32,"));
+
+    let mut table = Vec::new();
+    write_batch_report(&mut table, &rows, BatchReportFormat::Table).unwrap();
+    let table = String::from_utf8(table).unwrap();
+    assert!(table.starts_with("World          Compression"));
+    assert!(table.contains("world_a  DoubleZlib (0x32)"));
+    assert!(table.contains("world,b  error: "));
+}
@@ -0,0 +1,60 @@
+/// Default of --max-open-files. Well under the common limit of 1024 file handles per process.
+pub const DEFAULT_MAX_OPEN_FILES: usize = 64;
+
+/// Runs `read` on every name, concurrently but on at most `max_open_files` (at least one) names
+/// at a time, so reading a directory of thousands of player saves never holds more handles than
+/// that. Names are read in chunks, and the results are in the order of `names`.
+pub fn read_bounded<T: Send>(
+    names: &[String],
+    max_open_files: usize,
+    read: impl Fn(&str) -> T + Sync,
+) -> Vec<T> {
+    let read = &read;
+    names
+        .chunks(max_open_files.max(1))
+        .flat_map(|chunk| {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = chunk
+                    .iter()
+                    .map(|name| scope.spawn(move || read(name)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| {
+                        handle
+                            .join()
+                            .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect()
+}
+
+#[test]
+pub fn test_read_bounded_respects_max_open_files() {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    // A directory of many player saves, where each read holds a handle for a moment
+    let names: Vec<String> = (0..200).map(|i| format!("{:032X}.sav", i)).collect();
+    let open = AtomicUsize::new(0);
+    let max_open = AtomicUsize::new(0);
+    let read = |name: &str| {
+        let now_open = open.fetch_add(1, Ordering::SeqCst) + 1;
+        max_open.fetch_max(now_open, Ordering::SeqCst);
+        std::thread::sleep(Duration::from_millis(1));
+        open.fetch_sub(1, Ordering::SeqCst);
+        name.to_string()
+    };
+
+    assert_eq!(read_bounded(&names, 8, read), names);
+    assert!(max_open.load(Ordering::SeqCst) <= 8);
+    assert_eq!(open.load(Ordering::SeqCst), 0);
+
+    max_open.store(0, Ordering::SeqCst);
+    assert_eq!(read_bounded(&names, 0, read), names);
+    assert_eq!(max_open.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,130 @@
+use anyhow::Context;
+use uuid::Uuid;
+
+use crate::{
+    character_save_parameter::read_raw_character_save_parameter,
+    patch::{apply_patch, Patch, PatchOp},
+    sav::{get_raw_data, iter_character_entries, PalSave},
+};
+
+/// Returns the `RawData` of the character `instance_id`, byte for byte, in the format of
+/// `assets/character_save_parameter.bin`.
+pub fn export_character_raw_data(
+    level_save: &PalSave,
+    instance_id: Uuid,
+) -> anyhow::Result<Vec<u8>> {
+    let entry = iter_character_entries(level_save)
+        .filter_map(|entry| entry.ok())
+        .find(|(_, id, _)| *id == instance_id)
+        .map(|(_, _, entry)| entry)
+        .with_context(|| format!("Character {} not found", instance_id))?;
+    Ok(get_raw_data(entry)?.into_owned())
+}
+
+/// Puts `raw_data` in the character `instance_id`, or appends a character of `player_uid` (nil
+/// for a pal) when there is none. `raw_data` must decode as a character of this save first.
+/// Returns whether an existing character was replaced.
+pub fn import_character_raw_data(
+    level_save: &mut PalSave,
+    instance_id: Uuid,
+    player_uid: Uuid,
+    raw_data: Vec<u8>,
+) -> anyhow::Result<bool> {
+    let (patch, exists) = import_character_patch(level_save, instance_id, player_uid, raw_data)?;
+    apply_patch(level_save, &patch)?;
+    Ok(exists)
+}
+
+/// The change of [`import_character_raw_data`], without making it, and whether it replaces an
+/// existing character.
+pub fn import_character_patch(
+    level_save: &PalSave,
+    instance_id: Uuid,
+    player_uid: Uuid,
+    raw_data: Vec<u8>,
+) -> anyhow::Result<(Patch, bool)> {
+    read_raw_character_save_parameter(&level_save.save.header)(&raw_data)
+        .context("RawData is not a character of this save")?;
+    let exists = iter_character_entries(level_save)
+        .filter_map(|entry| entry.ok())
+        .any(|(_, id, _)| id == instance_id);
+    let op = if exists {
+        PatchOp::SetCharacterRawData {
+            instance_id,
+            raw_data,
+        }
+    } else {
+        PatchOp::AppendCharacterSave {
+            player_uid,
+            instance_id,
+            raw_data,
+        }
+    };
+    Ok((Patch::new(vec![op]), exists))
+}
+
+#[test]
+pub fn test_export_import_character_raw_data() {
+    use crate::{
+        fixtures,
+        sav::{get_character_key_ids, get_character_save_parameter_map},
+    };
+
+    let player_uid = Uuid::from_u128(1);
+    let instance_id = Uuid::from_u128(2);
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    fixtures::add_character_save(&mut level_save, player_uid, instance_id);
+    assert!(export_character_raw_data(&level_save, Uuid::from_u128(3)).is_err());
+    let raw_data = export_character_raw_data(&level_save, instance_id).unwrap();
+    assert_eq!(
+        raw_data,
+        get_raw_data(&get_character_save_parameter_map(&level_save)[0])
+            .unwrap()
+            .as_ref()
+    );
+
+    // Into a fresh map entry
+    let mut fresh_level_save = fixtures::read_level_save();
+    fixtures::add_character_save_parameter_map(&mut fresh_level_save);
+    let replaced = import_character_raw_data(
+        &mut fresh_level_save,
+        instance_id,
+        player_uid,
+        raw_data.clone(),
+    )
+    .unwrap();
+    assert!(!replaced);
+    assert_eq!(
+        get_character_key_ids(&get_character_save_parameter_map(&fresh_level_save)[0]),
+        Some((player_uid, instance_id))
+    );
+    assert_eq!(
+        export_character_raw_data(&fresh_level_save, instance_id).unwrap(),
+        raw_data
+    );
+    assert_eq!(
+        fixtures::character_nickname(&fresh_level_save, 0),
+        fixtures::character_nickname(&level_save, 0)
+    );
+
+    // Over an existing character
+    fixtures::set_character_group_id(&mut fresh_level_save, 0, Uuid::from_u128(0x200));
+    assert!(import_character_raw_data(
+        &mut fresh_level_save,
+        instance_id,
+        player_uid,
+        raw_data.clone()
+    )
+    .unwrap());
+    assert_eq!(get_character_save_parameter_map(&fresh_level_save).len(), 1);
+    assert_eq!(
+        fixtures::character_group_id(&fresh_level_save, 0),
+        fixtures::character_group_id(&level_save, 0)
+    );
+
+    assert!(
+        import_character_raw_data(&mut fresh_level_save, instance_id, player_uid, vec![0; 8])
+            .is_err()
+    );
+}
@@ -0,0 +1,73 @@
+use uuid::Uuid;
+
+use crate::{
+    patch::{Patch, PatchOp},
+    sav::{iter_character_entries, PalSave},
+};
+
+/// The change that moves the character `instance_id` to the key `new_instance_id`, for a player
+/// whose `IndividualId.InstanceId` was regenerated. Fails when there is no such character or
+/// `new_instance_id` is already taken.
+pub fn rekey_character_patch(
+    level_save: &PalSave,
+    instance_id: Uuid,
+    new_instance_id: Uuid,
+) -> anyhow::Result<Patch> {
+    if instance_id == new_instance_id {
+        return Err(anyhow::anyhow!(
+            "Character {} already has that InstanceId",
+            instance_id
+        ));
+    }
+    let instance_ids: Vec<Uuid> = iter_character_entries(level_save)
+        .filter_map(|entry| entry.ok())
+        .map(|(_, id, _)| id)
+        .collect();
+    if !instance_ids.contains(&instance_id) {
+        return Err(anyhow::anyhow!("Character {} not found", instance_id));
+    }
+    if instance_ids.contains(&new_instance_id) {
+        return Err(anyhow::anyhow!(
+            "Character {} already exists",
+            new_instance_id
+        ));
+    }
+    Ok(Patch::new(vec![PatchOp::RekeyCharacter {
+        instance_id,
+        new_instance_id,
+    }]))
+}
+
+#[test]
+pub fn test_rekey_character() {
+    use crate::{character_bin::export_character_raw_data, fixtures, patch::apply_patch};
+
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(1), Uuid::from_u128(0x11));
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(2), Uuid::from_u128(0x12));
+    let raw_data = export_character_raw_data(&level_save, Uuid::from_u128(0x11)).unwrap();
+
+    // The new InstanceId must be free
+    assert!(
+        rekey_character_patch(&level_save, Uuid::from_u128(0x11), Uuid::from_u128(0x12)).is_err()
+    );
+    assert!(
+        rekey_character_patch(&level_save, Uuid::from_u128(0x99), Uuid::from_u128(0x21)).is_err()
+    );
+
+    let patch =
+        rekey_character_patch(&level_save, Uuid::from_u128(0x11), Uuid::from_u128(0x21)).unwrap();
+    apply_patch(&mut level_save, &patch).unwrap();
+    assert_eq!(
+        export_character_raw_data(&level_save, Uuid::from_u128(0x21)).unwrap(),
+        raw_data
+    );
+    assert!(export_character_raw_data(&level_save, Uuid::from_u128(0x11)).is_err());
+    // The PlayerUId of the key is kept
+    let (player_uid, _, _) = iter_character_entries(&level_save)
+        .filter_map(|entry| entry.ok())
+        .find(|(_, id, _)| *id == Uuid::from_u128(0x21))
+        .unwrap();
+    assert_eq!(player_uid, Some(Uuid::from_u128(1)));
+}
@@ -7,7 +7,10 @@ use indexmap::IndexMap;
 use uuid::Uuid;
 use winnow::Parser;
 
-use crate::group_guild::{read_uuid, stream, write_uuid};
+use crate::{
+    group_guild::{read_uuid, stream, write_uuid},
+    hex_dump::hex_dump,
+};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CharacterSaveParameter {
@@ -15,24 +18,71 @@ pub struct CharacterSaveParameter {
     pub group_id: Uuid,
 }
 
+/// Length of what follows the properties of character `RawData`: an unknown u32 and the group id.
+const TRAILER_LEN: usize = 4 + 16;
+
+/// Parses character `RawData`. The properties must be followed by exactly [`TRAILER_LEN`] bytes,
+/// so a change of the property list terminator or framing fails instead of shifting the group
+/// id. On failure the error shows the bytes around where parsing stopped.
 pub fn read_raw_character_save_parameter<'a>(
     header: &'a uesave::Header,
-) -> impl Fn(&'a [u8]) -> CharacterSaveParameter {
+) -> impl Fn(&'a [u8]) -> anyhow::Result<CharacterSaveParameter> {
     move |bytes: &[u8]| {
-        let mut reader = Cursor::new(bytes);
+        read_character_save_parameter(header, bytes, true)
+            .map(|(character_save_parameter, _)| character_save_parameter)
+    }
+}
+
+/// Like [`read_raw_character_save_parameter`], but bytes after the trailer are left unread and
+/// the number of bytes the character took is returned with it, to compare against the length
+/// of the `RawData`.
+#[cfg(test)]
+pub fn read_raw_character_save_parameter_consumed<'a>(
+    header: &'a uesave::Header,
+) -> impl Fn(&'a [u8]) -> anyhow::Result<(CharacterSaveParameter, usize)> {
+    move |bytes: &[u8]| read_character_save_parameter(header, bytes, false)
+}
+
+fn read_character_save_parameter(
+    header: &uesave::Header,
+    bytes: &[u8],
+    exact: bool,
+) -> anyhow::Result<(CharacterSaveParameter, usize)> {
+    let mut reader = Cursor::new(bytes);
+    let result = (|| -> anyhow::Result<CharacterSaveParameter> {
         let properties = uesave::Context::run(&mut reader, |reader| {
-            reader.header(&header, uesave::read_properties_until_none)
-        })
-        .unwrap();
-        let _unknown = reader.read_u32::<byteorder::LittleEndian>().unwrap();
-        let mut bytes = [0; 16];
-        reader.read_exact(&mut bytes).unwrap();
-        let uuid = read_uuid.parse_next(&mut stream(&bytes)).unwrap();
-        CharacterSaveParameter {
-            properties,
-            group_id: uuid,
+            reader.header(header, uesave::read_properties_until_none)
+        })?;
+        let remaining = bytes.len() - reader.position() as usize;
+        if remaining < TRAILER_LEN || (exact && remaining != TRAILER_LEN) {
+            return Err(anyhow::anyhow!(
+                "Expected {} bytes (unknown u32 and group id) after the properties, found {}",
+                TRAILER_LEN,
+                remaining
+            ));
         }
-    }
+        let _unknown = reader.read_u32::<byteorder::LittleEndian>()?;
+        let mut uuid = [0; 16];
+        reader.read_exact(&mut uuid)?;
+        let group_id = read_uuid
+            .parse_next(&mut stream(&uuid))
+            .map_err(|e| anyhow::anyhow!("{}", e))?;
+        Ok(CharacterSaveParameter {
+            properties,
+            group_id,
+        })
+    })();
+    let offset = reader.position() as usize;
+    result
+        .map(|character_save_parameter| (character_save_parameter, offset))
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to parse character RawData at byte {}: {:#}\n{}",
+                offset,
+                e,
+                hex_dump(bytes, offset)
+            )
+        })
 }
 
 pub fn write_raw_character_save_parameter<'a>(
@@ -55,11 +105,10 @@ pub fn write_raw_character_save_parameter<'a>(
     }
 }
 
-#[test]
-pub fn test_read_write_character_save_parameter() {
-    use std::io::Read;
-
-    let header = uesave::Header {
+/// A UE5 save header to parse the character `RawData` fixture with.
+#[cfg(test)]
+fn test_header() -> uesave::Header {
+    uesave::Header {
         magic: Default::default(),
         save_game_version: Default::default(),
         package_version: uesave::PackageVersion::Old(0),
@@ -70,7 +119,14 @@ pub fn test_read_write_character_save_parameter() {
         engine_version: Default::default(),
         custom_format_version: Default::default(),
         custom_format: Default::default(),
-    };
+    }
+}
+
+#[test]
+pub fn test_read_write_character_save_parameter() {
+    use std::io::Read;
+
+    let header = test_header();
 
     let file = std::fs::File::open("assets/character_save_parameter.bin").unwrap();
     let mut reader = std::io::BufReader::new(file);
@@ -78,7 +134,51 @@ pub fn test_read_write_character_save_parameter() {
     reader.read_to_end(&mut data).unwrap();
 
     let character_save_parameter =
-        read_raw_character_save_parameter(&header)(&mut stream(data.as_ref()));
+        read_raw_character_save_parameter(&header)(&mut stream(data.as_ref())).unwrap();
     let data2 = write_raw_character_save_parameter(&header)(&character_save_parameter);
     assert_eq!(data, data2.as_slice());
 }
+
+#[test]
+pub fn test_read_character_save_parameter_checks_trailer_length() {
+    let header = test_header();
+    let data = std::fs::read("assets/character_save_parameter.bin").unwrap();
+
+    // Unexpected trailing bytes
+    let mut longer = data.clone();
+    longer.extend_from_slice(&[0xff; 4]);
+    let error = read_raw_character_save_parameter(&header)(&longer).unwrap_err();
+    let message = format!("{:#}", error);
+    assert!(
+        message.contains(
+            "Expected 20 bytes (unknown u32 and group id) after the properties, found 24"
+        ),
+        "{}",
+        message
+    );
+    assert!(message.contains(&format!("at byte {}", data.len() - TRAILER_LEN)));
+
+    // A group id cut short
+    let shorter = &data[..data.len() - 4];
+    let message = format!(
+        "{:#}",
+        read_raw_character_save_parameter(&header)(shorter).unwrap_err()
+    );
+    assert!(message.contains("found 16"), "{}", message);
+}
+
+#[test]
+pub fn test_read_character_save_parameter_consumed() {
+    let header = test_header();
+    let data = std::fs::read("assets/character_save_parameter.bin").unwrap();
+
+    let (_, consumed) = read_raw_character_save_parameter_consumed(&header)(&data).unwrap();
+    assert_eq!(consumed, data.len());
+
+    // Trailing bytes are left for the caller
+    let mut longer = data.clone();
+    longer.extend_from_slice(&[0xff; 4]);
+    let (_, consumed) = read_raw_character_save_parameter_consumed(&header)(&longer).unwrap();
+    assert_eq!(consumed, data.len());
+    assert!(read_raw_character_save_parameter_consumed(&header)(&data[..data.len() - 4]).is_err());
+}
@@ -0,0 +1,78 @@
+use std::{ffi::OsString, io::IsTerminal};
+
+/// ANSI colors of text reports. JSON output is never colored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Colors {
+    enabled: bool,
+}
+
+impl Colors {
+    /// Colors when stdout is a terminal, unless `--no-color` is passed or `NO_COLOR` is set.
+    pub fn detect(no_color: bool) -> Colors {
+        Colors::detect_from(
+            no_color,
+            std::env::var_os("NO_COLOR"),
+            std::io::stdout().is_terminal(),
+        )
+    }
+
+    /// [`Colors::detect`] with the environment passed in. Per https://no-color.org, an empty
+    /// `NO_COLOR` does not disable colors.
+    pub fn detect_from(
+        no_color: bool,
+        no_color_env: Option<OsString>,
+        is_terminal: bool,
+    ) -> Colors {
+        let no_color_env = no_color_env.is_some_and(|value| !value.is_empty());
+        Colors {
+            enabled: is_terminal && !no_color && !no_color_env,
+        }
+    }
+
+    fn paint(&self, code: &str, text: impl std::fmt::Display) -> String {
+        if self.enabled {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Bold, for section titles
+    pub fn heading(&self, text: impl std::fmt::Display) -> String {
+        self.paint("1", text)
+    }
+
+    /// Yellow, for findings that need attention
+    pub fn warning(&self, text: impl std::fmt::Display) -> String {
+        self.paint("33", text)
+    }
+
+    /// Green, for changes that were made
+    pub fn changed(&self, text: impl std::fmt::Display) -> String {
+        self.paint("32", text)
+    }
+}
+
+#[test]
+pub fn test_colors_detect() {
+    let colored = Colors::detect_from(false, None, true);
+    assert_eq!(colored.heading("Guilds"), "\x1b[1mGuilds\x1b[0m");
+
+    // --no-color, NO_COLOR and piped stdout
+    [
+        Colors::detect_from(true, None, true),
+        Colors::detect_from(false, Some("1".into()), true),
+        Colors::detect_from(false, None, false),
+    ]
+    .iter()
+    .for_each(|colors| {
+        let text = [
+            colors.heading("Guilds"),
+            colors.warning("Unparsed guilds"),
+            colors.changed("Fixed"),
+        ]
+        .concat();
+        assert!(!text.contains('\x1b'));
+    });
+    assert_eq!(Colors::detect_from(false, Some("".into()), true), colored);
+}
@@ -0,0 +1,68 @@
+use std::io::{BufRead, IsTerminal};
+
+/// Asks before a command changes save files in a way that cannot be undone. Every such command
+/// goes through this, so `--assume-yes` and piped answers behave the same everywhere.
+pub struct Confirm {
+    assume_yes: bool,
+    /// Whether answers come from a person at a terminal
+    interactive: bool,
+    answers: Box<dyn BufRead>,
+}
+
+impl Confirm {
+    /// Reads answers from stdin.
+    pub fn stdin(assume_yes: bool) -> Confirm {
+        let stdin = std::io::stdin();
+        Confirm {
+            assume_yes,
+            interactive: stdin.is_terminal(),
+            answers: Box::new(stdin.lock()),
+        }
+    }
+
+    /// Reads answers from `answers` as if they were piped to stdin.
+    #[cfg(test)]
+    pub fn with_answers(assume_yes: bool, answers: impl BufRead + 'static) -> Confirm {
+        Confirm {
+            assume_yes,
+            interactive: false,
+            answers: Box::new(answers),
+        }
+    }
+
+    /// Prints `action` and how many `entities` it affects, then asks whether to proceed.
+    /// Without an answer (e.g. stdin is closed) the action is not taken.
+    pub fn ask(&mut self, action: &str, count: usize, entities: &str) -> anyhow::Result<bool> {
+        eprintln!("{} ({} {})", action, count, entities);
+        if self.assume_yes {
+            eprintln!("Proceeding because of --assume-yes");
+            return Ok(true);
+        }
+        eprint!("Proceed? [y/N] ");
+        let mut answer = String::new();
+        let read = self.answers.read_line(&mut answer)?;
+        if read == 0 {
+            eprintln!();
+            if !self.interactive {
+                eprintln!("No answer on stdin. Pass --assume-yes to proceed without a prompt");
+            }
+            return Ok(false);
+        }
+        Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+    }
+}
+
+#[test]
+pub fn test_confirm_answers() {
+    let ask = |assume_yes: bool, answers: &'static str| {
+        Confirm::with_answers(assume_yes, answers.as_bytes())
+            .ask("Delete", 2, "guilds")
+            .unwrap()
+    };
+    assert!(ask(false, "y\n"));
+    assert!(ask(false, "YES\n"));
+    assert!(!ask(false, "n\n"));
+    assert!(!ask(false, "\n"));
+    assert!(!ask(false, ""));
+    assert!(ask(true, ""));
+}
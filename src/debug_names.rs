@@ -0,0 +1,91 @@
+use std::io::Write;
+
+use uuid::Uuid;
+use winnow::Parser;
+
+use crate::{
+    group_guild::{read_group_guild_names_for, stream, GameVersion, RawFString},
+    sav::{get_group_save_data_map, get_raw_data, is_group_type_guild, PalSave},
+};
+
+/// Writes every guild and member name of `level_save` with its encoding branch and its bytes in
+/// hex, length included. Names that would not be written back to the same bytes are marked.
+/// Guilds whose names cannot be read are listed with the error.
+pub fn write_debug_names<W: Write>(
+    writer: &mut W,
+    level_save: &PalSave,
+    version: GameVersion,
+) -> anyhow::Result<()> {
+    get_group_save_data_map(level_save)
+        .iter()
+        .filter(|entry| is_group_type_guild(entry))
+        .try_for_each(|entry| -> anyhow::Result<()> {
+            let guild_id = match entry.key {
+                uesave::PropertyValue::Struct(uesave::StructValue::Guid(guild_id)) => guild_id,
+                _ => Uuid::nil(),
+            };
+            writeln!(writer, "Guild {}", guild_id)?;
+            let names = get_raw_data(entry).and_then(|data| {
+                read_group_guild_names_for(version)
+                    .parse_next(&mut stream(&data))
+                    .map_err(|e| anyhow::anyhow!("{}", e))
+            });
+            match names {
+                Ok(names) => {
+                    writeln!(writer, "  Name: {}", format_raw_fstring(&names.GuildName))?;
+                    names
+                        .PlayerNames
+                        .iter()
+                        .try_for_each(|(player_uid, player_name)| {
+                            writeln!(
+                                writer,
+                                "  Player {}: {}",
+                                player_uid,
+                                format_raw_fstring(player_name)
+                            )
+                        })?;
+                }
+                Err(error) => writeln!(writer, "  Names unreadable: {:#}", error)?,
+            }
+            Ok(())
+        })
+}
+
+fn format_raw_fstring(name: &RawFString) -> String {
+    let hex: Vec<String> = name
+        .bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!(
+        "{:?} ({}): {}{}",
+        name.value,
+        name.encoding.label(),
+        hex.join(" "),
+        if name.round_trips() {
+            ""
+        } else {
+            " [rewritten differently]"
+        }
+    )
+}
+
+#[test]
+pub fn test_write_debug_names() {
+    use crate::fixtures;
+
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_guild(
+        &mut level_save,
+        Uuid::from_u128(0x100),
+        &fixtures::read_guild(),
+    );
+    let mut output = Vec::new();
+    write_debug_names(&mut output, &level_save, GameVersion::V0_1_3).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains(&format!("Guild {}\n", Uuid::from_u128(0x100))));
+    // 3 UTF-16 units and the terminator, as a negative length
+    assert!(output.contains("  Name: \"해적단\" (unicode): fc ff ff ff 74 d5 01 c8 e8 b2 00 00\n"));
+    assert!(output.contains(": \"Suffolk\" (non-unicode): 08 00 00 00 53 75 66 66 6f 6c 6b 00\n"));
+}
@@ -0,0 +1,105 @@
+use std::collections::BTreeSet;
+
+use indexmap::IndexMap;
+
+use crate::sav::{PalSave, BASE_SAVE_TYPES};
+
+/// Which registered struct paths a save needed. uesave looks up the struct type of every map
+/// key and value that is a struct, at paths like `.worldSaveData.GroupSaveDataMap.Key`.
+#[derive(Debug, PartialEq)]
+pub struct TypesDiagnosis {
+    /// Registered paths found in the save
+    pub used: Vec<String>,
+    /// Struct paths found in the save without a registration, read with uesave's default
+    pub unregistered: Vec<String>,
+    /// Registered paths not found in the save
+    pub unused: Vec<String>,
+}
+
+pub fn diagnose_types(
+    pal_save: &PalSave,
+    extra_types: &[(String, uesave::StructType)],
+) -> TypesDiagnosis {
+    let registered: BTreeSet<String> = BASE_SAVE_TYPES
+        .iter()
+        .map(|(path, _)| path.to_string())
+        .chain(extra_types.iter().map(|(path, _)| path.clone()))
+        .collect();
+    let mut found = BTreeSet::new();
+    walk_properties("", &pal_save.save.root.properties, &mut found);
+
+    TypesDiagnosis {
+        used: found.intersection(&registered).cloned().collect(),
+        unregistered: found.difference(&registered).cloned().collect(),
+        unused: registered.difference(&found).cloned().collect(),
+    }
+}
+
+pub fn print_types_diagnosis(diagnosis: &TypesDiagnosis) {
+    info!("Registered struct types used by this save:");
+    diagnosis.used.iter().for_each(|path| info!("- {}", path));
+    info!("Struct types not registered (read with uesave's default):");
+    diagnosis
+        .unregistered
+        .iter()
+        .for_each(|path| info!("- {}", path));
+    info!("Registered struct types not found in this save:");
+    diagnosis.unused.iter().for_each(|path| info!("- {}", path));
+}
+
+fn walk_properties(
+    path: &str,
+    properties: &IndexMap<String, uesave::Property>,
+    found: &mut BTreeSet<String>,
+) {
+    properties
+        .iter()
+        .for_each(|(name, property)| walk_property(&format!("{}.{}", path, name), property, found));
+}
+
+fn walk_property(path: &str, property: &uesave::Property, found: &mut BTreeSet<String>) {
+    match property {
+        uesave::Property::Struct { value, .. } => walk_struct_value(path, value, found),
+        uesave::Property::Map { value, .. } => value.iter().for_each(|entry| {
+            if let uesave::PropertyValue::Struct(key) = &entry.key {
+                found.insert(format!("{}.Key", path));
+                walk_struct_value(path, key, found);
+            }
+            if let uesave::PropertyValue::Struct(value) = &entry.value {
+                found.insert(format!("{}.Value", path));
+                walk_struct_value(path, value, found);
+            }
+        }),
+        uesave::Property::Array {
+            value: uesave::ValueArray::Struct { value, .. },
+            ..
+        } => value
+            .iter()
+            .for_each(|value| walk_struct_value(path, value, found)),
+        _ => {}
+    }
+}
+
+/// Nested paths continue from the map or array name, without `.Key`/`.Value` or an index.
+fn walk_struct_value(path: &str, value: &uesave::StructValue, found: &mut BTreeSet<String>) {
+    if let uesave::StructValue::Struct(properties) = value {
+        walk_properties(path, properties, found);
+    }
+}
+
+#[test]
+pub fn test_diagnose_types() {
+    use crate::fixtures;
+
+    let level_save = fixtures::read_level_save();
+    let diagnosis = diagnose_types(&level_save, &[]);
+    assert!(diagnosis
+        .used
+        .contains(&".worldSaveData.GroupSaveDataMap.Key".to_string()));
+    assert!(diagnosis
+        .used
+        .contains(&".worldSaveData.GroupSaveDataMap.Value".to_string()));
+    assert!(diagnosis
+        .unused
+        .contains(&".worldSaveData.CharacterSaveParameterMap.Key".to_string()));
+}
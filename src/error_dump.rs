@@ -0,0 +1,131 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{
+    header_info::{read_header_info, write_header_info, write_sav_prefix},
+    sav::{read_sav_prefix, read_save_file_path_with_extra_types},
+};
+
+/// Writes a diagnostics bundle for a failed run to `dir`, to attach to a bug report:
+/// - `error.txt`: the error and its causes, with the bytes around where parsing stopped when a
+///   RawData parse failed
+/// - `version.txt`: the version of the tool
+/// - `<file>.header.txt` for each of `save_paths`: its compression type, lengths and GVAS header,
+///   or as much of them as can still be read
+///
+/// Returns the paths of the written files.
+pub fn write_error_dump(
+    dir: &Path,
+    error: &anyhow::Error,
+    save_paths: &[PathBuf],
+    extra_types: &[(String, uesave::StructType)],
+) -> anyhow::Result<Vec<PathBuf>> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let write = |name: &str, contents: &[u8]| -> anyhow::Result<PathBuf> {
+        let path = dir.join(name);
+        std::fs::write(&path, contents)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    };
+
+    let mut paths = vec![
+        write("error.txt", format!("{:#}\n", error).as_bytes())?,
+        write(
+            "version.txt",
+            format!("{} {}\n", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")).as_bytes(),
+        )?,
+    ];
+    save_paths
+        .iter()
+        .filter(|save_path| save_path.exists())
+        .try_for_each(|save_path| -> anyhow::Result<()> {
+            let mut header = Vec::new();
+            write_save_header(&mut header, save_path, extra_types)?;
+            let file_name = save_path
+                .file_name()
+                .map(|file_name| file_name.to_string_lossy())
+                .unwrap_or_default();
+            paths.push(write(&format!("{}.header.txt", file_name), &header)?);
+            Ok(())
+        })?;
+    Ok(paths)
+}
+
+/// Writes the header info of the save at `path`, or only its prefix when the save cannot be read.
+fn write_save_header<W: Write>(
+    writer: &mut W,
+    path: &Path,
+    extra_types: &[(String, uesave::StructType)],
+) -> anyhow::Result<()> {
+    let pal_save = match read_save_file_path_with_extra_types(path, extra_types) {
+        Ok(pal_save) => pal_save,
+        Err(error) => {
+            let prefix = std::fs::File::open(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|mut file| read_sav_prefix(&mut file));
+            match prefix {
+                Ok(prefix) => write_sav_prefix(writer, &prefix)?,
+                Err(prefix_error) => writeln!(writer, "Prefix: unreadable: {:#}", prefix_error)?,
+            }
+            writeln!(writer, "Header: unreadable: {:#}", error)?;
+            return Ok(());
+        }
+    };
+    write_header_info(writer, &read_header_info(path, &pal_save)?)
+}
+
+#[test]
+pub fn test_write_error_dump() {
+    use crate::{fixtures, group_guild::GameVersion, sav::get_guilds};
+    use uuid::Uuid;
+
+    // A guild whose RawData stops parsing partway
+    let input = fixtures::temp_dir("error_dump_input");
+    let mut level_save = fixtures::read_level_save();
+    let mut corrupt_guild = std::fs::read("assets/guild_0.bin").unwrap();
+    corrupt_guild.truncate(100);
+    fixtures::add_guild_raw(&mut level_save, Uuid::from_u128(0x200), corrupt_guild);
+    let level_sav_path = input.join("Level.sav");
+    fixtures::write_save(&level_sav_path, &level_save);
+    // A save that does not decompress
+    let broken_sav_path = input.join("Broken.sav");
+    let mut broken_sav = std::fs::read(&level_sav_path).unwrap();
+    broken_sav.truncate(64);
+    std::fs::write(&broken_sav_path, broken_sav).unwrap();
+
+    let error = get_guilds(&level_save, GameVersion::V0_1_3).unwrap_err();
+    let dir = fixtures::temp_dir("error_dump");
+    let paths = write_error_dump(
+        &dir,
+        &error,
+        &[level_sav_path, broken_sav_path, input.join("Missing.sav")],
+        &[],
+    )
+    .unwrap();
+    assert_eq!(
+        paths,
+        [
+            "error.txt",
+            "version.txt",
+            "Level.sav.header.txt",
+            "Broken.sav.header.txt"
+        ]
+        .map(|name| dir.join(name))
+    );
+
+    let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+    let error_txt = read("error.txt");
+    assert!(error_txt.contains("Failed to parse guild RawData at byte"));
+    assert!(error_txt.contains("00000000  "));
+    assert!(read("version.txt").starts_with("palworld-guild-save-fix "));
+    let level_header = read("Level.sav.header.txt");
+    assert!(level_header.contains("Compression type: DoubleZlib (0x32)\n"));
+    assert!(level_header.contains("Save game version: 3\n"));
+    let broken_header = read("Broken.sav.header.txt");
+    assert!(broken_header.contains("Compression type: DoubleZlib (0x32)\n"));
+    assert!(broken_header.contains("Header: unreadable: "));
+}
@@ -0,0 +1,164 @@
+#[cfg(any(feature = "events", test))]
+use std::io::Write;
+use std::sync::Mutex;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+/// Progress of a command for a front-end to show as it happens. Written as one JSON object per
+/// line, e.g. `{"event":"guild_parsed","guild_id":"...","name":"...","member_count":2}`.
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    LevelRead,
+    PlayerSaveRead {
+        name: String,
+    },
+    /// A player save that timed out, could not be read with `--keep-going` or is not a player save
+    PlayerSaveSkipped {
+        name: String,
+        reason: String,
+    },
+    GuildParsed {
+        guild_id: Uuid,
+        name: String,
+        member_count: usize,
+    },
+    GuildUnparsed {
+        guild_id: Uuid,
+        error: String,
+    },
+    CharacterSaveAdded {
+        player_uid: Uuid,
+        instance_id: Uuid,
+    },
+    /// The last event. `error` is set when the command failed
+    Finished {
+        error: Option<String>,
+    },
+}
+
+pub trait ProgressSink: Send {
+    fn emit(&mut self, event: &Event) -> std::io::Result<()>;
+}
+
+/// Writes each event as a line of JSON and flushes it, so the reader sees it right away.
+#[cfg(any(feature = "events", test))]
+pub struct JsonLinesSink<W: Write + Send>(pub W);
+
+#[cfg(any(feature = "events", test))]
+impl<W: Write + Send> ProgressSink for JsonLinesSink<W> {
+    fn emit(&mut self, event: &Event) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.0, event)?;
+        self.0.write_all(b"\n")?;
+        self.0.flush()
+    }
+}
+
+static SINK: Mutex<Option<Box<dyn ProgressSink>>> = Mutex::new(None);
+
+/// Only `--events` sets a sink, so without the `events` feature nothing is ever sent.
+#[cfg(feature = "events")]
+pub fn set_sink(sink: Option<Box<dyn ProgressSink>>) {
+    *SINK.lock().unwrap() = sink;
+}
+
+/// Sends `event` to the sink `--events` connected, if any. A sink that fails, e.g. because the
+/// front-end went away, is dropped; the command goes on.
+pub fn emit(event: Event) {
+    let mut sink = SINK.lock().unwrap();
+    let Some(progress_sink) = sink.as_mut() else {
+        return;
+    };
+    if let Err(error) = progress_sink.emit(&event) {
+        info!(
+            "Failed to send progress event, no longer sending them: {}",
+            error
+        );
+        *sink = None;
+    }
+}
+
+/// Connects to the Unix socket a front-end listens on.
+#[cfg(all(feature = "events", unix))]
+pub fn connect(path: &std::path::Path) -> anyhow::Result<Box<dyn ProgressSink>> {
+    use anyhow::Context;
+
+    let stream = std::os::unix::net::UnixStream::connect(path)
+        .with_context(|| format!("Failed to connect to {}", path.display()))?;
+    Ok(Box::new(JsonLinesSink(stream)))
+}
+
+/// Opens the named pipe (`\\.\pipe\...`) a front-end created.
+#[cfg(all(feature = "events", windows))]
+pub fn connect(path: &std::path::Path) -> anyhow::Result<Box<dyn ProgressSink>> {
+    use anyhow::Context;
+
+    let pipe = std::fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .with_context(|| format!("Failed to connect to {}", path.display()))?;
+    Ok(Box::new(JsonLinesSink(pipe)))
+}
+
+#[test]
+pub fn test_json_lines_sink() {
+    let mut sink = JsonLinesSink(Vec::new());
+    sink.emit(&Event::PlayerSaveSkipped {
+        name: "0001.sav".to_string(),
+        reason: "timed out".to_string(),
+    })
+    .unwrap();
+    sink.emit(&Event::Finished {
+        error: Some("failed".to_string()),
+    })
+    .unwrap();
+    assert_eq!(
+        String::from_utf8(sink.0).unwrap(),
+        "{\"event\":\"player_save_skipped\",\"name\":\"0001.sav\",\"reason\":\"timed out\"}\n\
+         {\"event\":\"finished\",\"error\":\"failed\"}\n"
+    );
+}
+
+#[cfg(all(feature = "events", unix))]
+#[test]
+pub fn test_connect_sends_json_lines() {
+    use std::io::BufRead;
+
+    use crate::fixtures;
+
+    let dir = fixtures::temp_dir("events");
+    let socket_path = dir.join("events.sock");
+    let listener = std::os::unix::net::UnixListener::bind(&socket_path).unwrap();
+    let reader = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        std::io::BufReader::new(stream)
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(&line.unwrap()).unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    let mut sink = connect(&socket_path).unwrap();
+    sink.emit(&Event::GuildParsed {
+        guild_id: Uuid::from_u128(0x100),
+        name: "Guild".to_string(),
+        member_count: 2,
+    })
+    .unwrap();
+    sink.emit(&Event::Finished { error: None }).unwrap();
+    drop(sink);
+
+    assert_eq!(
+        reader.join().unwrap(),
+        vec![
+            serde_json::json!({
+                "event": "guild_parsed",
+                "guild_id": Uuid::from_u128(0x100).to_string(),
+                "name": "Guild",
+                "member_count": 2,
+            }),
+            serde_json::json!({"event": "finished", "error": null}),
+        ]
+    );
+    assert!(connect(&dir.join("missing.sock")).is_err());
+}
@@ -0,0 +1,103 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::group_guild::GroupGuildSave;
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PlayerMatch {
+    pub player_uid: Uuid,
+    pub player_name: String,
+    pub guild_id: Uuid,
+    pub guild_name: String,
+}
+
+/// Returns the guild members whose name contains `query`, ignoring case, or whose PlayerUId
+/// starts with `query` as hex digits (dashes are ignored, so both `cbc6d68d0000` and
+/// `cbc6d68d-0000` match). A player in several guilds is returned once per guild.
+pub fn find_players(groups: &[(Uuid, GroupGuildSave)], query: &str) -> Vec<PlayerMatch> {
+    let name_query = query.to_lowercase();
+    let uid_prefix: String = query.chars().filter(|c| *c != '-').collect();
+    let uid_prefix = (!uid_prefix.is_empty() && uid_prefix.chars().all(|c| c.is_ascii_hexdigit()))
+        .then(|| uid_prefix.to_lowercase());
+    groups
+        .iter()
+        .flat_map(|(guild_id, group)| {
+            group
+                .GuildPlayerInfo
+                .iter()
+                .map(move |player_info| (guild_id, group, player_info))
+        })
+        .filter(|(_, _, player_info)| {
+            player_info.PlayerName.to_lowercase().contains(&name_query)
+                || uid_prefix.as_ref().is_some_and(|uid_prefix| {
+                    player_info
+                        .PlayerUId
+                        .simple()
+                        .to_string()
+                        .starts_with(uid_prefix)
+                })
+        })
+        .map(|(guild_id, group, player_info)| PlayerMatch {
+            player_uid: player_info.PlayerUId,
+            player_name: player_info.PlayerName.clone(),
+            guild_id: *guild_id,
+            guild_name: group.GuildName.clone(),
+        })
+        .collect()
+}
+
+pub fn print_player_matches(matches: &[PlayerMatch]) {
+    matches.iter().for_each(|player_match| {
+        println!(
+            "{} {} in guild {}({})",
+            player_match.player_uid,
+            player_match.player_name,
+            player_match.guild_name,
+            player_match.guild_id
+        );
+    });
+}
+
+#[test]
+pub fn test_find_players() {
+    use crate::{fixtures, group_guild::GameVersion, sav::get_guilds};
+
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    let groups = get_guilds(&level_save, GameVersion::V0_1_3).unwrap();
+
+    // By name substring, ignoring case
+    let matches = find_players(&groups, "suff");
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].player_name, "Suffolk");
+    assert_eq!(matches[0].guild_id, Uuid::from_u128(0x100));
+    assert_eq!(matches[0].guild_name, "해적단");
+    let matches = find_players(&groups, "시쿨");
+    assert_eq!(matches.len(), 1);
+
+    // By PlayerUId prefix, with or without dashes
+    let player_info = &group_guild_save.GuildPlayerInfo[2];
+    let uid = player_info.PlayerUId.to_string();
+    [
+        &uid[..8],
+        &uid[..13],
+        &player_info.PlayerUId.simple().to_string()[..12],
+    ]
+    .iter()
+    .for_each(|prefix| {
+        let matches = find_players(&groups, prefix);
+        assert!(
+            matches
+                .iter()
+                .any(|player_match| player_match.player_uid == player_info.PlayerUId),
+            "{}",
+            prefix
+        );
+    });
+    let matches = find_players(&groups, &uid.to_uppercase());
+    assert_eq!(matches.len(), 1);
+    assert_eq!(matches[0].player_name, player_info.PlayerName);
+
+    assert!(find_players(&groups, "nobody").is_empty());
+}
@@ -0,0 +1,495 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    color::Colors,
+    json::{write_json, JsonStyle},
+};
+
+/// What a fix run changed in Level.sav.
+#[derive(Debug, Default, Serialize)]
+pub struct FixReport {
+    /// Guilds whose admin was replaced by their first member
+    pub fixed_admin_guild_ids: Vec<Uuid>,
+    /// `(PlayerUId, InstanceId)` of players that got a new character save
+    pub added_character_saves: Vec<(Uuid, Uuid)>,
+    /// `(InstanceId, group id)` of player characters moved to the guild they are a member of
+    pub corrected_group_ids: Vec<(Uuid, Uuid)>,
+    /// `(PlayerUId, InstanceId, owner PlayerUId)` of players whose InstanceId belongs to another
+    /// player's character. These need manual attention and are not fixed
+    pub instance_id_collisions: Vec<(Uuid, Uuid, Uuid)>,
+    /// Guilds whose RawData could not be parsed and were left untouched
+    pub unparsed_guild_ids: Vec<Uuid>,
+    /// Player saves whose parse exceeded --parse-timeout. Their players were not fixed
+    pub timed_out_player_savs: Vec<String>,
+    /// Files in `Players` without `SaveData.IndividualId`, which were skipped
+    pub non_player_savs: Vec<String>,
+    /// `(file, error)` of player saves that could not be read and were skipped with
+    /// --keep-going. Their players were not fixed
+    pub unreadable_player_savs: Vec<(String, String)>,
+    /// `(file, PlayerUId, InstanceId)` of player saves that share their InstanceId with another
+    /// one, skipped with --skip-duplicate-instance-ids. Their players were not fixed
+    pub duplicate_instance_id_savs: Vec<(String, Uuid, Uuid)>,
+    /// `(file, difference)` of player saves whose header custom formats differ from Level.sav's,
+    /// which points to a save from another game version. They are fixed all the same
+    pub custom_format_mismatches: Vec<(String, String)>,
+    /// Guilds without members, with --report-empty-guilds. They are left untouched
+    pub empty_guild_ids: Vec<Uuid>,
+    /// Players without a character save who are not a member of any parsed guild, so no
+    /// character could be made for them
+    pub guildless_player_uids: Vec<Uuid>,
+}
+
+impl FixReport {
+    pub fn has_changes(&self) -> bool {
+        !self.fixed_admin_guild_ids.is_empty()
+            || !self.added_character_saves.is_empty()
+            || !self.corrected_group_ids.is_empty()
+    }
+
+    /// The errors --keep-going skipped, one line each.
+    pub fn failures(&self) -> Vec<String> {
+        self.unreadable_player_savs
+            .iter()
+            .map(|(_, error)| error.clone())
+            .collect()
+    }
+}
+
+/// Format of the printed fix report.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// Lists, colored on terminals
+    Text,
+    /// The report as a JSON object
+    Json,
+    /// Tables, for posting to forums and issues
+    Markdown,
+}
+
+pub fn write_fix_report<W: Write>(
+    writer: &mut W,
+    report: &FixReport,
+    format: ReportFormat,
+    colors: Colors,
+    json_style: JsonStyle,
+) -> anyhow::Result<()> {
+    match format {
+        ReportFormat::Text => write_fix_report_text(writer, report, colors),
+        ReportFormat::Json => {
+            write_json(&mut *writer, report, json_style)?;
+            writeln!(writer)?;
+            Ok(())
+        }
+        ReportFormat::Markdown => {
+            write!(writer, "{}", render_fix_report_markdown(report))?;
+            Ok(())
+        }
+    }
+}
+
+fn write_fix_report_text<W: Write>(
+    writer: &mut W,
+    report: &FixReport,
+    colors: Colors,
+) -> anyhow::Result<()> {
+    writeln!(
+        writer,
+        "{}",
+        colors.changed(format!(
+            "Fixed guild admins: {}",
+            report.fixed_admin_guild_ids.len()
+        ))
+    )?;
+    report
+        .fixed_admin_guild_ids
+        .iter()
+        .try_for_each(|guild_id| writeln!(writer, "- {}", guild_id))?;
+    writeln!(
+        writer,
+        "{}",
+        colors.changed(format!(
+            "Added character saves: {}",
+            report.added_character_saves.len()
+        ))
+    )?;
+    report
+        .added_character_saves
+        .iter()
+        .try_for_each(|(player_uid, instance_id)| {
+            writeln!(writer, "- Player {} with id {}", player_uid, instance_id)
+        })?;
+    writeln!(
+        writer,
+        "{}",
+        colors.changed(format!(
+            "Corrected character group ids: {}",
+            report.corrected_group_ids.len()
+        ))
+    )?;
+    report
+        .corrected_group_ids
+        .iter()
+        .try_for_each(|(instance_id, group_id)| {
+            writeln!(
+                writer,
+                "- Character {} moved to guild {}",
+                instance_id, group_id
+            )
+        })?;
+    if !report.instance_id_collisions.is_empty() {
+        writeln!(
+            writer,
+            "{}",
+            colors.warning(format!(
+                "InstanceId collisions: {}",
+                report.instance_id_collisions.len()
+            ))
+        )?;
+        report.instance_id_collisions.iter().try_for_each(
+            |(player_uid, instance_id, owner_uid)| {
+                writeln!(
+                    writer,
+                    "- Player {} has id {} of player {}'s character",
+                    player_uid, instance_id, owner_uid
+                )
+            },
+        )?;
+    }
+    if !report.unparsed_guild_ids.is_empty() {
+        writeln!(
+            writer,
+            "{}",
+            colors.warning(format!(
+                "Unparsed guilds: {}",
+                report.unparsed_guild_ids.len()
+            ))
+        )?;
+        report
+            .unparsed_guild_ids
+            .iter()
+            .try_for_each(|guild_id| writeln!(writer, "- {}", guild_id))?;
+    }
+    if !report.timed_out_player_savs.is_empty() {
+        writeln!(
+            writer,
+            "{}",
+            colors.warning(format!(
+                "Player saves skipped after --parse-timeout: {}",
+                report.timed_out_player_savs.len()
+            ))
+        )?;
+        report
+            .timed_out_player_savs
+            .iter()
+            .try_for_each(|name| writeln!(writer, "- {}", name))?;
+    }
+    if !report.unreadable_player_savs.is_empty() {
+        writeln!(
+            writer,
+            "{}",
+            colors.warning(format!(
+                "Player saves that could not be read: {}",
+                report.unreadable_player_savs.len()
+            ))
+        )?;
+        report
+            .unreadable_player_savs
+            .iter()
+            .try_for_each(|(name, error)| writeln!(writer, "- {}: {}", name, error))?;
+    }
+    if !report.duplicate_instance_id_savs.is_empty() {
+        writeln!(
+            writer,
+            "{}",
+            colors.warning(format!(
+                "Player saves sharing an InstanceId: {}",
+                report.duplicate_instance_id_savs.len()
+            ))
+        )?;
+        report.duplicate_instance_id_savs.iter().try_for_each(
+            |(name, player_uid, instance_id)| {
+                writeln!(
+                    writer,
+                    "- {}: player {} with id {}",
+                    name, player_uid, instance_id
+                )
+            },
+        )?;
+    }
+    if !report.custom_format_mismatches.is_empty() {
+        writeln!(
+            writer,
+            "{}",
+            colors.warning(format!(
+                "Player saves with other custom formats than Level.sav: {}",
+                report.custom_format_mismatches.len()
+            ))
+        )?;
+        report
+            .custom_format_mismatches
+            .iter()
+            .try_for_each(|(name, mismatch)| writeln!(writer, "- {}: {}", name, mismatch))?;
+    }
+    if !report.non_player_savs.is_empty() {
+        writeln!(
+            writer,
+            "{}",
+            colors.warning(format!(
+                "Files in Players that are not player saves: {}",
+                report.non_player_savs.len()
+            ))
+        )?;
+        report
+            .non_player_savs
+            .iter()
+            .try_for_each(|name| writeln!(writer, "- {}", name))?;
+    }
+    if !report.guildless_player_uids.is_empty() {
+        writeln!(
+            writer,
+            "{}",
+            colors.warning(format!(
+                "Players without a guild: {}",
+                report.guildless_player_uids.len()
+            ))
+        )?;
+        report
+            .guildless_player_uids
+            .iter()
+            .try_for_each(|player_uid| writeln!(writer, "- {}", player_uid))?;
+    }
+    if !report.empty_guild_ids.is_empty() {
+        writeln!(
+            writer,
+            "{}",
+            colors.warning(format!(
+                "Empty guilds, candidates for deletion: {}",
+                report.empty_guild_ids.len()
+            ))
+        )?;
+        report
+            .empty_guild_ids
+            .iter()
+            .try_for_each(|guild_id| writeln!(writer, "- {}", guild_id))?;
+    }
+    Ok(())
+}
+
+/// Renders `report` as Markdown: a summary table of the counts, then a table for each kind of
+/// change or finding that has entries.
+pub fn render_fix_report_markdown(report: &FixReport) -> String {
+    let uid = |uid: &Uuid| format!("`{}`", uid);
+    let tables: Vec<(&str, Vec<&str>, Vec<Vec<String>>)> = vec![
+        (
+            "Fixed guild admins",
+            vec!["Guild"],
+            report
+                .fixed_admin_guild_ids
+                .iter()
+                .map(|guild_id| vec![uid(guild_id)])
+                .collect(),
+        ),
+        (
+            "Added character saves",
+            vec!["Player", "InstanceId"],
+            report
+                .added_character_saves
+                .iter()
+                .map(|(player_uid, instance_id)| vec![uid(player_uid), uid(instance_id)])
+                .collect(),
+        ),
+        (
+            "Corrected character group ids",
+            vec!["Character", "Guild"],
+            report
+                .corrected_group_ids
+                .iter()
+                .map(|(instance_id, group_id)| vec![uid(instance_id), uid(group_id)])
+                .collect(),
+        ),
+        (
+            "InstanceId collisions",
+            vec!["Player", "InstanceId", "Owner"],
+            report
+                .instance_id_collisions
+                .iter()
+                .map(|(player_uid, instance_id, owner_uid)| {
+                    vec![uid(player_uid), uid(instance_id), uid(owner_uid)]
+                })
+                .collect(),
+        ),
+        (
+            "Unparsed guilds",
+            vec!["Guild"],
+            report
+                .unparsed_guild_ids
+                .iter()
+                .map(|guild_id| vec![uid(guild_id)])
+                .collect(),
+        ),
+        (
+            "Player saves skipped after --parse-timeout",
+            vec!["File"],
+            report
+                .timed_out_player_savs
+                .iter()
+                .map(|name| vec![format!("`{}`", name)])
+                .collect(),
+        ),
+        (
+            "Player saves that could not be read",
+            vec!["File", "Error"],
+            report
+                .unreadable_player_savs
+                .iter()
+                .map(|(name, error)| {
+                    vec![
+                        format!("`{}`", name),
+                        error.replace('|', "\\|").replace('\n', " "),
+                    ]
+                })
+                .collect(),
+        ),
+        (
+            "Player saves sharing an InstanceId",
+            vec!["File", "Player", "InstanceId"],
+            report
+                .duplicate_instance_id_savs
+                .iter()
+                .map(|(name, player_uid, instance_id)| {
+                    vec![format!("`{}`", name), uid(player_uid), uid(instance_id)]
+                })
+                .collect(),
+        ),
+        (
+            "Player saves with other custom formats than Level.sav",
+            vec!["File", "Difference"],
+            report
+                .custom_format_mismatches
+                .iter()
+                .map(|(name, mismatch)| vec![format!("`{}`", name), mismatch.clone()])
+                .collect(),
+        ),
+        (
+            "Files in Players that are not player saves",
+            vec!["File"],
+            report
+                .non_player_savs
+                .iter()
+                .map(|name| vec![format!("`{}`", name)])
+                .collect(),
+        ),
+        (
+            "Players without a guild",
+            vec!["Player"],
+            report
+                .guildless_player_uids
+                .iter()
+                .map(|player_uid| vec![uid(player_uid)])
+                .collect(),
+        ),
+        (
+            "Empty guilds",
+            vec!["Guild"],
+            report
+                .empty_guild_ids
+                .iter()
+                .map(|guild_id| vec![uid(guild_id)])
+                .collect(),
+        ),
+    ];
+
+    let mut markdown = String::from("# Fix report\n\n| | Count |\n| --- | --- |\n");
+    tables.iter().for_each(|(title, _, rows)| {
+        markdown.push_str(&format!("| {} | {} |\n", title, rows.len()));
+    });
+    tables
+        .iter()
+        .filter(|(_, _, rows)| !rows.is_empty())
+        .for_each(|(title, headers, rows)| {
+            markdown.push_str(&format!("\n## {}\n\n", title));
+            markdown.push_str(&format!("| {} |\n", headers.join(" | ")));
+            markdown.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+            rows.iter().for_each(|row| {
+                markdown.push_str(&format!("| {} |\n", row.join(" | ")));
+            });
+        });
+    markdown
+}
+
+#[cfg(test)]
+fn sample_fix_report() -> FixReport {
+    FixReport {
+        fixed_admin_guild_ids: vec![Uuid::from_u128(0x100)],
+        added_character_saves: vec![
+            (Uuid::from_u128(1), Uuid::from_u128(0x11)),
+            (Uuid::from_u128(2), Uuid::from_u128(0x12)),
+        ],
+        instance_id_collisions: vec![(Uuid::from_u128(3), Uuid::from_u128(0x13), Uuid::nil())],
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+fn render_fix_report(report: &FixReport, format: ReportFormat) -> String {
+    let mut output = Vec::new();
+    write_fix_report(
+        &mut output,
+        report,
+        format,
+        Colors::detect_from(true, None, false),
+        JsonStyle::Compact,
+    )
+    .unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+pub fn test_write_fix_report_text() {
+    let text = render_fix_report(&sample_fix_report(), ReportFormat::Text);
+    assert!(text.contains("Fixed guild admins: 1\n"));
+    assert!(text.contains(&format!(
+        "- Player {} with id {}\n",
+        Uuid::from_u128(2),
+        Uuid::from_u128(0x12)
+    )));
+    assert!(text.contains("Corrected character group ids: 0\n"));
+    assert!(text.contains("InstanceId collisions: 1\n"));
+    assert!(!text.contains("Unparsed guilds"));
+}
+
+#[test]
+pub fn test_write_fix_report_json() {
+    let json = render_fix_report(&sample_fix_report(), ReportFormat::Json);
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["fixed_admin_guild_ids"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        value["added_character_saves"][1][0],
+        Uuid::from_u128(2).to_string()
+    );
+    assert!(value["corrected_group_ids"].as_array().unwrap().is_empty());
+}
+
+#[test]
+pub fn test_write_fix_report_markdown() {
+    let markdown = render_fix_report(&sample_fix_report(), ReportFormat::Markdown);
+    assert!(markdown.starts_with("# Fix report\n\n| | Count |\n| --- | --- |\n"));
+    assert!(markdown.contains("| Added character saves | 2 |\n"));
+    assert!(markdown.contains("| Empty guilds | 0 |\n"));
+    assert!(
+        markdown.contains("\n## Added character saves\n\n| Player | InstanceId |\n| --- | --- |\n")
+    );
+    assert!(markdown.contains(&format!(
+        "| `{}` | `{}` |\n",
+        Uuid::from_u128(1),
+        Uuid::from_u128(0x11)
+    )));
+    assert!(markdown.contains("| Player | InstanceId | Owner |\n| --- | --- | --- |\n"));
+    // Tables without entries are left out
+    assert!(!markdown.contains("## Corrected character group ids"));
+    assert_eq!(markdown.matches("\n## ").count(), 3);
+}
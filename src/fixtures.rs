@@ -0,0 +1,322 @@
+//! Synthetic save directories for tests, built from the files in `assets/`.
+
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+use indexmap::IndexMap;
+use uuid::Uuid;
+
+use crate::{
+    character_save_parameter::write_raw_character_save_parameter,
+    fix_report::FixReport,
+    fix_save_store,
+    group_guild::{
+        read_group_guild_save, stream, write_group_guild_save, GameVersion, GroupGuildSave,
+    },
+    sav::{
+        get_character_save_parameter_map, get_character_save_parameter_map_mut,
+        get_group_save_data_map_mut, get_world_save_data_mut, iter_character_entries,
+        match_character_key_shape, new_character_save_entry, parse_raw_character_save_parameter,
+        parse_raw_group_guild_save, read_save_file_path, write_raw_group_guild_save,
+        write_save_file, PalSave,
+    },
+    save_store::MemSaveStore,
+    template::{load_template, DEFAULT_TEMPLATE},
+    Args,
+};
+
+/// Returns an empty directory under the system temp directory.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("palworld-guild-save-fix-{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+pub fn read_level_save() -> PalSave {
+    read_save_file_path(Path::new("assets/Level.sav")).unwrap()
+}
+
+pub fn read_guild() -> GroupGuildSave {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    read_group_guild_save(&mut stream(data.as_ref())).unwrap()
+}
+
+/// Appends a guild to `GroupSaveDataMap`, reusing the shape of an existing group entry.
+pub fn add_guild(level_save: &mut PalSave, guild_id: Uuid, group_guild_save: &GroupGuildSave) {
+    add_guild_raw(
+        level_save,
+        guild_id,
+        write_group_guild_save(group_guild_save),
+    );
+}
+
+/// Appends a guild whose `RawData` is `raw_data`, which does not have to be a valid guild.
+pub fn add_guild_raw(level_save: &mut PalSave, guild_id: Uuid, raw_data: Vec<u8>) {
+    let group_save_data_map = get_group_save_data_map_mut(level_save);
+    let mut entry = group_save_data_map[0].clone();
+    entry.key = uesave::PropertyValue::Struct(uesave::StructValue::Guid(guild_id));
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref mut value)) = entry.value
+    else {
+        panic!()
+    };
+    let uesave::Property::Enum {
+        value: group_type, ..
+    } = value.get_mut("GroupType").unwrap()
+    else {
+        panic!()
+    };
+    *group_type = "EPalGroupType::Guild".into();
+    let uesave::Property::Array {
+        value: uesave::ValueArray::Base(uesave::ValueVec::Byte(data)),
+        ..
+    } = value.get_mut("RawData").unwrap()
+    else {
+        panic!()
+    };
+    *data = uesave::ByteArray::Byte(raw_data);
+    group_save_data_map.push(entry);
+}
+
+/// Parses the guild `guild_id`, applies `update` and writes it back.
+pub fn update_guild(
+    level_save: &mut PalSave,
+    guild_id: Uuid,
+    update: impl FnOnce(&mut GroupGuildSave),
+) {
+    let entry = get_group_save_data_map_mut(level_save)
+        .iter_mut()
+        .find(|entry| {
+            matches!(
+                entry.key,
+                uesave::PropertyValue::Struct(uesave::StructValue::Guid(id)) if id == guild_id
+            )
+        })
+        .unwrap();
+    let mut group_guild_save = parse_raw_group_guild_save(entry, GameVersion::V0_1_3).unwrap();
+    update(&mut group_guild_save);
+    write_raw_group_guild_save(entry, &group_guild_save);
+}
+
+/// Overwrites the group id in the RawData of the character at `index` of `CharacterSaveParameterMap`.
+pub fn set_character_group_id(level_save: &mut PalSave, index: usize, group_id: Uuid) {
+    let header = &level_save.save.header;
+    let entry = &get_character_save_parameter_map(level_save)[index];
+    let mut character_save_parameter = parse_raw_character_save_parameter(header)(entry).unwrap();
+    character_save_parameter.group_id = group_id;
+    let raw_data = write_raw_character_save_parameter(header)(&character_save_parameter);
+
+    let entry = &mut get_character_save_parameter_map_mut(level_save)[index];
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref mut value)) = entry.value
+    else {
+        panic!()
+    };
+    let uesave::Property::Array {
+        value: uesave::ValueArray::Base(uesave::ValueVec::Byte(data)),
+        ..
+    } = value.get_mut("RawData").unwrap()
+    else {
+        panic!()
+    };
+    *data = uesave::ByteArray::Byte(raw_data);
+}
+
+/// Returns the group id in the RawData of the character at `index` of `CharacterSaveParameterMap`.
+pub fn character_group_id(level_save: &PalSave, index: usize) -> Uuid {
+    let entry = &get_character_save_parameter_map(level_save)[index];
+    parse_raw_character_save_parameter(&level_save.save.header)(entry)
+        .unwrap()
+        .group_id
+}
+
+/// Appends a character save made from the built-in template to `CharacterSaveParameterMap`.
+pub fn add_character_save(level_save: &mut PalSave, player_uid: Uuid, instance_id: Uuid) {
+    let character_save_parameter = load_template(DEFAULT_TEMPLATE)
+        .unwrap()
+        .create_character_save("Player", Uuid::from_u128(0x100));
+    let mut entry = new_character_save_entry(
+        &level_save.save.header,
+        player_uid,
+        instance_id,
+        &character_save_parameter,
+    );
+    match_character_key_shape(&mut entry, get_character_save_parameter_map(level_save));
+    get_character_save_parameter_map_mut(level_save).push(entry);
+}
+
+/// Adds an empty `CharacterSaveParameterMap`, which `assets/Level.sav` does not have.
+pub fn add_character_save_parameter_map(level_save: &mut PalSave) {
+    let world_save_data = get_world_save_data_mut(level_save);
+    let mut map = world_save_data["GroupSaveDataMap"].clone();
+    let uesave::Property::Map { ref mut value, .. } = map else {
+        panic!()
+    };
+    value.clear();
+    world_save_data.insert("CharacterSaveParameterMap".into(), map);
+}
+
+pub fn guid_property(uuid: Uuid) -> uesave::Property {
+    uesave::Property::Struct {
+        id: None,
+        value: uesave::StructValue::Guid(uuid),
+        struct_type: uesave::StructType::Guid,
+        struct_id: Uuid::nil(),
+    }
+}
+
+pub fn struct_property(properties: IndexMap<String, uesave::Property>) -> uesave::Property {
+    uesave::Property::Struct {
+        id: None,
+        value: uesave::StructValue::Struct(properties),
+        struct_type: uesave::StructType::Struct(Some("PalInstanceID".into())),
+        struct_id: Uuid::nil(),
+    }
+}
+
+/// Builds a player save with `SaveData.IndividualId`, borrowing the header of `level_save`.
+pub fn player_save(level_save: &PalSave, player_uid: Uuid, instance_id: Uuid) -> PalSave {
+    let mut individual_id = IndexMap::new();
+    individual_id.insert("PlayerUId".to_string(), guid_property(player_uid));
+    individual_id.insert("InstanceId".to_string(), guid_property(instance_id));
+    let mut save_data = IndexMap::new();
+    save_data.insert("IndividualId".to_string(), struct_property(individual_id));
+
+    let mut pal_save = level_save.clone();
+    pal_save.save.root.properties.clear();
+    pal_save
+        .save
+        .root
+        .properties
+        .insert("SaveData".into(), struct_property(save_data));
+    pal_save
+}
+
+/// Builds a LevelMeta.sav with `SaveData.HostPlayerName`, borrowing the header of `level_save`.
+pub fn level_meta_save(level_save: &PalSave, host_player_name: &str) -> PalSave {
+    let mut save_data = IndexMap::new();
+    save_data.insert(
+        "HostPlayerName".to_string(),
+        uesave::Property::Str {
+            id: None,
+            value: host_player_name.into(),
+        },
+    );
+
+    let mut pal_save = level_save.clone();
+    pal_save.save.root.properties.clear();
+    pal_save
+        .save
+        .root
+        .properties
+        .insert("SaveData".into(), struct_property(save_data));
+    pal_save
+}
+
+pub fn write_save(path: &Path, pal_save: &PalSave) {
+    let mut file = std::fs::File::create(path).unwrap();
+    write_save_file(&mut file, pal_save).unwrap();
+}
+
+pub fn player_sav_name(player_uid: &Uuid) -> String {
+    format!("{}.sav", player_uid.simple().to_string().to_uppercase())
+}
+
+/// Writes `Level.sav` and `Players/*.sav` to `dir`.
+pub fn write_save_directory(dir: &Path, level_save: &PalSave, player_saves: &[(Uuid, PalSave)]) {
+    std::fs::create_dir_all(dir.join("Players")).unwrap();
+    write_save(&dir.join("Level.sav"), level_save);
+    for (player_uid, player_save) in player_saves {
+        write_save(
+            &dir.join("Players").join(player_sav_name(player_uid)),
+            player_save,
+        );
+    }
+}
+
+/// Builds a save with one guild (`assets/guild_0.bin`) whose first `broken` members have a
+/// player save but no character save. InstanceIds are assigned in descending order.
+/// Returns Level.sav, the player saves and the broken players' `(PlayerUId, InstanceId)`.
+pub fn broken_save(broken: usize) -> (PalSave, Vec<(Uuid, PalSave)>, Vec<(Uuid, Uuid)>) {
+    let group_guild_save = read_guild();
+    let broken_players: Vec<(Uuid, Uuid)> = group_guild_save.GuildPlayerInfo[..broken]
+        .iter()
+        .enumerate()
+        .map(|(i, player_info)| (player_info.PlayerUId, Uuid::from_u128((broken - i) as u128)))
+        .collect();
+
+    let mut level_save = read_level_save();
+    add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    add_character_save_parameter_map(&mut level_save);
+    let player_saves: Vec<(Uuid, PalSave)> = broken_players
+        .iter()
+        .map(|(player_uid, instance_id)| {
+            (
+                *player_uid,
+                player_save(&level_save, *player_uid, *instance_id),
+            )
+        })
+        .collect();
+    (level_save, player_saves, broken_players)
+}
+
+/// Writes [`broken_save`] to `dir`. Returns the broken players' `(PlayerUId, InstanceId)`.
+pub fn write_broken_save_directory(dir: &Path, broken: usize) -> Vec<(Uuid, Uuid)> {
+    let (level_save, player_saves, broken_players) = broken_save(broken);
+    write_save_directory(dir, &level_save, &player_saves);
+    broken_players
+}
+
+/// Puts `level_save` and `player_saves` in a [`MemSaveStore`], naming each player save after
+/// its `PlayerUId` with [`player_sav_name`].
+pub fn mem_save_store(level_save: &PalSave, player_saves: &[(Uuid, PalSave)]) -> MemSaveStore {
+    let player_saves: Vec<(String, PalSave)> = player_saves
+        .iter()
+        .map(|(player_uid, player_save)| (player_sav_name(player_uid), player_save.clone()))
+        .collect();
+    MemSaveStore::new(level_save, &player_saves).unwrap()
+}
+
+/// Puts [`broken_save`] in a [`MemSaveStore`]. Returns the store and the broken players'
+/// `(PlayerUId, InstanceId)`.
+pub fn broken_mem_save_store(broken: usize) -> (MemSaveStore, Vec<(Uuid, Uuid)>) {
+    let (level_save, player_saves, broken_players) = broken_save(broken);
+    (mem_save_store(&level_save, &player_saves), broken_players)
+}
+
+/// Runs the fix on `store` with the default template, as `palworld-guild-save-fix <dir>`
+/// followed by `argv` would on a save directory.
+pub fn run_fix(argv: &[&str], store: &mut MemSaveStore) -> anyhow::Result<FixReport> {
+    let args = Args::parse_from(
+        ["palworld-guild-save-fix", "unused"]
+            .iter()
+            .chain(argv)
+            .copied(),
+    );
+    let template = load_template(DEFAULT_TEMPLATE)?;
+    fix_save_store(&args.global, &args.fix, &template, store)
+}
+
+/// Returns the `InstanceId` of every `CharacterSaveParameterMap` key, in map order.
+pub fn character_instance_ids(level_save: &PalSave) -> Vec<Uuid> {
+    iter_character_entries(level_save)
+        .map(|entry| entry.unwrap().1)
+        .collect()
+}
+
+/// Returns the `NickName` in the RawData of the character at `index` of `CharacterSaveParameterMap`.
+pub fn character_nickname(level_save: &PalSave, index: usize) -> String {
+    let entry = &get_character_save_parameter_map(level_save)[index];
+    let character_save_parameter =
+        parse_raw_character_save_parameter(&level_save.save.header)(entry).unwrap();
+    let Some(uesave::Property::Struct {
+        value: uesave::StructValue::Struct(properties),
+        ..
+    }) = character_save_parameter.properties.get("SaveParameter")
+    else {
+        panic!()
+    };
+    let uesave::Property::Str { value, .. } = &properties["NickName"] else {
+        panic!()
+    };
+    value.clone()
+}
@@ -0,0 +1,138 @@
+use uuid::Uuid;
+use winnow::{stream::Stream as _, Parser};
+
+use crate::{
+    group_guild::{
+        read_group_guild_save_repairing_fstrings_for, stream, write_group_guild_save, GameVersion,
+    },
+    patch::{Patch, PatchOp},
+    sav::{get_group_save_data_map, get_raw_data, is_group_type_guild, PalSave},
+};
+
+/// A guild whose FString lengths are corrected.
+#[derive(Debug, PartialEq)]
+pub struct GuildFStringRepair {
+    pub guild_id: Uuid,
+    pub guild_name: String,
+    /// FStrings (guild name, `MayBeOwner` and player names) whose length was wrong
+    pub repaired_count: usize,
+}
+
+/// The change that rewrites every guild with an FString whose length does not end at its
+/// terminator, so each FString gets the length [`write_fstring`] computes, and those guilds.
+/// Guilds that do not parse even so, or whose `RawData` does not end after the guild, are left
+/// alone.
+///
+/// [`write_fstring`]: crate::group_guild::write_fstring
+pub fn repair_fstrings_patch(
+    level_save: &PalSave,
+    version: GameVersion,
+) -> (Patch, Vec<GuildFStringRepair>) {
+    let repairs: Vec<(GuildFStringRepair, Vec<u8>)> = get_group_save_data_map(level_save)
+        .iter()
+        .filter(|entry| is_group_type_guild(entry))
+        .filter_map(|entry| {
+            let uesave::PropertyValue::Struct(uesave::StructValue::Guid(guild_id)) = entry.key
+            else {
+                return None;
+            };
+            let data = get_raw_data(entry).ok()?;
+            let mut input = stream(&data);
+            let (group_guild_save, repaired_count) =
+                read_group_guild_save_repairing_fstrings_for(version)
+                    .parse_next(&mut input)
+                    .ok()?;
+            if repaired_count == 0 || input.eof_offset() != 0 {
+                return None;
+            }
+            let repair = GuildFStringRepair {
+                guild_id,
+                guild_name: group_guild_save.GuildName.clone(),
+                repaired_count,
+            };
+            Some((repair, write_group_guild_save(&group_guild_save)))
+        })
+        .collect();
+    let patch = Patch::new(
+        repairs
+            .iter()
+            .map(|(repair, raw_data)| PatchOp::SetGuildRawData {
+                guild_id: repair.guild_id,
+                raw_data: raw_data.clone(),
+            })
+            .collect(),
+    );
+    (
+        patch,
+        repairs.into_iter().map(|(repair, _)| repair).collect(),
+    )
+}
+
+#[test]
+pub fn test_repair_fstrings() {
+    use crate::{
+        fixtures, group_guild::write_fstring, patch::apply_patch, sav::get_guilds_skipping_failures,
+    };
+
+    let mut group_guild_save = fixtures::read_guild();
+    group_guild_save.GuildName = "Guild".to_string();
+    group_guild_save.GuildPlayerInfo[0].PlayerName = "플레이어".to_string();
+    let data = write_group_guild_save(&group_guild_save);
+    // Lengths one character short, so the terminator is not where they end
+    let mut corrupted = data.clone();
+    [
+        (write_fstring("Guild"), 6 - 1),
+        (write_fstring("플레이어"), -5 + 1),
+    ]
+    .iter()
+    .for_each(|(fstring, wrong_len)| {
+        let offset = corrupted
+            .windows(fstring.len())
+            .position(|window| window == fstring.as_slice())
+            .unwrap();
+        corrupted[offset..offset + 4].copy_from_slice(&i32::to_le_bytes(*wrong_len));
+    });
+
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_guild(
+        &mut level_save,
+        Uuid::from_u128(0x100),
+        &fixtures::read_guild(),
+    );
+    fixtures::add_guild_raw(&mut level_save, Uuid::from_u128(0x200), corrupted);
+    // Not a guild even when FStrings are read leniently
+    fixtures::add_guild_raw(&mut level_save, Uuid::from_u128(0x300), vec![0xff; 7]);
+    let (_, failures) = get_guilds_skipping_failures(&level_save, GameVersion::V0_1_3);
+    assert_eq!(failures.len(), 2);
+
+    let (patch, repairs) = repair_fstrings_patch(&level_save, GameVersion::V0_1_3);
+    assert_eq!(
+        repairs,
+        vec![GuildFStringRepair {
+            guild_id: Uuid::from_u128(0x200),
+            guild_name: "Guild".to_string(),
+            repaired_count: 2,
+        }]
+    );
+    apply_patch(&mut level_save, &patch).unwrap();
+
+    let (_, failures) = get_guilds_skipping_failures(&level_save, GameVersion::V0_1_3);
+    assert_eq!(failures.len(), 1);
+    let raw_data = get_group_save_data_map(&level_save)
+        .iter()
+        .find(|entry| {
+            matches!(
+                entry.key,
+                uesave::PropertyValue::Struct(uesave::StructValue::Guid(id))
+                    if id == Uuid::from_u128(0x200)
+            )
+        })
+        .map(|entry| get_raw_data(entry).unwrap().into_owned())
+        .unwrap();
+    assert_eq!(raw_data, data);
+
+    // Nothing left to repair
+    let (patch, repairs) = repair_fstrings_patch(&level_save, GameVersion::V0_1_3);
+    assert!(patch.ops.is_empty());
+    assert!(repairs.is_empty());
+}
@@ -1,19 +1,30 @@
+//! Parsers and writers of guild `RawData`. Like the rest of a save it is little-endian: every
+//! integer is read with `le_*` and written with `to_le_bytes`, and UTF-16 FStrings are written
+//! unit by unit, so saves are identical whatever the byte order of the host.
+
 #![allow(non_snake_case)]
 
 use uuid::Uuid;
 use winnow::{
-    binary::{le_i32, le_u32, le_u64, le_u8, length_repeat},
-    combinator::terminated,
+    binary::{le_i32, le_u16, le_u32, le_u64, le_u8, length_repeat},
+    combinator::{fail, repeat_till0, terminated},
+    error::StrContext,
     seq,
-    token::take,
+    stream::StreamIsPartial,
+    token::{take, take_till0},
     trace::trace,
     Bytes, PResult, Parser, Partial,
 };
 
 pub type Stream<'i> = Partial<&'i Bytes>;
 
+/// A stream over a whole `RawData`. The blob is always read in full, so the stream is marked
+/// complete: running out of bytes is a `Backtrack` error like any other corruption, never
+/// `Incomplete`.
 pub fn stream(bytes: &[u8]) -> Stream<'_> {
-    Partial::new(Bytes::new(bytes))
+    let mut stream = Partial::new(Bytes::new(bytes));
+    let _ = stream.complete();
+    stream
 }
 
 pub fn read_uuid(s: &mut Stream) -> PResult<Uuid> {
@@ -35,30 +46,47 @@ pub fn write_uuid(guid: &Uuid) -> Vec<u8> {
     ]
 }
 
+/// Upper bound on the character count of an FString, well above any name the game stores.
+pub const FSTRING_MAX_LENGTH: u32 = 0x10000;
+
 pub fn read_fstring(s: &mut Stream) -> PResult<String> {
     trace("FString", move |i: &mut Stream| {
         let len = le_i32.parse_next(i)?;
         if len == 0 {
             return Ok("".to_string());
         }
+        // `unsigned_abs` also covers `i32::MIN`, whose negation would overflow
+        if len.unsigned_abs() > FSTRING_MAX_LENGTH {
+            return fail::<_, String, _>
+                .context(StrContext::Label("FString length out of range"))
+                .parse_next(i);
+        }
 
         let is_unicode = len < 0;
         if is_unicode {
             let len = -len as usize;
-            trace(
-                "Unicode",
-                terminated(
-                    take((len - 1) * 2).map(|s: &[u8]| {
-                        String::from_utf16_lossy(
-                            s.chunks(2)
-                                .map(|c| u16::from_le_bytes([c[0], c[1]]))
-                                .collect::<Vec<_>>()
-                                .as_slice(),
-                        )
-                    }),
-                    b"\0\0",
-                ),
-            )
+            trace("Unicode", move |i: &mut Stream| {
+                // The length counts UTF-16 units including the terminator. The last unit is
+                // checked before any is decoded, so a length that does not match the string,
+                // e.g. single-byte text stored with a UTF-16 length, fails instead of decoding
+                // units shifted by a byte into garbage
+                let bytes: &[u8] = take(len * 2).parse_next(i)?;
+                let (units, terminator) = bytes.split_at(bytes.len() - 2);
+                if terminator != [0, 0] {
+                    return fail::<_, String, _>
+                        .context(StrContext::Label(
+                            "Unicode FString is not \\0\\0 terminated",
+                        ))
+                        .parse_next(i);
+                }
+                Ok(String::from_utf16_lossy(
+                    units
+                        .chunks_exact(2)
+                        .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                        .collect::<Vec<_>>()
+                        .as_slice(),
+                ))
+            })
             .parse_next(i)
         } else {
             let len = len as usize;
@@ -75,14 +103,41 @@ pub fn read_fstring(s: &mut Stream) -> PResult<String> {
     .parse_next(s)
 }
 
+/// Reads an FString like [`read_fstring`], but when its length does not end at a terminator, as a
+/// buggy save editor can leave it, reads up to the first terminator instead. The sign of the
+/// length is still trusted for the encoding. Returns whether the length was wrong:
+/// [`write_fstring`] writes the string back with the right one.
+pub fn read_fstring_repairing(s: &mut Stream) -> PResult<(String, bool)> {
+    trace("FString (repairing)", |i: &mut Stream| {
+        let mut exact = *i;
+        if let Ok(value) = read_fstring.parse_next(&mut exact) {
+            *i = exact;
+            return Ok((value, false));
+        }
+        let len = le_i32.parse_next(i)?;
+        let value = if len < 0 {
+            let (units, _): (Vec<u16>, &[u8]) = repeat_till0(le_u16, b"\0\0").parse_next(i)?;
+            String::from_utf16_lossy(&units)
+        } else {
+            let bytes: &[u8] = terminated(take_till0(0u8), b"\0").parse_next(i)?;
+            String::from_utf8_lossy(bytes).to_string()
+        };
+        Ok((value, true))
+    })
+    .parse_next(s)
+}
+
+/// Writes `s` as a NUL-terminated FString: single bytes when it is ASCII, UTF-16LE with a
+/// negative length otherwise, like the game does. An empty string is a bare zero length, without
+/// a terminator, as the game writes an FString that never held characters.
 pub fn write_fstring(s: &str) -> Vec<u8> {
     let mut bytes = Vec::new();
-    let is_unicode = s.len() != s.chars().count();
-    if is_unicode {
+    if s.is_empty() {
+        bytes.extend_from_slice(&0i32.to_le_bytes());
+    } else if !s.is_ascii() {
         let utf16: Vec<u16> = s.encode_utf16().collect();
-        let (_, aligned, _) = unsafe { utf16.align_to::<u8>() };
-        bytes.extend_from_slice(&(-(aligned.len() as i32 / 2) - 1).to_le_bytes());
-        bytes.extend_from_slice(aligned);
+        bytes.extend_from_slice(&(-(utf16.len() as i32) - 1).to_le_bytes());
+        bytes.extend(utf16.iter().flat_map(|unit| unit.to_le_bytes()));
         bytes.extend_from_slice(&[0, 0]);
     } else {
         bytes.extend_from_slice(&(s.len() as i32 + 1).to_le_bytes());
@@ -92,6 +147,59 @@ pub fn write_fstring(s: &str) -> Vec<u8> {
     bytes
 }
 
+/// How an FString was stored, from the sign of its length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FStringEncoding {
+    /// UTF-16LE, with a negative length
+    Unicode,
+    /// Single bytes, with a positive or zero length
+    NonUnicode,
+}
+
+impl FStringEncoding {
+    pub fn label(&self) -> &'static str {
+        match self {
+            FStringEncoding::Unicode => "unicode",
+            FStringEncoding::NonUnicode => "non-unicode",
+        }
+    }
+}
+
+/// An FString with the bytes it was read from, length included, to diagnose garbled names.
+#[derive(Debug, Clone)]
+pub struct RawFString {
+    pub value: String,
+    pub bytes: Vec<u8>,
+    pub encoding: FStringEncoding,
+}
+
+impl RawFString {
+    /// Whether [`write_fstring`] writes the value back to the same bytes, so rewriting an
+    /// unchanged guild leaves the name as it was. Not the case for a non-unicode name that is not
+    /// ASCII, or an empty one stored with a terminator.
+    pub fn round_trips(&self) -> bool {
+        write_fstring(&self.value) == self.bytes
+    }
+}
+
+pub fn read_raw_fstring(s: &mut Stream) -> PResult<RawFString> {
+    read_fstring
+        .with_recognized()
+        .map(|(value, bytes): (String, &[u8])| {
+            let len = i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            RawFString {
+                value,
+                bytes: bytes.to_vec(),
+                encoding: if len < 0 {
+                    FStringEncoding::Unicode
+                } else {
+                    FStringEncoding::NonUnicode
+                },
+            }
+        })
+        .parse_next(s)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct FDateTime {
     Ticks: u64,
@@ -117,8 +225,9 @@ pub fn write_fdatetime(datetime: &FDateTime) -> Vec<u8> {
 
 #[derive(Debug, Clone, Copy)]
 pub struct FPalInstanceId {
-    PlayerUId: Uuid,
-    InstanceUId: Uuid,
+    /// Nil for pals
+    pub PlayerUId: Uuid,
+    pub InstanceUId: Uuid,
 }
 
 pub fn read_fpal_instance_id(s: &mut Stream) -> PResult<FPalInstanceId> {
@@ -170,6 +279,9 @@ pub fn write_fpal_guild_player_info(player_info: &FPalGuildPlayerInfo) -> Vec<u8
     bytes
 }
 
+/// Highest `BaseCampLevel` set-base-camp-level accepts, well above any level the game reaches.
+pub const MAX_BASE_CAMP_LEVEL: u32 = 50;
+
 #[derive(Debug, Clone)]
 pub struct GroupGuildSave {
     pub UnknownUuid: Uuid,
@@ -205,6 +317,294 @@ pub fn read_group_guild_save(s: &mut Stream) -> PResult<GroupGuildSave> {
     .parse_next(s)
 }
 
+/// The FStrings of a guild as they are stored.
+#[derive(Debug, Clone)]
+pub struct GroupGuildNames {
+    pub MayBeOwner: RawFString,
+    pub GuildName: RawFString,
+    /// `(PlayerUId, PlayerName)` of each member
+    pub PlayerNames: Vec<(Uuid, RawFString)>,
+}
+
+/// Reads only the FStrings of [`read_group_guild_save`]'s layout, keeping their bytes.
+pub fn read_group_guild_names(s: &mut Stream) -> PResult<GroupGuildNames> {
+    trace("GroupGuildNames", |i: &mut Stream| {
+        let _: Uuid = read_uuid.parse_next(i)?;
+        let may_be_owner = read_raw_fstring.parse_next(i)?;
+        let _: Vec<FPalInstanceId> = length_repeat(le_u32, read_fpal_instance_id).parse_next(i)?;
+        let _: u8 = le_u8.parse_next(i)?;
+        let _: Vec<Uuid> = length_repeat(le_u32, read_uuid).parse_next(i)?;
+        let _: u32 = le_u32.parse_next(i)?;
+        let _: Vec<Uuid> = length_repeat(le_u32, read_uuid).parse_next(i)?;
+        let guild_name = read_raw_fstring.parse_next(i)?;
+        let _: Uuid = read_uuid.parse_next(i)?;
+        let player_names: Vec<(Uuid, RawFString)> = length_repeat(
+            le_u32,
+            (read_uuid, read_fdatetime, read_raw_fstring)
+                .map(|(player_uid, _, player_name)| (player_uid, player_name)),
+        )
+        .parse_next(i)?;
+        Ok(GroupGuildNames {
+            MayBeOwner: may_be_owner,
+            GuildName: guild_name,
+            PlayerNames: player_names,
+        })
+    })
+    .parse_next(s)
+}
+
+/// Reads a guild of [`read_group_guild_save`]'s layout with [`read_fstring_repairing`], returning
+/// it with the number of FStrings whose length was wrong.
+pub fn read_group_guild_save_repairing_fstrings(
+    s: &mut Stream,
+) -> PResult<(GroupGuildSave, usize)> {
+    trace("GroupGuildSave (repairing FStrings)", |i: &mut Stream| {
+        let unknown_uuid = read_uuid.parse_next(i)?;
+        let (may_be_owner, may_be_owner_repaired) = read_fstring_repairing.parse_next(i)?;
+        let instance_ids = length_repeat(le_u32, read_fpal_instance_id).parse_next(i)?;
+        let unknown = le_u8.parse_next(i)?;
+        let unknown_guid = length_repeat(le_u32, read_uuid).parse_next(i)?;
+        let base_camp_level = le_u32.parse_next(i)?;
+        let unknown_guid2 = length_repeat(le_u32, read_uuid).parse_next(i)?;
+        let (guild_name, guild_name_repaired) = read_fstring_repairing.parse_next(i)?;
+        let admin_player_uid = read_uuid.parse_next(i)?;
+        let player_infos: Vec<(FPalGuildPlayerInfo, bool)> = length_repeat(
+            le_u32,
+            (read_uuid, read_fdatetime, read_fstring_repairing).map(
+                |(player_uid, last_online_real_time, (player_name, repaired))| {
+                    let player_info = FPalGuildPlayerInfo {
+                        PlayerUId: player_uid,
+                        LastOnlineRealTime: last_online_real_time,
+                        PlayerName: player_name,
+                    };
+                    (player_info, repaired)
+                },
+            ),
+        )
+        .parse_next(i)?;
+        let repaired_count = [may_be_owner_repaired, guild_name_repaired]
+            .into_iter()
+            .chain(player_infos.iter().map(|(_, repaired)| *repaired))
+            .filter(|repaired| *repaired)
+            .count();
+        let group_guild_save = GroupGuildSave {
+            UnknownUuid: unknown_uuid,
+            MayBeOwner: may_be_owner,
+            InstanceIds: instance_ids,
+            unknown,
+            UnknownGuid: unknown_guid,
+            BaseCampLevel: base_camp_level,
+            UnknownGuid2: unknown_guid2,
+            GuildName: guild_name,
+            AdminPlayerUId: admin_player_uid,
+            GuildPlayerInfo: player_infos
+                .into_iter()
+                .map(|(player_info, _)| player_info)
+                .collect(),
+        };
+        Ok((group_guild_save, repaired_count))
+    })
+    .parse_next(s)
+}
+
+/// Returns `true` when `AdminPlayerUId` is one of the guild members.
+pub fn is_admin_in_guild(group_guild_save: &GroupGuildSave) -> bool {
+    group_guild_save
+        .GuildPlayerInfo
+        .iter()
+        .any(|player_info| player_info.PlayerUId == group_guild_save.AdminPlayerUId)
+}
+
+/// Returns the `InstanceIds` entries whose `PlayerUId` is set but is not one of the guild
+/// members: references to players that left or were removed. Pals have a nil `PlayerUId`.
+pub fn find_stale_instance_ids(group_guild_save: &GroupGuildSave) -> Vec<FPalInstanceId> {
+    group_guild_save
+        .InstanceIds
+        .iter()
+        .filter(|instance_id| {
+            !instance_id.PlayerUId.is_nil()
+                && !group_guild_save
+                    .GuildPlayerInfo
+                    .iter()
+                    .any(|player_info| player_info.PlayerUId == instance_id.PlayerUId)
+        })
+        .copied()
+        .collect()
+}
+
+/// Returns the `AdminPlayerUId` of a guild for display, or "no admin set" when it is nil.
+pub fn guild_admin_label(admin_player_uid: Uuid) -> String {
+    if admin_player_uid.is_nil() {
+        "no admin set".to_string()
+    } else {
+        admin_player_uid.to_string()
+    }
+}
+
+/// Promotes the first member listed in `GuildPlayerInfo` to admin. Returns the new admin, or
+/// `None` for a guild without members.
+///
+/// A nil `AdminPlayerUId` is treated like an admin who left: a guild with members always has an
+/// admin in game, so it gets one. A guild without members is left admin-less, since there is
+/// nobody to promote.
+pub fn fix_guild_admin(group_guild_save: &mut GroupGuildSave) -> Option<Uuid> {
+    let new_admin = group_guild_save.GuildPlayerInfo.first()?.PlayerUId;
+    group_guild_save.AdminPlayerUId = new_admin;
+    Some(new_admin)
+}
+
+/// Returns `true` when the player entries of `InstanceIds` (those with a `PlayerUId`) are in
+/// the same relative order as `GuildPlayerInfo`, which is how the game writes them.
+#[cfg(test)]
+pub fn is_instance_order_in_sync(group_guild_save: &GroupGuildSave) -> bool {
+    let member_order: Vec<Uuid> = group_guild_save
+        .GuildPlayerInfo
+        .iter()
+        .map(|player_info| player_info.PlayerUId)
+        .collect();
+    let instance_order: Vec<Uuid> = group_guild_save
+        .InstanceIds
+        .iter()
+        .map(|instance_id| instance_id.PlayerUId)
+        .filter(|player_uid| member_order.contains(player_uid))
+        .collect();
+    let member_order: Vec<Uuid> = member_order
+        .into_iter()
+        .filter(|player_uid| instance_order.contains(player_uid))
+        .collect();
+    instance_order == member_order
+}
+
+/// Reorders the player entries of `InstanceIds` to follow `GuildPlayerInfo`, after members were
+/// reordered. Player entries stay in the slots they occupied, so pal entries do not move.
+pub fn sync_instance_order(group_guild_save: &mut GroupGuildSave) {
+    let member_index = |player_uid: &Uuid| {
+        group_guild_save
+            .GuildPlayerInfo
+            .iter()
+            .position(|player_info| player_info.PlayerUId == *player_uid)
+    };
+    let slots: Vec<usize> = group_guild_save
+        .InstanceIds
+        .iter()
+        .enumerate()
+        .filter(|(_, instance_id)| member_index(&instance_id.PlayerUId).is_some())
+        .map(|(slot, _)| slot)
+        .collect();
+    let mut player_instance_ids: Vec<FPalInstanceId> = slots
+        .iter()
+        .map(|slot| group_guild_save.InstanceIds[*slot])
+        .collect();
+    // Stable, so several instances of one player keep their order
+    player_instance_ids.sort_by_key(|instance_id| member_index(&instance_id.PlayerUId));
+    slots
+        .into_iter()
+        .zip(player_instance_ids)
+        .for_each(|(slot, instance_id)| group_guild_save.InstanceIds[slot] = instance_id);
+}
+
+/// Appends the members and `InstanceIds` of `source` to `destination`, which keeps its name and
+/// admin. Members and instances `destination` already has are not added twice. A new instance of
+/// a member `destination` already has lands after the others, so `InstanceIds` is then put back
+/// in member order with [`sync_instance_order`].
+pub fn merge_group_guild_save(destination: &mut GroupGuildSave, source: &GroupGuildSave) {
+    source.GuildPlayerInfo.iter().for_each(|player_info| {
+        if !destination
+            .GuildPlayerInfo
+            .iter()
+            .any(|member| member.PlayerUId == player_info.PlayerUId)
+        {
+            destination.GuildPlayerInfo.push(player_info.clone());
+        }
+    });
+    source.InstanceIds.iter().for_each(|instance_id| {
+        if !destination
+            .InstanceIds
+            .iter()
+            .any(|existing| existing.InstanceUId == instance_id.InstanceUId)
+        {
+            destination.InstanceIds.push(*instance_id);
+        }
+    });
+    sync_instance_order(destination);
+}
+
+/// Known guild `RawData` layouts, named after the game version that introduced them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameVersion {
+    /// v0.1.2.0 ~ v0.1.3.0
+    V0_1_3,
+}
+
+impl GameVersion {
+    pub const ALL: &'static [GameVersion] = &[GameVersion::V0_1_3];
+
+    pub fn tag(&self) -> &'static str {
+        match self {
+            GameVersion::V0_1_3 => "0.1.3",
+        }
+    }
+}
+
+impl std::fmt::Display for GameVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.tag())
+    }
+}
+
+impl std::str::FromStr for GameVersion {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        GameVersion::ALL
+            .iter()
+            .find(|version| version.tag() == s)
+            .copied()
+            .ok_or_else(|| {
+                let tags: Vec<&str> = GameVersion::ALL.iter().map(|v| v.tag()).collect();
+                format!("unknown game version {} (known: {})", s, tags.join(", "))
+            })
+    }
+}
+
+pub fn read_group_guild_save_for(
+    version: GameVersion,
+) -> fn(&mut Stream) -> PResult<GroupGuildSave> {
+    match version {
+        GameVersion::V0_1_3 => read_group_guild_save,
+    }
+}
+
+/// Parses a guild of `version` from the start of `data`. Returns the result with the number of
+/// bytes read, which is where parsing stopped on failure, and is compared against `data.len()`
+/// on success.
+pub fn read_group_guild_save_consumed(
+    data: &[u8],
+    version: GameVersion,
+) -> (PResult<GroupGuildSave>, usize) {
+    use winnow::stream::Stream as _;
+
+    let mut input = stream(data);
+    let result = read_group_guild_save_for(version).parse_next(&mut input);
+    (result, data.len() - input.eof_offset())
+}
+
+pub fn read_group_guild_names_for(
+    version: GameVersion,
+) -> fn(&mut Stream) -> PResult<GroupGuildNames> {
+    match version {
+        GameVersion::V0_1_3 => read_group_guild_names,
+    }
+}
+
+pub fn read_group_guild_save_repairing_fstrings_for(
+    version: GameVersion,
+) -> fn(&mut Stream) -> PResult<(GroupGuildSave, usize)> {
+    match version {
+        GameVersion::V0_1_3 => read_group_guild_save_repairing_fstrings,
+    }
+}
+
 pub fn write_tarray<T, F>(items: &[T], write_item: F) -> Vec<u8>
 where
     F: Fn(&T) -> Vec<u8>,
@@ -220,6 +620,9 @@ where
     bytes
 }
 
+/// Serializes a guild. Every array is written in its in-memory order, so member edits must
+/// keep `InstanceIds` in sync with `GuildPlayerInfo`, as [`merge_group_guild_save`] does with
+/// [`sync_instance_order`].
 pub fn write_group_guild_save(group_guild_save: &GroupGuildSave) -> Vec<u8> {
     let mut bytes = Vec::new();
     bytes.extend_from_slice(&write_uuid(&group_guild_save.UnknownUuid));
@@ -254,3 +657,506 @@ pub fn test_read_write_group_guild_save() {
     let data2 = write_group_guild_save(&group_guild_save);
     assert_eq!(data, data2.as_slice());
 }
+
+#[test]
+pub fn test_read_group_guild_save_consumes_all_bytes() {
+    use winnow::{error::ErrMode, stream::Stream as _};
+
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    let mut input = stream(data.as_ref());
+    read_group_guild_save(&mut input).unwrap();
+    assert_eq!(input.eof_offset(), 0);
+
+    // A blob cut inside the last GuildPlayerInfo is corrupt, not waiting for more input
+    let result = read_group_guild_save(&mut stream(&data[..data.len() - 1]));
+    assert!(matches!(result, Err(ErrMode::Backtrack(_))));
+}
+
+#[test]
+pub fn test_read_group_guild_save_consumed() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    let (result, consumed) = read_group_guild_save_consumed(&data, GameVersion::V0_1_3);
+    assert!(result.is_ok());
+    assert_eq!(consumed, data.len());
+
+    // Bytes after the guild are not part of it
+    let mut longer = data.clone();
+    longer.extend_from_slice(&[0xff; 3]);
+    let (result, consumed) = read_group_guild_save_consumed(&longer, GameVersion::V0_1_3);
+    assert!(result.is_ok());
+    assert_eq!(consumed, data.len());
+}
+
+#[test]
+pub fn test_read_group_guild_save_for_forced_version() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+
+    let version: GameVersion = "0.1.3".parse().unwrap();
+    assert_eq!(version, GameVersion::V0_1_3);
+    assert!("9.9.9".parse::<GameVersion>().is_err());
+
+    let group_guild_save = read_group_guild_save_for(version)
+        .parse_next(&mut stream(data.as_ref()))
+        .unwrap();
+    assert_eq!(data, write_group_guild_save(&group_guild_save).as_slice());
+}
+
+/// What a guild fixture is expected to parse to.
+#[cfg(test)]
+struct GuildFixture {
+    version: GameVersion,
+    path: &'static str,
+    guild_name: &'static str,
+    admin_player_uid: u128,
+    base_camp_level: u32,
+    instance_id_count: usize,
+    member_names: &'static [&'static str],
+}
+
+/// One guild `RawData` per known layout. When a game version changes the layout, add its
+/// `GameVersion`, a fixture as `assets/guild_v<tag>.bin` and a row here.
+#[cfg(test)]
+const GUILD_FIXTURES: &[GuildFixture] = &[GuildFixture {
+    version: GameVersion::V0_1_3,
+    path: "assets/guild_0.bin",
+    guild_name: "해적단",
+    admin_player_uid: 0xcbc6d68d_0000_0000_0000_000000000000,
+    base_camp_level: 15,
+    instance_id_count: 838,
+    member_names: &["차차", "시쿨", "MISO", "Suffolk", "고덕P4화감팀장"],
+}];
+
+#[test]
+pub fn test_guild_fixtures() {
+    GameVersion::ALL.iter().for_each(|version| {
+        assert!(
+            GUILD_FIXTURES
+                .iter()
+                .any(|fixture| fixture.version == *version),
+            "No guild fixture for {}",
+            version
+        );
+    });
+    GUILD_FIXTURES.iter().for_each(|fixture| {
+        let data = std::fs::read(fixture.path).unwrap();
+        let group_guild_save = read_group_guild_save_for(fixture.version)
+            .parse_next(&mut stream(data.as_ref()))
+            .unwrap_or_else(|error| panic!("{} ({}): {}", fixture.path, fixture.version, error));
+        assert_eq!(group_guild_save.GuildName, fixture.guild_name);
+        assert_eq!(
+            group_guild_save.AdminPlayerUId,
+            Uuid::from_u128(fixture.admin_player_uid)
+        );
+        assert_eq!(group_guild_save.BaseCampLevel, fixture.base_camp_level);
+        assert_eq!(
+            group_guild_save.InstanceIds.len(),
+            fixture.instance_id_count
+        );
+        assert_eq!(
+            group_guild_save
+                .GuildPlayerInfo
+                .iter()
+                .map(|player_info| player_info.PlayerName.as_str())
+                .collect::<Vec<_>>(),
+            fixture.member_names
+        );
+        assert_eq!(
+            write_group_guild_save(&group_guild_save),
+            data,
+            "{} does not round-trip",
+            fixture.path
+        );
+    });
+}
+
+#[test]
+pub fn test_fix_guild_admin_not_member() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    let mut group_guild_save = read_group_guild_save(&mut stream(data.as_ref())).unwrap();
+    assert!(is_admin_in_guild(&group_guild_save));
+
+    group_guild_save.AdminPlayerUId = Uuid::from_u128(0xdeadbeef);
+    assert!(!is_admin_in_guild(&group_guild_save));
+
+    let new_admin = fix_guild_admin(&mut group_guild_save).unwrap();
+    assert_eq!(new_admin, group_guild_save.GuildPlayerInfo[0].PlayerUId);
+    assert!(is_admin_in_guild(&group_guild_save));
+}
+
+#[test]
+pub fn test_find_stale_instance_ids() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    let mut group_guild_save = read_group_guild_save(&mut stream(data.as_ref())).unwrap();
+    let stale_count = find_stale_instance_ids(&group_guild_save).len();
+
+    let stale = FPalInstanceId {
+        PlayerUId: Uuid::from_u128(0xdead),
+        InstanceUId: Uuid::from_u128(0xbeef),
+    };
+    group_guild_save.InstanceIds.push(stale);
+    // Pals and members are not stale
+    group_guild_save.InstanceIds.push(FPalInstanceId {
+        PlayerUId: Uuid::nil(),
+        InstanceUId: Uuid::from_u128(0xbef0),
+    });
+    group_guild_save.InstanceIds.push(FPalInstanceId {
+        PlayerUId: group_guild_save.GuildPlayerInfo[0].PlayerUId,
+        InstanceUId: Uuid::from_u128(0xbef1),
+    });
+
+    let found = find_stale_instance_ids(&group_guild_save);
+    assert_eq!(found.len(), stale_count + 1);
+    assert_eq!(found.last().unwrap().PlayerUId, stale.PlayerUId);
+    assert_eq!(found.last().unwrap().InstanceUId, stale.InstanceUId);
+}
+
+#[test]
+pub fn test_fix_guild_admin_nil() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    let mut group_guild_save = read_group_guild_save(&mut stream(data.as_ref())).unwrap();
+    assert_eq!(
+        guild_admin_label(group_guild_save.AdminPlayerUId),
+        group_guild_save.AdminPlayerUId.to_string()
+    );
+
+    group_guild_save.AdminPlayerUId = Uuid::nil();
+    assert!(!is_admin_in_guild(&group_guild_save));
+    assert_eq!(
+        guild_admin_label(group_guild_save.AdminPlayerUId),
+        "no admin set"
+    );
+    // The first listed member is promoted, whatever the order of the list
+    group_guild_save.GuildPlayerInfo.reverse();
+    let members: Vec<Uuid> = group_guild_save
+        .GuildPlayerInfo
+        .iter()
+        .map(|player_info| player_info.PlayerUId)
+        .collect();
+    assert!(members.len() > 1);
+    let new_admin = fix_guild_admin(&mut group_guild_save).unwrap();
+    assert_eq!(new_admin, members[0]);
+    assert_eq!(group_guild_save.AdminPlayerUId, members[0]);
+    assert!(is_admin_in_guild(&group_guild_save));
+    let members_after: Vec<Uuid> = group_guild_save
+        .GuildPlayerInfo
+        .iter()
+        .map(|player_info| player_info.PlayerUId)
+        .collect();
+    assert_eq!(members_after, members);
+
+    // Nobody to promote in a guild without members
+    group_guild_save.AdminPlayerUId = Uuid::nil();
+    group_guild_save.GuildPlayerInfo.clear();
+    assert_eq!(fix_guild_admin(&mut group_guild_save), None);
+    assert!(group_guild_save.AdminPlayerUId.is_nil());
+}
+
+#[test]
+pub fn test_write_fstring_round_trip() {
+    // (string, written as UTF-16)
+    let cases = [
+        ("Suffolk", false),
+        ("Crème brûlée", true),
+        ("해적단", true),
+        ("🦀 crab", true),
+    ];
+    cases.iter().for_each(|(s, is_unicode)| {
+        let bytes = write_fstring(s);
+        let len = i32::from_le_bytes(bytes[..4].try_into().unwrap());
+        if *is_unicode {
+            assert_eq!(len, -(s.encode_utf16().count() as i32) - 1, "{}", s);
+            assert_eq!(bytes.len(), 4 + (s.encode_utf16().count() + 1) * 2, "{}", s);
+            assert_eq!(&bytes[bytes.len() - 2..], &[0, 0]);
+        } else {
+            assert_eq!(len, s.len() as i32 + 1, "{}", s);
+            assert_eq!(bytes.len(), 4 + s.len() + 1, "{}", s);
+            assert_eq!(bytes.last(), Some(&0));
+        }
+        assert_eq!(read_fstring(&mut stream(bytes.as_ref())).unwrap(), *s);
+    });
+
+    // An empty string has no terminator
+    assert_eq!(write_fstring(""), [0, 0, 0, 0]);
+    assert_eq!(read_fstring(&mut stream(&[0, 0, 0, 0])).unwrap(), "");
+
+    // A surrogate pair takes two UTF-16 units
+    assert_eq!(
+        i32::from_le_bytes(write_fstring("🦀")[..4].try_into().unwrap()),
+        -3
+    );
+}
+
+#[test]
+pub fn test_read_fstring_repairing() {
+    use winnow::stream::Stream as _;
+
+    let with_len = |len: i32, bytes: &[u8]| -> Vec<u8> {
+        let mut data = len.to_le_bytes().to_vec();
+        data.extend_from_slice(bytes);
+        // The field after the string
+        data.extend_from_slice(&[0xaa, 0xbb]);
+        data
+    };
+    let cases = [
+        // (length, bytes, string, whether the length was wrong)
+        (8, &b"Suffolk\0"[..], "Suffolk", false),
+        (5, &b"Suffolk\0"[..], "Suffolk", true),
+        (12, &b"Suffolk\0"[..], "Suffolk", true),
+        (-2, &[0x74, 0xd5, 0x01, 0xc8, 0x00, 0x00][..], "해적", true),
+        (-3, &[0x74, 0xd5, 0x01, 0xc8, 0x00, 0x00][..], "해적", false),
+        (-9, &[0x74, 0xd5, 0x01, 0xc8, 0x00, 0x00][..], "해적", true),
+    ];
+    cases.iter().for_each(|(len, bytes, value, repaired)| {
+        let data = with_len(*len, bytes);
+        let mut input = stream(data.as_ref());
+        assert_eq!(
+            read_fstring_repairing(&mut input).unwrap(),
+            (value.to_string(), *repaired),
+            "{}",
+            value
+        );
+        // The field after the string is where it was
+        assert_eq!(input.eof_offset(), 2, "{}", value);
+    });
+
+    // Without any terminator there is nothing to repair
+    assert!(read_fstring_repairing(&mut stream(&with_len(5, b"Suffolk")[..4 + 6])).is_err());
+}
+
+#[test]
+pub fn test_read_group_guild_save_repairing_fstrings() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    let (group_guild_save, repaired_count) =
+        read_group_guild_save_repairing_fstrings(&mut stream(data.as_ref())).unwrap();
+    assert_eq!(repaired_count, 0);
+    assert_eq!(write_group_guild_save(&group_guild_save), data);
+
+    // A GuildName length one unit short
+    let guild_name = write_fstring(&group_guild_save.GuildName);
+    let offset = data
+        .windows(guild_name.len())
+        .position(|window| window == guild_name.as_slice())
+        .unwrap();
+    let mut corrupted = data.clone();
+    let len = i32::from_le_bytes(corrupted[offset..offset + 4].try_into().unwrap());
+    corrupted[offset..offset + 4].copy_from_slice(&(len + 1).to_le_bytes());
+    assert!(read_group_guild_save(&mut stream(corrupted.as_ref())).is_err());
+
+    let (repaired, repaired_count) =
+        read_group_guild_save_repairing_fstrings(&mut stream(corrupted.as_ref())).unwrap();
+    assert_eq!(repaired_count, 1);
+    assert_eq!(repaired.GuildName, group_guild_save.GuildName);
+    assert_eq!(write_group_guild_save(&repaired), data);
+}
+
+#[test]
+pub fn test_group_guild_fstrings_round_trip() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    let names = read_group_guild_names(&mut stream(data.as_ref())).unwrap();
+    let fstrings: Vec<&RawFString> = [&names.MayBeOwner, &names.GuildName]
+        .into_iter()
+        .chain(names.PlayerNames.iter().map(|(_, player_name)| player_name))
+        .collect();
+    assert_eq!(fstrings.len(), 2 + 5);
+    fstrings.iter().for_each(|fstring| {
+        assert!(fstring.round_trips(), "{:?}", fstring);
+    });
+
+    // Layouts write_fstring cannot reproduce are detected
+    let diverging: [&[u8]; 2] = [
+        // Empty, with a terminator
+        &[1, 0, 0, 0, 0],
+        // Latin-1 "é" as a single byte
+        &[2, 0, 0, 0, 0xe9, 0],
+    ];
+    diverging.iter().for_each(|bytes| {
+        assert!(!read_raw_fstring(&mut stream(bytes)).unwrap().round_trips());
+    });
+    assert!(read_raw_fstring(&mut stream(&[0, 0, 0, 0]))
+        .unwrap()
+        .round_trips());
+}
+
+#[test]
+pub fn test_little_endian_byte_layouts() {
+    assert_eq!(
+        write_fstring("ab"),
+        [0x03, 0x00, 0x00, 0x00, b'a', b'b', 0x00]
+    );
+    // -2 UTF-16 units, then U+D574 and the terminator
+    assert_eq!(
+        write_fstring("해"),
+        [0xfe, 0xff, 0xff, 0xff, 0x74, 0xd5, 0x00, 0x00]
+    );
+    // Each 32-bit word of the Guid is little-endian
+    let uuid = Uuid::from_u128(0x00010203_04050607_08090a0b_0c0d0e0f);
+    let bytes = [
+        0x03, 0x02, 0x01, 0x00, 0x07, 0x06, 0x05, 0x04, 0x0b, 0x0a, 0x09, 0x08, 0x0f, 0x0e, 0x0d,
+        0x0c,
+    ];
+    assert_eq!(write_uuid(&uuid), bytes);
+    assert_eq!(read_uuid(&mut stream(&bytes)).unwrap(), uuid);
+    assert_eq!(write_tarray(&[1u8], |byte| vec![*byte]), [1, 0, 0, 0, 1]);
+}
+
+#[test]
+pub fn test_read_fstring_rejects_out_of_range_length() {
+    use winnow::error::ErrMode;
+
+    for len in [i32::MIN, i32::MAX] {
+        let mut data = len.to_le_bytes().to_vec();
+        data.extend_from_slice(&[0; 16]);
+        let result = read_fstring(&mut stream(data.as_ref()));
+        assert!(matches!(result, Err(ErrMode::Backtrack(_))));
+    }
+}
+
+#[test]
+pub fn test_read_fstring_rejects_unterminated_unicode() {
+    use winnow::error::ErrMode;
+
+    // "ab" as UTF-16 with a length of 3 units, but the terminator is "c"
+    let mut data = (-3i32).to_le_bytes().to_vec();
+    data.extend_from_slice(&[b'a', 0, b'b', 0, b'c', 0]);
+    data.extend_from_slice(&[0; 16]);
+    let Err(ErrMode::Backtrack(error)) = read_fstring(&mut stream(data.as_ref())) else {
+        panic!()
+    };
+    assert!(error.context().any(|context| matches!(
+        context,
+        StrContext::Label(label) if label.contains("terminated")
+    )));
+
+    // Single-byte "ab\0" behind a UTF-16 length, so the \0 sits at an odd offset
+    let mut data = (-2i32).to_le_bytes().to_vec();
+    data.extend_from_slice(&[b'a', b'b', 0]);
+    data.extend_from_slice(&[0xff; 16]);
+    assert!(matches!(
+        read_fstring(&mut stream(data.as_ref())),
+        Err(ErrMode::Backtrack(_))
+    ));
+
+    let mut data = (-3i32).to_le_bytes().to_vec();
+    data.extend_from_slice(&[b'a', 0, b'b', 0, 0, 0]);
+    assert_eq!(read_fstring(&mut stream(data.as_ref())).unwrap(), "ab");
+}
+
+/// Regression seeds for the `fuzz/` targets: truncated and corrupted guilds must not panic.
+#[test]
+pub fn test_parsers_do_not_panic_on_corrupt_input() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    for end in (0..data.len()).step_by(13) {
+        let truncated = &data[..end];
+        let _ = read_fstring(&mut stream(truncated));
+        let _ = read_uuid(&mut stream(truncated));
+        let _ = read_fpal_guild_player_info(&mut stream(truncated));
+        assert!(read_group_guild_save(&mut stream(truncated)).is_err());
+    }
+    for position in (0..data.len()).step_by(7) {
+        let mut corrupted = data.clone();
+        corrupted[position] ^= 0xff;
+        let _ = read_group_guild_save(&mut stream(corrupted.as_ref()));
+    }
+    for len in [0i32, 1, -1, 2, -2] {
+        let data = len.to_le_bytes();
+        let _ = read_fstring(&mut stream(data.as_ref()));
+    }
+}
+
+#[test]
+pub fn test_write_group_guild_save_keeps_member_order() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    let mut group_guild_save = read_group_guild_save(&mut stream(data.as_ref())).unwrap();
+    assert!(is_instance_order_in_sync(&group_guild_save));
+    let pal_instance_ids: Vec<Uuid> = group_guild_save
+        .InstanceIds
+        .iter()
+        .filter(|instance_id| instance_id.PlayerUId.is_nil())
+        .map(|instance_id| instance_id.InstanceUId)
+        .collect();
+
+    group_guild_save.GuildPlayerInfo.reverse();
+    assert!(!is_instance_order_in_sync(&group_guild_save));
+    sync_instance_order(&mut group_guild_save);
+    assert!(is_instance_order_in_sync(&group_guild_save));
+
+    let data = write_group_guild_save(&group_guild_save);
+    let group_guild_save2 = read_group_guild_save(&mut stream(data.as_ref())).unwrap();
+    let member_order: Vec<Uuid> = group_guild_save
+        .GuildPlayerInfo
+        .iter()
+        .map(|player_info| player_info.PlayerUId)
+        .collect();
+    assert_eq!(
+        group_guild_save2
+            .GuildPlayerInfo
+            .iter()
+            .map(|player_info| player_info.PlayerUId)
+            .collect::<Vec<_>>(),
+        member_order
+    );
+    assert_eq!(
+        group_guild_save2
+            .InstanceIds
+            .iter()
+            .filter(|instance_id| !instance_id.PlayerUId.is_nil())
+            .map(|instance_id| instance_id.PlayerUId)
+            .collect::<Vec<_>>(),
+        member_order
+    );
+    assert_eq!(
+        group_guild_save2
+            .InstanceIds
+            .iter()
+            .filter(|instance_id| instance_id.PlayerUId.is_nil())
+            .map(|instance_id| instance_id.InstanceUId)
+            .collect::<Vec<_>>(),
+        pal_instance_ids
+    );
+}
+
+#[test]
+pub fn test_merge_group_guild_save_keeps_instance_order() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    let guild = read_group_guild_save(&mut stream(data.as_ref())).unwrap();
+    let instance = |player_uid: u128, instance_uid: u128| FPalInstanceId {
+        PlayerUId: Uuid::from_u128(player_uid),
+        InstanceUId: Uuid::from_u128(instance_uid),
+    };
+    let members = |player_uids: &[u128]| -> Vec<FPalGuildPlayerInfo> {
+        player_uids
+            .iter()
+            .map(|player_uid| {
+                let mut player_info = guild.GuildPlayerInfo[0].clone();
+                player_info.PlayerUId = Uuid::from_u128(*player_uid);
+                player_info
+            })
+            .collect()
+    };
+    let mut destination = guild.clone();
+    destination.GuildPlayerInfo = members(&[0xa, 0xb]);
+    destination.InstanceIds = vec![instance(0xa, 0xa1), instance(0, 0x1), instance(0xb, 0xb1)];
+    // A second character of a member the destination already has, and a new member
+    let mut source = guild.clone();
+    source.GuildPlayerInfo = members(&[0xa, 0xc]);
+    source.InstanceIds = vec![instance(0xa, 0xa2), instance(0xc, 0xc1)];
+
+    merge_group_guild_save(&mut destination, &source);
+    assert_eq!(
+        destination
+            .GuildPlayerInfo
+            .iter()
+            .map(|player_info| player_info.PlayerUId.as_u128())
+            .collect::<Vec<_>>(),
+        [0xa, 0xb, 0xc]
+    );
+    // Player entries follow the members, the pal keeps its slot
+    assert_eq!(
+        destination
+            .InstanceIds
+            .iter()
+            .map(|instance_id| instance_id.InstanceUId.as_u128())
+            .collect::<Vec<_>>(),
+        [0xa1, 0x1, 0xa2, 0xb1, 0xc1]
+    );
+}
@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    color::Colors,
+    group_guild::{find_stale_instance_ids, GameVersion},
+    sav::{
+        get_group_save_data_map, get_guilds_skipping_failures, is_group_type_guild,
+        iter_character_entries, parse_raw_character_save_parameter, PalSave,
+    },
+};
+
+/// How the group ids of characters match the groups declared in `GroupSaveDataMap`.
+#[derive(Debug, Default, Serialize)]
+pub struct GroupReconciliation {
+    /// Group ids that characters reference but `GroupSaveDataMap` does not declare, with the
+    /// InstanceIds of those orphaned characters
+    pub orphaned_group_ids: BTreeMap<Uuid, Vec<Uuid>>,
+    /// Guilds that no character references
+    pub guilds_without_characters: Vec<Uuid>,
+    /// Characters whose key or RawData could not be decoded, by InstanceId when it is known
+    pub undecoded_characters: Vec<Option<Uuid>>,
+    /// The `InstanceIds` of every guild checked against its members, when it was asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance_ids: Option<InstanceIdCheck>,
+}
+
+/// `InstanceIds` entries of a guild whose `PlayerUId` is not one of its members.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct StaleInstanceIds {
+    pub count: usize,
+    /// The distinct `PlayerUId`s of those entries, in the order they first appear
+    pub player_uids: Vec<Uuid>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct InstanceIdCheck {
+    /// Guilds with stale `InstanceIds` entries, by guild id
+    pub stale_instance_ids: BTreeMap<Uuid, StaleInstanceIds>,
+    /// Guilds whose RawData could not be parsed, so they were not checked
+    pub undecoded_guilds: Vec<Uuid>,
+}
+
+/// Checks the `PlayerUId` of the `InstanceIds` of every guild against the guild's own
+/// `GuildPlayerInfo`. Unlike [`reconcile_groups`], which compares characters with groups, this
+/// catches drift inside a guild.
+pub fn check_guild_instance_ids(level_save: &PalSave, version: GameVersion) -> InstanceIdCheck {
+    let (guilds, failures) = get_guilds_skipping_failures(level_save, version);
+    InstanceIdCheck {
+        stale_instance_ids: guilds
+            .iter()
+            .filter_map(|(guild_id, group_guild_save)| {
+                let stale = find_stale_instance_ids(group_guild_save);
+                if stale.is_empty() {
+                    return None;
+                }
+                let mut player_uids: Vec<Uuid> = Vec::new();
+                stale.iter().for_each(|instance_id| {
+                    if !player_uids.contains(&instance_id.PlayerUId) {
+                        player_uids.push(instance_id.PlayerUId);
+                    }
+                });
+                Some((
+                    *guild_id,
+                    StaleInstanceIds {
+                        count: stale.len(),
+                        player_uids,
+                    },
+                ))
+            })
+            .collect(),
+        undecoded_guilds: failures.iter().map(|(guild_id, _)| *guild_id).collect(),
+    }
+}
+
+impl GroupReconciliation {
+    /// Number of findings: orphaned group ids, guilds without characters, undecoded characters
+    /// and, when they were checked, guilds with stale `InstanceIds` or that could not be parsed.
+    pub fn warning_count(&self) -> usize {
+        self.orphaned_group_ids.len()
+            + self.guilds_without_characters.len()
+            + self.undecoded_characters.len()
+            + self.instance_ids.as_ref().map_or(0, |check| {
+                check.stale_instance_ids.len() + check.undecoded_guilds.len()
+            })
+    }
+}
+
+/// Compares the group ids in the RawData of every character with the group keys of
+/// `GroupSaveDataMap`. Characters without a group (a nil group id) are not orphaned.
+pub fn reconcile_groups(level_save: &PalSave) -> GroupReconciliation {
+    let group_save_data_map = get_group_save_data_map(level_save);
+    let group_id = |entry: &uesave::MapEntry| match entry.key {
+        uesave::PropertyValue::Struct(uesave::StructValue::Guid(group_id)) => Some(group_id),
+        _ => None,
+    };
+    let declared_group_ids: BTreeSet<Uuid> =
+        group_save_data_map.iter().filter_map(group_id).collect();
+
+    let mut reconciliation = GroupReconciliation::default();
+    let mut referenced_group_ids = BTreeSet::new();
+    let header = &level_save.save.header;
+    iter_character_entries(level_save).for_each(|entry| {
+        let Ok((_, instance_id, entry)) = entry else {
+            reconciliation.undecoded_characters.push(None);
+            return;
+        };
+        let Ok(character_save_parameter) = parse_raw_character_save_parameter(header)(entry) else {
+            reconciliation.undecoded_characters.push(Some(instance_id));
+            return;
+        };
+        let character_group_id = character_save_parameter.group_id;
+        if character_group_id.is_nil() {
+            return;
+        }
+        referenced_group_ids.insert(character_group_id);
+        if !declared_group_ids.contains(&character_group_id) {
+            reconciliation
+                .orphaned_group_ids
+                .entry(character_group_id)
+                .or_default()
+                .push(instance_id);
+        }
+    });
+    reconciliation.guilds_without_characters = group_save_data_map
+        .iter()
+        .filter(|entry| is_group_type_guild(entry))
+        .filter_map(group_id)
+        .filter(|guild_id| !referenced_group_ids.contains(guild_id))
+        .collect();
+    reconciliation
+}
+
+pub fn print_group_reconciliation(reconciliation: &GroupReconciliation, colors: Colors) {
+    let orphaned = format!(
+        "Group ids without a group: {}",
+        reconciliation.orphaned_group_ids.len()
+    );
+    if reconciliation.orphaned_group_ids.is_empty() {
+        println!("{}", orphaned);
+    } else {
+        println!("{}", colors.warning(orphaned));
+    }
+    reconciliation
+        .orphaned_group_ids
+        .iter()
+        .for_each(|(group_id, instance_ids)| {
+            println!(
+                "- {} has {} orphaned characters",
+                group_id,
+                instance_ids.len()
+            );
+            instance_ids.iter().for_each(|instance_id| {
+                println!("  - {}", instance_id);
+            });
+        });
+    println!(
+        "Guilds without characters: {}",
+        reconciliation.guilds_without_characters.len()
+    );
+    reconciliation
+        .guilds_without_characters
+        .iter()
+        .for_each(|guild_id| {
+            println!("- {}", guild_id);
+        });
+    if !reconciliation.undecoded_characters.is_empty() {
+        println!(
+            "{}",
+            colors.warning(format!(
+                "Characters that could not be decoded: {}",
+                reconciliation.undecoded_characters.len()
+            ))
+        );
+        reconciliation
+            .undecoded_characters
+            .iter()
+            .for_each(|instance_id| match instance_id {
+                Some(instance_id) => println!("- {}", instance_id),
+                None => println!("- (key without InstanceId)"),
+            });
+    }
+    if let Some(check) = &reconciliation.instance_ids {
+        let stale = format!(
+            "Guilds with InstanceIds of non-members: {}",
+            check.stale_instance_ids.len()
+        );
+        if check.stale_instance_ids.is_empty() {
+            println!("{}", stale);
+        } else {
+            println!("{}", colors.warning(stale));
+        }
+        check
+            .stale_instance_ids
+            .iter()
+            .for_each(|(guild_id, stale)| {
+                println!(
+                    "- {} has {} InstanceIds of {} non-members",
+                    guild_id,
+                    stale.count,
+                    stale.player_uids.len()
+                );
+                stale.player_uids.iter().for_each(|player_uid| {
+                    println!("  - {}", player_uid);
+                });
+            });
+        if !check.undecoded_guilds.is_empty() {
+            println!(
+                "{}",
+                colors.warning(format!(
+                    "Guilds that could not be parsed: {}",
+                    check.undecoded_guilds.len()
+                ))
+            );
+            check.undecoded_guilds.iter().for_each(|guild_id| {
+                println!("- {}", guild_id);
+            });
+        }
+    }
+}
+
+#[test]
+pub fn test_reconcile_groups() {
+    use crate::fixtures;
+
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x200), &group_guild_save);
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(1), Uuid::from_u128(0x11));
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(2), Uuid::from_u128(0x12));
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(3), Uuid::from_u128(0x13));
+    fixtures::set_character_group_id(&mut level_save, 0, Uuid::from_u128(0x100));
+    fixtures::set_character_group_id(&mut level_save, 1, Uuid::from_u128(0x999));
+    fixtures::set_character_group_id(&mut level_save, 2, Uuid::nil());
+
+    let reconciliation = reconcile_groups(&level_save);
+    assert_eq!(
+        reconciliation.orphaned_group_ids,
+        BTreeMap::from([(Uuid::from_u128(0x999), vec![Uuid::from_u128(0x12)])])
+    );
+    assert_eq!(
+        reconciliation.guilds_without_characters,
+        vec![Uuid::from_u128(0x200)]
+    );
+    assert!(reconciliation.undecoded_characters.is_empty());
+}
+
+#[test]
+pub fn test_check_guild_instance_ids() {
+    use crate::{
+        fixtures,
+        group_guild::{find_stale_instance_ids, FPalInstanceId},
+    };
+
+    let mut level_save = fixtures::read_level_save();
+    let mut group_guild_save = fixtures::read_guild();
+    // Only members and pals, then two entries of a player who left
+    let members: Vec<Uuid> = group_guild_save
+        .GuildPlayerInfo
+        .iter()
+        .map(|player_info| player_info.PlayerUId)
+        .collect();
+    group_guild_save.InstanceIds.retain(|instance_id| {
+        instance_id.PlayerUId.is_nil() || members.contains(&instance_id.PlayerUId)
+    });
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    [0x11, 0x12].iter().for_each(|instance_uid| {
+        group_guild_save.InstanceIds.push(FPalInstanceId {
+            PlayerUId: Uuid::from_u128(0xdead),
+            InstanceUId: Uuid::from_u128(*instance_uid),
+        });
+    });
+    assert_eq!(find_stale_instance_ids(&group_guild_save).len(), 2);
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x200), &group_guild_save);
+
+    let check = check_guild_instance_ids(&level_save, GameVersion::V0_1_3);
+    assert_eq!(
+        check.stale_instance_ids,
+        BTreeMap::from([(
+            Uuid::from_u128(0x200),
+            StaleInstanceIds {
+                count: 2,
+                player_uids: vec![Uuid::from_u128(0xdead)],
+            }
+        )])
+    );
+    assert!(check.undecoded_guilds.is_empty());
+
+    let mut reconciliation = reconcile_groups(&level_save);
+    let warning_count = reconciliation.warning_count();
+    reconciliation.instance_ids = Some(check);
+    assert_eq!(reconciliation.warning_count(), warning_count + 1);
+}
@@ -0,0 +1,152 @@
+use clap::ValueEnum;
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::group_guild::{guild_admin_label, GroupGuildSave};
+
+/// Order of the guilds printed by `list`.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuildSortKey {
+    /// Guild name, ignoring case
+    Name,
+    /// Member count
+    Members,
+    /// Base camp level
+    Basecamp,
+    /// `AdminPlayerUId`
+    Admin,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct GuildListEntry {
+    pub guild_id: Uuid,
+    pub name: String,
+    pub member_count: usize,
+    pub base_camp_level: u32,
+    /// Nil when the guild has no admin
+    pub admin_player_uid: Uuid,
+}
+
+/// Lists the guilds in `GroupSaveDataMap` order, or ascending by `sort` (descending with
+/// `reverse`). The sort is stable, so guilds with the same key keep their map order either way.
+pub fn list_guilds(
+    groups: &[(Uuid, GroupGuildSave)],
+    sort: Option<GuildSortKey>,
+    reverse: bool,
+) -> Vec<GuildListEntry> {
+    let mut entries: Vec<GuildListEntry> = groups
+        .iter()
+        .map(|(guild_id, group)| GuildListEntry {
+            guild_id: *guild_id,
+            name: group.GuildName.clone(),
+            member_count: group.GuildPlayerInfo.len(),
+            base_camp_level: group.BaseCampLevel,
+            admin_player_uid: group.AdminPlayerUId,
+        })
+        .collect();
+    let Some(sort) = sort else {
+        return entries;
+    };
+    entries.sort_by(|a, b| {
+        let ordering = match sort {
+            GuildSortKey::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+            GuildSortKey::Members => a.member_count.cmp(&b.member_count),
+            GuildSortKey::Basecamp => a.base_camp_level.cmp(&b.base_camp_level),
+            GuildSortKey::Admin => a.admin_player_uid.cmp(&b.admin_player_uid),
+        };
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+    entries
+}
+
+pub fn print_guild_list(entries: &[GuildListEntry]) {
+    entries.iter().for_each(|entry| {
+        println!(
+            "{}({}): {} members, base camp level {}, admin {}",
+            entry.name,
+            entry.guild_id,
+            entry.member_count,
+            entry.base_camp_level,
+            guild_admin_label(entry.admin_player_uid)
+        );
+    });
+    println!("Total: {} guilds", entries.len());
+}
+
+#[test]
+pub fn test_list_guilds_sorted() {
+    use crate::fixtures;
+
+    let guild = |name: &str, member_count: usize, base_camp_level: u32, admin: u128| {
+        let mut group_guild_save = fixtures::read_guild();
+        group_guild_save.GuildName = name.to_string();
+        group_guild_save.GuildPlayerInfo.truncate(member_count);
+        group_guild_save.BaseCampLevel = base_camp_level;
+        group_guild_save.AdminPlayerUId = Uuid::from_u128(admin);
+        group_guild_save
+    };
+    let groups = vec![
+        (Uuid::from_u128(0x100), guild("beta", 2, 3, 0x3)),
+        (Uuid::from_u128(0x200), guild("Alpha", 1, 5, 0x1)),
+        (Uuid::from_u128(0x300), guild("gamma", 3, 1, 0x2)),
+        // Ties with 0x100 on members and base camp level
+        (Uuid::from_u128(0x400), guild("delta", 2, 3, 0x0)),
+    ];
+    let order = |sort: Option<GuildSortKey>, reverse: bool| -> Vec<u128> {
+        list_guilds(&groups, sort, reverse)
+            .iter()
+            .map(|entry| entry.guild_id.as_u128())
+            .collect()
+    };
+
+    assert_eq!(order(None, false), [0x100, 0x200, 0x300, 0x400]);
+    assert_eq!(
+        order(Some(GuildSortKey::Name), false),
+        [0x200, 0x100, 0x400, 0x300]
+    );
+    assert_eq!(
+        order(Some(GuildSortKey::Name), true),
+        [0x300, 0x400, 0x100, 0x200]
+    );
+    assert_eq!(
+        order(Some(GuildSortKey::Members), false),
+        [0x200, 0x100, 0x400, 0x300]
+    );
+    // Ties keep their map order when reversed too
+    assert_eq!(
+        order(Some(GuildSortKey::Members), true),
+        [0x300, 0x100, 0x400, 0x200]
+    );
+    assert_eq!(
+        order(Some(GuildSortKey::Basecamp), false),
+        [0x300, 0x100, 0x400, 0x200]
+    );
+    assert_eq!(
+        order(Some(GuildSortKey::Basecamp), true),
+        [0x200, 0x100, 0x400, 0x300]
+    );
+    assert_eq!(
+        order(Some(GuildSortKey::Admin), false),
+        [0x400, 0x200, 0x300, 0x100]
+    );
+    assert_eq!(
+        order(Some(GuildSortKey::Admin), true),
+        [0x100, 0x300, 0x200, 0x400]
+    );
+
+    let entries = list_guilds(&groups, None, false);
+    assert_eq!(
+        entries[0],
+        GuildListEntry {
+            guild_id: Uuid::from_u128(0x100),
+            name: "beta".to_string(),
+            member_count: 2,
+            base_camp_level: 3,
+            admin_player_uid: Uuid::from_u128(0x3),
+        }
+    );
+}
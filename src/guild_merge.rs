@@ -0,0 +1,235 @@
+use anyhow::Context;
+use uuid::Uuid;
+
+use crate::{
+    character_save_parameter::write_raw_character_save_parameter,
+    group_guild::{merge_group_guild_save, write_group_guild_save, GameVersion, GroupGuildSave},
+    patch::{Patch, PatchOp},
+    sav::{
+        get_character_key_ids, get_character_save_parameter_map, get_group_save_data_map,
+        is_group_type_guild, parse_raw_character_save_parameter, parse_raw_group_guild_save,
+        PalSave,
+    },
+};
+
+/// Returns the index in `GroupSaveDataMap` and the parsed data of the guild `guild_id`.
+pub fn find_guild(
+    level_save: &PalSave,
+    game_version: GameVersion,
+    guild_id: Uuid,
+) -> anyhow::Result<(usize, GroupGuildSave)> {
+    let index = get_group_save_data_map(level_save)
+        .iter()
+        .position(|entry| {
+            matches!(
+                entry.key,
+                uesave::PropertyValue::Struct(uesave::StructValue::Guid(id)) if id == guild_id
+            ) && is_group_type_guild(entry)
+        })
+        .with_context(|| format!("Guild {} not found", guild_id))?;
+    let group =
+        parse_raw_group_guild_save(&get_group_save_data_map(level_save)[index], game_version)
+            .with_context(|| format!("Failed to parse guild {}", guild_id))?;
+    Ok((index, group))
+}
+
+/// Moves the members, instances and characters of the guild `source` to `destination`, then
+/// removes `source` from `GroupSaveDataMap`. `destination` keeps its name and admin. Returns the
+/// InstanceIds of the characters whose group id was changed.
+#[cfg(test)]
+pub fn merge_guild_into(
+    level_save: &mut PalSave,
+    game_version: GameVersion,
+    source: Uuid,
+    destination: Uuid,
+) -> anyhow::Result<Vec<Uuid>> {
+    use crate::patch::apply_patch;
+
+    let (patch, moved_characters, _) =
+        merge_guild_patch(level_save, game_version, source, destination, false)?;
+    apply_patch(level_save, &patch)?;
+    Ok(moved_characters)
+}
+
+/// The changes that merge the guild `source` into `destination`, without making them, the
+/// InstanceIds of the characters they move and the characters skipped with `keep_going`.
+/// `destination` keeps its name and admin.
+///
+/// A character whose `RawData` cannot be parsed might belong to `source`, and would be left in
+/// a removed guild, so it fails the merge. With `keep_going` it is skipped and listed instead.
+pub fn merge_guild_patch(
+    level_save: &PalSave,
+    game_version: GameVersion,
+    source: Uuid,
+    destination: Uuid,
+    keep_going: bool,
+) -> anyhow::Result<(Patch, Vec<Uuid>, Vec<String>)> {
+    if source == destination {
+        return Err(anyhow::anyhow!("Cannot merge guild {} into itself", source));
+    }
+    let (_, source_group) = find_guild(level_save, game_version, source)?;
+    let (_, mut destination_group) = find_guild(level_save, game_version, destination)?;
+
+    let header = &level_save.save.header;
+    let mut unparsable_characters = Vec::new();
+    let moved_characters: Vec<(Uuid, Vec<u8>)> = get_character_save_parameter_map(level_save)
+        .iter()
+        .filter_map(|entry| {
+            let (_, instance_id) = get_character_key_ids(entry)?;
+            let mut character_save_parameter =
+                match parse_raw_character_save_parameter(header)(entry) {
+                    Ok(character_save_parameter) => character_save_parameter,
+                    Err(error) => {
+                        unparsable_characters
+                            .push(format!("character {}: {:#}", instance_id, error));
+                        return None;
+                    }
+                };
+            if character_save_parameter.group_id != source {
+                return None;
+            }
+            character_save_parameter.group_id = destination;
+            Some((
+                instance_id,
+                write_raw_character_save_parameter(header)(&character_save_parameter),
+            ))
+        })
+        .collect();
+    if !unparsable_characters.is_empty() && !keep_going {
+        return Err(anyhow::anyhow!(
+            "{} characters could not be parsed, so they might be left in the removed guild {}. \
+             Nothing was changed; skip them with --keep-going:\n{}",
+            unparsable_characters.len(),
+            source,
+            unparsable_characters
+                .iter()
+                .map(|character| format!("- {}", character))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    unparsable_characters
+        .iter()
+        .for_each(|character| info!("{}, skipping", character));
+
+    merge_group_guild_save(&mut destination_group, &source_group);
+    let mut ops = vec![
+        PatchOp::SetGuildRawData {
+            guild_id: destination,
+            raw_data: write_group_guild_save(&destination_group),
+        },
+        PatchOp::RemoveGuild { guild_id: source },
+    ];
+    let moved_instance_ids = moved_characters
+        .iter()
+        .map(|(instance_id, _)| *instance_id)
+        .collect();
+    ops.extend(moved_characters.into_iter().map(|(instance_id, raw_data)| {
+        PatchOp::SetCharacterRawData {
+            instance_id,
+            raw_data,
+        }
+    }));
+    Ok((Patch::new(ops), moved_instance_ids, unparsable_characters))
+}
+
+#[test]
+pub fn test_merge_guild_into() {
+    use crate::{
+        fixtures,
+        group_guild::{is_instance_order_in_sync, FPalInstanceId},
+        sav::{get_character_save_parameter_map_mut, get_guilds, get_raw_data, set_raw_data},
+    };
+
+    let destination = Uuid::from_u128(0x100);
+    let source = Uuid::from_u128(0x200);
+    let mut level_save = fixtures::read_level_save();
+    let destination_group = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, destination, &destination_group);
+
+    // A source guild of two new players, each with a character in that guild
+    let mut source_group = fixtures::read_guild();
+    source_group.GuildName = "Source".into();
+    source_group.GuildPlayerInfo.truncate(2);
+    source_group.GuildPlayerInfo[0].PlayerUId = Uuid::from_u128(0x1001);
+    source_group.GuildPlayerInfo[1].PlayerUId = Uuid::from_u128(0x1002);
+    source_group.AdminPlayerUId = Uuid::from_u128(0x1001);
+    source_group.InstanceIds = vec![
+        FPalInstanceId {
+            PlayerUId: Uuid::from_u128(0x1001),
+            InstanceUId: Uuid::from_u128(0x2001),
+        },
+        FPalInstanceId {
+            PlayerUId: Uuid::from_u128(0x1002),
+            InstanceUId: Uuid::from_u128(0x2002),
+        },
+    ];
+    fixtures::add_guild(&mut level_save, source, &source_group);
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    fixtures::add_character_save(
+        &mut level_save,
+        Uuid::from_u128(0x1001),
+        Uuid::from_u128(0x2001),
+    );
+    fixtures::add_character_save(
+        &mut level_save,
+        Uuid::from_u128(0x1002),
+        Uuid::from_u128(0x2002),
+    );
+    fixtures::set_character_group_id(&mut level_save, 0, source);
+    fixtures::set_character_group_id(&mut level_save, 1, source);
+
+    assert!(merge_guild_into(&mut level_save, GameVersion::V0_1_3, source, source).is_err());
+
+    // A character that cannot be parsed might be in the source guild, so it stops the merge
+    let unparsable = Uuid::from_u128(0x2003);
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(0x1003), unparsable);
+    let entry = &mut get_character_save_parameter_map_mut(&mut level_save)[2];
+    let truncated = get_raw_data(entry).unwrap()[..10].to_vec();
+    set_raw_data(entry, truncated);
+    let original_level_save = level_save.clone();
+    let error = merge_guild_into(&mut level_save, GameVersion::V0_1_3, source, destination)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.starts_with("1 characters could not be parsed"),
+        "{}",
+        error
+    );
+    assert!(error.contains(&unparsable.to_string()), "{}", error);
+    assert_eq!(level_save, original_level_save);
+    let (_, moved, skipped) =
+        merge_guild_patch(&level_save, GameVersion::V0_1_3, source, destination, true).unwrap();
+    assert_eq!(moved.len(), 2);
+    assert_eq!(skipped.len(), 1);
+    assert!(skipped[0].contains(&unparsable.to_string()));
+    get_character_save_parameter_map_mut(&mut level_save).remove(2);
+
+    let moved =
+        merge_guild_into(&mut level_save, GameVersion::V0_1_3, source, destination).unwrap();
+    assert_eq!(
+        moved,
+        vec![Uuid::from_u128(0x2001), Uuid::from_u128(0x2002)]
+    );
+
+    let groups = get_guilds(&level_save, GameVersion::V0_1_3).unwrap();
+    assert_eq!(groups.len(), 1);
+    let (guild_id, group) = &groups[0];
+    assert_eq!(*guild_id, destination);
+    assert_eq!(group.GuildName, destination_group.GuildName);
+    assert_eq!(group.AdminPlayerUId, destination_group.AdminPlayerUId);
+    assert_eq!(
+        group.GuildPlayerInfo.len(),
+        destination_group.GuildPlayerInfo.len() + 2
+    );
+    assert_eq!(
+        group.InstanceIds.len(),
+        destination_group.InstanceIds.len() + 2
+    );
+    assert!(is_instance_order_in_sync(group));
+    assert_eq!(fixtures::character_group_id(&level_save, 0), destination);
+    assert_eq!(fixtures::character_group_id(&level_save, 1), destination);
+
+    // The source is gone
+    assert!(merge_guild_into(&mut level_save, GameVersion::V0_1_3, source, destination).is_err());
+}
@@ -0,0 +1,77 @@
+use uuid::Uuid;
+
+use crate::{
+    group_guild::GameVersion,
+    patch::{Patch, PatchOp},
+    sav::{get_guilds_skipping_failures, PalSave},
+};
+
+/// Returns the id and name of every guild without members and without `InstanceIds`, in
+/// `GroupSaveDataMap` order. Guilds that cannot be parsed are never considered empty.
+pub fn find_empty_guilds(level_save: &PalSave, version: GameVersion) -> Vec<(Uuid, String)> {
+    let (guilds, _) = get_guilds_skipping_failures(level_save, version);
+    guilds
+        .into_iter()
+        .filter(|(_, group)| group.GuildPlayerInfo.is_empty() && group.InstanceIds.is_empty())
+        .map(|(guild_id, group)| (guild_id, group.GuildName))
+        .collect()
+}
+
+/// The change that removes every guild [`find_empty_guilds`] returns, and those guilds.
+pub fn prune_empty_guilds_patch(
+    level_save: &PalSave,
+    version: GameVersion,
+) -> (Patch, Vec<(Uuid, String)>) {
+    let empty_guilds = find_empty_guilds(level_save, version);
+    let patch = Patch::new(
+        empty_guilds
+            .iter()
+            .map(|(guild_id, _)| PatchOp::RemoveGuild {
+                guild_id: *guild_id,
+            })
+            .collect(),
+    );
+    (patch, empty_guilds)
+}
+
+#[test]
+pub fn test_prune_empty_guilds() {
+    use crate::{
+        fixtures,
+        patch::apply_patch,
+        sav::{get_group_save_data_map, get_guilds},
+    };
+
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    let mut empty_guild = group_guild_save.clone();
+    empty_guild.GuildPlayerInfo.clear();
+    empty_guild.InstanceIds.clear();
+    empty_guild.GuildName = "Empty".to_string();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x200), &empty_guild);
+    // No members, but pals still reference it
+    let mut guild_with_pals = empty_guild.clone();
+    guild_with_pals.InstanceIds = group_guild_save.InstanceIds.clone();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x300), &guild_with_pals);
+    let group_count = get_group_save_data_map(&level_save).len();
+
+    let (patch, empty_guilds) = prune_empty_guilds_patch(&level_save, GameVersion::V0_1_3);
+    assert_eq!(
+        empty_guilds,
+        vec![(Uuid::from_u128(0x200), "Empty".to_string())]
+    );
+    apply_patch(&mut level_save, &patch).unwrap();
+
+    let guild_ids: Vec<Uuid> = get_guilds(&level_save, GameVersion::V0_1_3)
+        .unwrap()
+        .into_iter()
+        .map(|(guild_id, _)| guild_id)
+        .collect();
+    assert_eq!(
+        guild_ids,
+        vec![Uuid::from_u128(0x100), Uuid::from_u128(0x300)]
+    );
+    // Groups that are not guilds are kept
+    assert_eq!(get_group_save_data_map(&level_save).len(), group_count - 1);
+}
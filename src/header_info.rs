@@ -0,0 +1,155 @@
+use std::{io::Write, path::Path};
+
+use anyhow::Context;
+use serde::Serialize;
+
+use crate::sav::{describe_compression_byte, read_sav_prefix, PalSave, SavPrefix};
+
+/// Save metadata for bug reports. It contains no game data.
+#[derive(Debug, Serialize)]
+pub struct HeaderInfo<'a> {
+    #[serde(flatten)]
+    pub prefix: SavPrefix,
+    pub header: &'a uesave::Header,
+}
+
+pub fn read_header_info<'a>(path: &Path, pal_save: &'a PalSave) -> anyhow::Result<HeaderInfo<'a>> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let prefix =
+        read_sav_prefix(&mut file).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(HeaderInfo {
+        prefix,
+        header: &pal_save.save.header,
+    })
+}
+
+/// Writes the compression type and lengths of a .sav prefix, which can be read even when the
+/// rest of the save cannot.
+pub fn write_sav_prefix<W: Write>(writer: &mut W, prefix: &SavPrefix) -> anyhow::Result<()> {
+    writeln!(
+        writer,
+        "Compression type: {}",
+        describe_compression_byte(prefix.compression_type)
+    )?;
+    if prefix.has_lengths() {
+        writeln!(
+            writer,
+            "Decompressed length: {}",
+            prefix.decompressed_length
+        )?;
+        writeln!(writer, "Compressed length: {}", prefix.compressed_length)?;
+    } else {
+        writeln!(
+            writer,
+            "Lengths: not set (zeroed by the tool that wrote the save)"
+        )?;
+    }
+    Ok(())
+}
+
+pub fn write_header_info<W: Write>(writer: &mut W, info: &HeaderInfo) -> anyhow::Result<()> {
+    let header = info.header;
+    write_sav_prefix(writer, &info.prefix)?;
+    writeln!(writer, "Magic: {:#x}", header.magic)?;
+    writeln!(writer, "Save game version: {}", header.save_game_version)?;
+    writeln!(writer, "Package version: {:?}", header.package_version)?;
+    writeln!(
+        writer,
+        "Engine version: {}.{}.{}.{} ({})",
+        header.engine_version_major,
+        header.engine_version_minor,
+        header.engine_version_patch,
+        header.engine_version_build,
+        header.engine_version
+    )?;
+    writeln!(
+        writer,
+        "Custom format version: {}",
+        header.custom_format_version
+    )?;
+    writeln!(writer, "Custom formats: {}", header.custom_format.len())?;
+    header
+        .custom_format
+        .iter()
+        .try_for_each(|custom_format| writeln!(writer, "- {:?}", custom_format))?;
+    Ok(())
+}
+
+/// Describes how the custom formats of `header` differ from those of Level.sav's `level_header`,
+/// or `None` when they are the same. The saves of one world share them, so a player save that
+/// differs most likely comes from another game version.
+pub fn custom_format_mismatch(
+    header: &uesave::Header,
+    level_header: &uesave::Header,
+) -> Option<String> {
+    if header.custom_format_version != level_header.custom_format_version {
+        return Some(format!(
+            "custom format version {}, Level.sav has {}",
+            header.custom_format_version, level_header.custom_format_version
+        ));
+    }
+    if header.custom_format == level_header.custom_format {
+        return None;
+    }
+    let missing = level_header
+        .custom_format
+        .iter()
+        .filter(|custom_format| !header.custom_format.contains(custom_format))
+        .count();
+    let extra = header
+        .custom_format
+        .iter()
+        .filter(|custom_format| !level_header.custom_format.contains(custom_format))
+        .count();
+    Some(format!(
+        "{} custom formats, {} of Level.sav's {} missing and {} others",
+        header.custom_format.len(),
+        missing,
+        level_header.custom_format.len(),
+        extra
+    ))
+}
+
+#[test]
+pub fn test_custom_format_mismatch() {
+    use crate::sav::read_save_file_path;
+
+    let level_header = read_save_file_path(Path::new("assets/Level.sav"))
+        .unwrap()
+        .save
+        .header;
+    assert_eq!(custom_format_mismatch(&level_header, &level_header), None);
+
+    let mut header = level_header.clone();
+    header.custom_format.pop();
+    assert_eq!(
+        custom_format_mismatch(&header, &level_header).unwrap(),
+        "68 custom formats, 1 of Level.sav's 69 missing and 0 others"
+    );
+    header.custom_format_version += 1;
+    assert_eq!(
+        custom_format_mismatch(&header, &level_header).unwrap(),
+        "custom format version 4, Level.sav has 3"
+    );
+}
+
+#[test]
+pub fn test_write_header_info() {
+    use crate::sav::read_save_file_path;
+
+    let path = Path::new("assets/Level.sav");
+    let pal_save = read_save_file_path(path).unwrap();
+    let info = read_header_info(path, &pal_save).unwrap();
+    let mut output = Vec::new();
+    write_header_info(&mut output, &info).unwrap();
+    let output = String::from_utf8(output).unwrap();
+
+    assert!(output.contains("Compression type: DoubleZlib (0x32)\n"));
+    assert!(output.contains("Decompressed length: 162039\n"));
+    assert!(output.contains("Compressed length: 13189\n"));
+    assert!(output.contains("Save game version: 3\n"));
+    assert!(output.contains("Engine version: 5.1.1.0 (++UE5+Release-5.1)\n"));
+    assert!(output.contains("Custom format version: 3\n"));
+    assert!(output.contains("Custom formats: 69\n"));
+}
@@ -0,0 +1,67 @@
+/// Number of bytes shown before and after the failure offset.
+pub const HEX_DUMP_RADIUS: usize = 32;
+
+const ROW_LEN: usize = 16;
+
+/// Formats the bytes of `data` within [`HEX_DUMP_RADIUS`] of `offset` as hex and ASCII rows, with
+/// a `^^` marker under the byte at `offset`. Truncated input stops at the end of `data`, where
+/// there is no byte to mark.
+pub fn hex_dump(data: &[u8], offset: usize) -> String {
+    let offset = offset.min(data.len());
+    let start = offset.saturating_sub(HEX_DUMP_RADIUS) / ROW_LEN * ROW_LEN;
+    let end = (offset + HEX_DUMP_RADIUS).min(data.len());
+
+    let mut dump = String::new();
+    let mut row_start = start;
+    loop {
+        let row = &data[row_start..(row_start + ROW_LEN).min(end)];
+        let hex: Vec<String> = row.iter().map(|byte| format!("{:02x}", byte)).collect();
+        let ascii: String = row
+            .iter()
+            .map(|byte| {
+                if byte.is_ascii_graphic() || *byte == b' ' {
+                    *byte as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        dump.push_str(&format!(
+            "{:08x}  {:<width$}  |{}|\n",
+            row_start,
+            hex.join(" "),
+            ascii,
+            width = ROW_LEN * 3 - 1
+        ));
+        if (row_start..row_start + ROW_LEN).contains(&offset) {
+            dump.push_str(&format!(
+                "{}^^\n",
+                " ".repeat(10 + (offset - row_start) * 3)
+            ));
+        }
+        row_start += ROW_LEN;
+        if row_start >= end {
+            break;
+        }
+    }
+    dump
+}
+
+#[test]
+pub fn test_hex_dump_marks_offset() {
+    let data: Vec<u8> = (0..=0xff).collect();
+    let dump = hex_dump(&data, 0x45);
+    let lines: Vec<&str> = dump.lines().collect();
+    assert!(lines.first().unwrap().starts_with("00000020"));
+    assert!(lines.last().unwrap().starts_with("00000060"));
+    let row = lines
+        .iter()
+        .position(|line| line.starts_with("00000040"))
+        .unwrap();
+    let column = lines[row + 1].find("^^").unwrap();
+    assert_eq!(&lines[row][column..column + 2], "45");
+    assert!(lines[row].ends_with("|@ABCDEFGHIJKLMNO|"));
+
+    let dump = hex_dump(&data[..0x10], 0x10);
+    assert_eq!(dump.lines().count(), 1);
+}
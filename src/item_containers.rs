@@ -0,0 +1,145 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::sav::{get_map_entries, PalSave};
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ItemContainerSummary {
+    /// `ID` of the container key, when it could be read
+    pub id: Option<Uuid>,
+    /// Length of `Slots`, or `SlotNum` when there is no `Slots` array
+    pub slot_count: Option<usize>,
+}
+
+/// The containers of `ItemContainerSaveData` and how many slots they have.
+#[derive(Debug, Serialize)]
+pub struct ItemContainerReport {
+    pub container_count: usize,
+    /// Slots of the containers whose slot count is known
+    pub slot_count: usize,
+    /// Containers with neither a `Slots` array nor a `SlotNum`
+    pub containers_without_slot_count: usize,
+    /// Every container, in map order
+    pub containers: Vec<ItemContainerSummary>,
+}
+
+/// Reads the container ids and slot counts of `ItemContainerSaveData`. Nothing is changed and
+/// item `RawData` is not decoded. A save without item containers has an empty report.
+pub fn list_item_containers(level_save: &PalSave) -> ItemContainerReport {
+    let containers: Vec<ItemContainerSummary> =
+        get_map_entries(level_save, "ItemContainerSaveData")
+            .iter()
+            .map(|entry| ItemContainerSummary {
+                id: item_container_id(&entry.key),
+                slot_count: item_container_slot_count(&entry.value),
+            })
+            .collect();
+    ItemContainerReport {
+        container_count: containers.len(),
+        slot_count: containers
+            .iter()
+            .filter_map(|container| container.slot_count)
+            .sum(),
+        containers_without_slot_count: containers
+            .iter()
+            .filter(|container| container.slot_count.is_none())
+            .count(),
+        containers,
+    }
+}
+
+/// The key is a Guid, or a struct with an `ID` Guid when its type is registered as `Struct`.
+fn item_container_id(key: &uesave::PropertyValue) -> Option<Uuid> {
+    match key {
+        uesave::PropertyValue::Struct(uesave::StructValue::Guid(id)) => Some(*id),
+        uesave::PropertyValue::Struct(uesave::StructValue::Struct(properties)) => {
+            match properties.get("ID") {
+                Some(uesave::Property::Struct {
+                    value: uesave::StructValue::Guid(id),
+                    ..
+                }) => Some(*id),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn item_container_slot_count(value: &uesave::PropertyValue) -> Option<usize> {
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(properties)) = value else {
+        return None;
+    };
+    match (properties.get("Slots"), properties.get("SlotNum")) {
+        (
+            Some(uesave::Property::Array {
+                value: uesave::ValueArray::Struct { value: slots, .. },
+                ..
+            }),
+            _,
+        ) => Some(slots.len()),
+        (_, Some(uesave::Property::Int { value, .. })) => usize::try_from(*value).ok(),
+        _ => None,
+    }
+}
+
+pub fn print_item_container_report(report: &ItemContainerReport) {
+    println!("Item containers: {}", report.container_count);
+    println!("Slots: {}", report.slot_count);
+    if report.containers_without_slot_count > 0 {
+        println!(
+            "Containers without a slot count: {}",
+            report.containers_without_slot_count
+        );
+    }
+}
+
+#[test]
+pub fn test_list_item_containers() {
+    use crate::{fixtures, sav::get_world_save_data_mut};
+
+    let mut level_save = fixtures::read_level_save();
+    let report = list_item_containers(&level_save);
+    assert_eq!(report.container_count, 0);
+    assert_eq!(report.slot_count, 0);
+
+    // A map shaped like GroupSaveDataMap, with a SlotNum in each value
+    let world_save_data = get_world_save_data_mut(&mut level_save);
+    let mut item_container_save_data = world_save_data["GroupSaveDataMap"].clone();
+    let uesave::Property::Map { value: entries, .. } = &mut item_container_save_data else {
+        panic!()
+    };
+    entries.truncate(2);
+    entries.iter_mut().enumerate().for_each(|(index, entry)| {
+        entry.key = uesave::PropertyValue::Struct(uesave::StructValue::Guid(Uuid::from_u128(
+            index as u128,
+        )));
+        let uesave::PropertyValue::Struct(uesave::StructValue::Struct(properties)) =
+            &mut entry.value
+        else {
+            panic!()
+        };
+        properties.clear();
+        if index == 0 {
+            properties.insert(
+                "SlotNum".into(),
+                uesave::Property::Int {
+                    id: None,
+                    value: 42,
+                },
+            );
+        }
+    });
+    world_save_data.insert("ItemContainerSaveData".into(), item_container_save_data);
+
+    let report = list_item_containers(&level_save);
+    assert_eq!(report.container_count, 2);
+    assert_eq!(report.slot_count, 42);
+    assert_eq!(report.containers_without_slot_count, 1);
+    assert_eq!(
+        report.containers[0],
+        ItemContainerSummary {
+            id: Some(Uuid::from_u128(0)),
+            slot_count: Some(42),
+        }
+    );
+}
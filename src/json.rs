@@ -0,0 +1,57 @@
+use std::io::{IsTerminal, Write};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Formatting of JSON output.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonStyle {
+    /// Indented, for reading
+    Pretty,
+    /// One line, for piping and storage
+    Compact,
+}
+
+impl JsonStyle {
+    /// Pretty when stdout is a terminal, compact when it is piped or redirected.
+    pub fn detect() -> JsonStyle {
+        if std::io::stdout().is_terminal() {
+            JsonStyle::Pretty
+        } else {
+            JsonStyle::Compact
+        }
+    }
+}
+
+pub fn write_json<W: Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+    style: JsonStyle,
+) -> anyhow::Result<()> {
+    match style {
+        JsonStyle::Pretty => serde_json::to_writer_pretty(writer, value)?,
+        JsonStyle::Compact => serde_json::to_writer(writer, value)?,
+    }
+    Ok(())
+}
+
+#[test]
+pub fn test_write_json_styles_round_trip() {
+    use crate::sav::read_save_file;
+
+    let pal_save = read_save_file(std::fs::File::open("assets/Level.sav").unwrap()).unwrap();
+
+    let mut pretty = Vec::new();
+    write_json(&mut pretty, &pal_save.save, JsonStyle::Pretty).unwrap();
+    let mut compact = Vec::new();
+    write_json(&mut compact, &pal_save.save, JsonStyle::Compact).unwrap();
+    assert!(pretty.contains(&b'\n'));
+    assert!(!compact.contains(&b'\n'));
+    assert!(compact.len() < pretty.len());
+
+    let pretty: serde_json::Value = serde_json::from_slice(&pretty).unwrap();
+    let compact: serde_json::Value = serde_json::from_slice(&compact).unwrap();
+    assert_eq!(pretty, compact);
+    let save: uesave::Save = serde_json::from_value(compact).unwrap();
+    assert_eq!(save, pal_save.save);
+}
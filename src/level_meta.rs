@@ -0,0 +1,50 @@
+use std::collections::HashMap;
+
+use indexmap::IndexMap;
+use uuid::Uuid;
+
+use crate::sav::PalSave;
+
+/// PlayerUId of the player hosting a co-op world.
+pub const HOST_PLAYER_UID: Uuid = Uuid::from_u128(1);
+
+/// Returns the `SaveData` properties of LevelMeta.sav (`WorldName`, `HostPlayerName`, ...).
+pub fn get_level_meta_save_data(pal_save: &PalSave) -> Option<&IndexMap<String, uesave::Property>> {
+    match pal_save.save.root.properties.get("SaveData") {
+        Some(uesave::Property::Struct {
+            value: uesave::StructValue::Struct(save_data),
+            ..
+        }) => Some(save_data),
+        _ => None,
+    }
+}
+
+/// Returns the player names LevelMeta.sav knows by PlayerUId. The game updates it on every
+/// save, so it is fresher than the `PlayerName` of a guild. It only records the host of a
+/// co-op world.
+pub fn get_level_meta_player_names(pal_save: &PalSave) -> HashMap<Uuid, String> {
+    let mut player_names = HashMap::new();
+    if let Some(uesave::Property::Str { value, .. }) =
+        get_level_meta_save_data(pal_save).and_then(|save_data| save_data.get("HostPlayerName"))
+    {
+        if !value.is_empty() {
+            player_names.insert(HOST_PLAYER_UID, value.clone());
+        }
+    }
+    player_names
+}
+
+#[test]
+pub fn test_get_level_meta_player_names() {
+    use crate::fixtures;
+
+    let level_save = fixtures::read_level_save();
+    assert!(get_level_meta_save_data(&level_save).is_none());
+    assert!(get_level_meta_player_names(&level_save).is_empty());
+
+    let level_meta = fixtures::level_meta_save(&level_save, "Host");
+    assert_eq!(
+        get_level_meta_player_names(&level_meta),
+        HashMap::from([(HOST_PLAYER_UID, "Host".to_string())])
+    );
+}
@@ -0,0 +1,20 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Prints progress to stderr unless `--quiet` is set. Stdout is kept for machine-readable output.
+macro_rules! info {
+    ($($arg:tt)*) => {
+        if !$crate::log::is_quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
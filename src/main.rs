@@ -1,226 +1,1824 @@
+#[macro_use]
+pub mod log;
+
+pub mod anonymize;
+pub mod batch_report;
+pub mod bounded_read;
+pub mod character_bin;
+pub mod character_rekey;
 pub mod character_save_parameter;
+pub mod color;
+pub mod confirm;
+pub mod debug_names;
+pub mod diagnose_types;
+pub mod error_dump;
+pub mod events;
+pub mod find_player;
+pub mod fix_report;
+#[cfg(test)]
+pub mod fixtures;
+pub mod fstring_repair;
 pub mod group_guild;
+pub mod group_reconcile;
+pub mod guild_list;
+pub mod guild_merge;
+pub mod guild_prune;
+pub mod header_info;
+pub mod hex_dump;
+pub mod item_containers;
+pub mod json;
+pub mod level_meta;
+pub mod make_fixtures;
+pub mod pal_count;
+pub mod parse_timeout;
+pub mod patch;
+pub mod player_bundle;
+pub mod remediation;
 pub mod sav;
+pub mod save_directory;
+pub mod save_hash;
+pub mod save_store;
+pub mod size_report;
+pub mod stats;
+pub mod tar_archive;
+pub mod template;
+pub mod uesave_json;
 
-use std::{collections::HashSet, io::BufWriter, path::PathBuf};
+use std::{
+    collections::HashMap,
+    io::{BufWriter, Cursor},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
-use clap::Parser as ClapParser;
-use indexmap::IndexMap;
-use sav::read_save_file;
+use anyhow::Context;
+use clap::{Parser as ClapParser, Subcommand, ValueEnum};
 use uuid::Uuid;
 
 use crate::{
-    character_save_parameter::{write_raw_character_save_parameter, CharacterSaveParameter},
-    group_guild::GroupGuildSave,
+    anonymize::anonymize_save_directory,
+    batch_report::{batch_report, write_batch_report, BatchReportFormat},
+    bounded_read::{read_bounded, DEFAULT_MAX_OPEN_FILES},
+    character_bin::{export_character_raw_data, import_character_patch},
+    character_rekey::rekey_character_patch,
+    character_save_parameter::write_raw_character_save_parameter,
+    color::Colors,
+    confirm::Confirm,
+    debug_names::write_debug_names,
+    diagnose_types::{diagnose_types, print_types_diagnosis},
+    error_dump::write_error_dump,
+    events::{emit, Event},
+    find_player::{find_players, print_player_matches},
+    fix_report::{write_fix_report, FixReport, ReportFormat},
+    fstring_repair::repair_fstrings_patch,
+    group_guild::{
+        fix_guild_admin, guild_admin_label, is_admin_in_guild, write_group_guild_save, GameVersion,
+        GroupGuildSave, MAX_BASE_CAMP_LEVEL,
+    },
+    group_reconcile::{check_guild_instance_ids, print_group_reconciliation, reconcile_groups},
+    guild_list::{list_guilds, print_guild_list, GuildSortKey},
+    guild_merge::{find_guild, merge_guild_patch},
+    guild_prune::prune_empty_guilds_patch,
+    header_info::{custom_format_mismatch, read_header_info, write_header_info},
+    item_containers::{list_item_containers, print_item_container_report},
+    json::{write_json, JsonStyle},
+    level_meta::get_level_meta_player_names,
+    make_fixtures::make_fixtures,
+    pal_count::{count_pals, print_pal_count_report},
+    parse_timeout::parse_with_timeout,
+    patch::{apply_patch, inverse_patch, read_patch, Patch, PatchOp},
+    player_bundle::extract_player_bundle,
+    remediation::render_remediation,
     sav::{
-        get_character_save_parameter_map, get_character_save_parameter_map_mut,
-        get_group_save_data_map, is_group_type_guild, parse_raw_group_guild_save, write_save_file,
-        PalSave,
+        check_struct_depth, check_world_save_data, detect_game_version,
+        find_player_character_in_group, get_character_debug_names, get_character_key_ids,
+        get_character_owners, get_character_save_parameter_map, get_group_save_data_map,
+        get_guilds, get_guilds_skipping_failures, get_player_individual_id, parse_compression_type,
+        parse_extra_type, parse_raw_character_save_parameter, read_save_file_path_with_extra_types,
+        read_save_file_with_extra_types, read_uncompressed_length, uncompressed_length,
+        write_save_file, write_save_file_path, CompressionType, PalSave, DEFAULT_MAX_DEPTH,
+        DEFAULT_MAX_SHRINK_PERCENT,
+    },
+    save_directory::copy_save_directory,
+    save_hash::{verify_hash, write_hash_file},
+    save_store::{FsSaveStore, SaveStore},
+    size_report::{compute_size_report, print_size_report, DEFAULT_TOP},
+    stats::{compute_guild_stats, count_player_characters, print_guild_stats, DEFAULT_MEMBER_CAP},
+    tar_archive::{is_tar_gz, read_tar_gz_save_store},
+    template::{
+        disambiguate_nicknames, load_template, pick_nickname, NicknameSource, Template,
+        DEFAULT_TEMPLATE,
     },
+    uesave_json::{read_uesave_json, write_uesave_json},
 };
 
 #[derive(ClapParser, Debug)]
-#[command(name = "palworld-guild-save-fix", about)]
+#[command(
+    name = "palworld-guild-save-fix",
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(flatten)]
+    fix: FixArgs,
+}
+
+/// Options shared by every command
+#[derive(clap::Args, Debug)]
+struct GlobalArgs {
+    /// Do not print progress messages
+    #[arg(long, short, global = true)]
+    quiet: bool,
+
+    /// Force the guild data layout of a game version instead of detecting it (known: 0.1.3)
+    #[arg(long, global = true, value_name = "TAG")]
+    game_version: Option<GameVersion>,
+
+    /// Register the struct type of a map key or value, e.g.
+    /// `.worldSaveData.GroupSaveDataMap.Key=Guid` (Guid or Struct). Can be repeated
+    #[arg(long = "type", global = true, value_name = "PATH=STRUCT", value_parser = parse_extra_type)]
+    types: Vec<(String, uesave::StructType)>,
+
+    /// Report which registered struct types each save file used
+    #[arg(long, global = true)]
+    diagnose_types: bool,
+
+    /// Do not color text output. Also set by the NO_COLOR environment variable. JSON output is
+    /// never colored
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Formatting of JSON output. Defaults to pretty when stdout is a terminal, compact otherwise
+    #[arg(long, global = true, value_enum, value_name = "STYLE")]
+    json_style: Option<JsonStyle>,
+
+    /// Write save files even when nothing in them changed
+    #[arg(long, global = true)]
+    force_write: bool,
+
+    /// Print the changes a command would make to Level.sav instead of making them
+    #[arg(long, global = true)]
+    dry_run: bool,
+
+    /// Answer yes to every confirmation prompt
+    #[arg(long, short = 'y', global = true)]
+    assume_yes: bool,
+
+    /// Fail stats and group-reconcile when they report a warning, not only when the save cannot
+    /// be read
+    #[arg(long, global = true)]
+    strict: bool,
+
+    /// Skip player saves that cannot be read (fix, repair-all) and guilds that cannot be parsed
+    /// (stats) instead of stopping at the first one, then fail listing all of them
+    #[arg(long, global = true)]
+    keep_going: bool,
+
+    /// Refuse to overwrite a save whose data would shrink by more than this percentage, which
+    /// points to a bug that dropped part of it rather than a fix
+    #[arg(
+        long,
+        global = true,
+        value_name = "PERCENT",
+        default_value_t = DEFAULT_MAX_SHRINK_PERCENT,
+        value_parser = clap::value_parser!(u8).range(0..=100),
+    )]
+    max_shrink: u8,
+
+    /// Overwrite saves even when they would shrink by more than --max-shrink
+    #[arg(long, global = true)]
+    allow_shrink: bool,
+
+    /// Refuse to walk a save whose structs nest deeper than this (to-uesave-json,
+    /// --diagnose-types), so a crafted save fails cleanly instead of overflowing the stack while
+    /// writing JSON or diagnosing types. It is checked after the save is read, so it does not
+    /// limit reading the save, in these or any other commands
+    #[arg(long, global = true, value_name = "N", default_value_t = DEFAULT_MAX_DEPTH)]
+    max_depth: usize,
+
+    /// When the command fails, write the error, the tool version and the header of the input
+    /// save to this directory, to attach to a bug report
+    #[arg(long, global = true, value_name = "DIR")]
+    dump_on_error: Option<PathBuf>,
+
+    /// After writing Level.sav, write its SHA-256 to this file, for verify-hash
+    #[arg(long, global = true, value_name = "FILE")]
+    emit_hash: Option<PathBuf>,
+
+    /// Also send progress as JSON lines to the Unix socket or Windows named pipe a front-end
+    /// listens on
+    #[cfg(feature = "events")]
+    #[arg(long, global = true, value_name = "PATH")]
+    events: Option<PathBuf>,
+}
+
+impl GlobalArgs {
+    fn read_save_file(&self, path: &Path) -> anyhow::Result<PalSave> {
+        let pal_save = read_save_file_path_with_extra_types(path, &self.types)?;
+        self.after_read(&path.display().to_string(), &pal_save);
+        Ok(pal_save)
+    }
+
+    /// Reads the Level.sav of the save directory `input`, checking that its world data is found.
+    fn read_level_save(&self, input: &Path) -> anyhow::Result<PalSave> {
+        let level_save = self.read_save_file(&input.join("Level.sav"))?;
+        check_world_save_data(&level_save)?;
+        Ok(level_save)
+    }
+
+    /// Like [`GlobalArgs::read_level_save`], but `input` can also be a `.tar.gz` backup of a save
+    /// directory, which is read without extracting it. For the commands that do not write to
+    /// `input`.
+    fn read_level_save_or_tar_gz(&self, input: &Path) -> anyhow::Result<PalSave> {
+        if !is_tar_gz(input) {
+            return self.read_level_save(input);
+        }
+        let level_save = read_tar_gz_save_store(input)?.read_level(&self.types)?;
+        self.after_read(&format!("Level.sav in {}", input.display()), &level_save);
+        Ok(level_save)
+    }
+
+    /// Prints the --diagnose-types report of a save that was just read.
+    fn after_read(&self, name: &str, pal_save: &PalSave) {
+        if self.diagnose_types {
+            if let Err(error) = check_struct_depth(pal_save, self.max_depth) {
+                info!("Struct types of {} not diagnosed: {:#}", name, error);
+                return;
+            }
+            info!("Struct types of {}", name);
+            print_types_diagnosis(&diagnose_types(pal_save, &self.types));
+        }
+    }
+
+    /// Writes `pal_save` to `path` only if `changed` is set or --force-write is passed, so
+    /// no-op runs leave the file (and its mtime) alone. Returns whether the file was written.
+    fn write_save_file_if_changed(
+        &self,
+        path: &Path,
+        pal_save: &PalSave,
+        changed: bool,
+        compression_type: CompressionType,
+    ) -> anyhow::Result<bool> {
+        if self.dry_run {
+            info!("Dry run, {} was not written", path.display());
+            return Ok(false);
+        }
+        if !changed && !self.force_write {
+            info!("No changes to write to {}", path.display());
+            return Ok(false);
+        }
+        if path.exists() {
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            let original_length = read_uncompressed_length(file)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            self.check_shrink(&path.display().to_string(), original_length, pal_save)?;
+        }
+        write_save_file_path(path, pal_save, compression_type)?;
+        info!("{} written successfully", path.display());
+        let bytes =
+            std::fs::read(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        self.emit_hash(&bytes)?;
+        Ok(true)
+    }
+
+    /// Writes the SHA-256 of the Level.sav that was just written to --emit-hash.
+    fn emit_hash(&self, level_sav: &[u8]) -> anyhow::Result<()> {
+        let Some(hash_path) = &self.emit_hash else {
+            return Ok(());
+        };
+        write_hash_file(hash_path, "Level.sav", level_sav)?;
+        info!("SHA-256 of Level.sav written to {}", hash_path.display());
+        Ok(())
+    }
+
+    /// Fails when `pal_save` would be more than --max-shrink percent smaller than the
+    /// `original_length` bytes of GVAS data it replaces, unless --allow-shrink is passed.
+    fn check_shrink(
+        &self,
+        name: &str,
+        original_length: usize,
+        pal_save: &PalSave,
+    ) -> anyhow::Result<()> {
+        if self.allow_shrink {
+            return Ok(());
+        }
+        let new_length = uncompressed_length(pal_save)?;
+        let shrink = original_length.saturating_sub(new_length);
+        if shrink * 100 > original_length * self.max_shrink as usize {
+            return Err(anyhow::anyhow!(
+                "Refusing to write {}: it would shrink from {} to {} bytes uncompressed, more than \
+                 --max-shrink {}%. Pass --allow-shrink to write it anyway",
+                name,
+                original_length,
+                new_length,
+                self.max_shrink
+            ));
+        }
+        Ok(())
+    }
+
+    /// Overwrites the Level.sav of `store`, guarded by [`GlobalArgs::check_shrink`].
+    fn write_level(
+        &self,
+        store: &mut dyn SaveStore,
+        pal_save: &PalSave,
+        compression_type: CompressionType,
+    ) -> anyhow::Result<()> {
+        if self.dry_run {
+            info!("Dry run, Level.sav was not written");
+            return Ok(());
+        }
+        self.check_shrink("Level.sav", store.level_uncompressed_length()?, pal_save)?;
+        store.write_level(pal_save, compression_type)?;
+        if self.emit_hash.is_some() {
+            self.emit_hash(&store.read_level_bytes()?)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the changes of a command to `level_save`, or with --dry-run prints them and
+    /// leaves `level_save` alone. Returns whether `level_save` changed.
+    fn apply_changes(&self, level_save: &mut PalSave, patch: &Patch) -> anyhow::Result<bool> {
+        if self.dry_run {
+            patch
+                .ops
+                .iter()
+                .for_each(|op| info!("Would: {}", op.describe()));
+            info!("Dry run, {} changes were not made", patch.ops.len());
+            return Ok(false);
+        }
+        apply_patch(level_save, patch)?;
+        Ok(!patch.ops.is_empty())
+    }
+
+    fn print_fix_report(&self, report: &FixReport, format: ReportFormat) -> anyhow::Result<()> {
+        write_fix_report(
+            &mut std::io::stdout().lock(),
+            report,
+            format,
+            self.colors(),
+            self.json_style(),
+        )
+    }
+
+    /// Fails with --strict when a check reported `warning_count` warnings, after its output.
+    fn check_warnings(&self, warning_count: usize) -> anyhow::Result<()> {
+        if self.strict && warning_count > 0 {
+            anyhow::bail!("{} warnings found and --strict is set", warning_count);
+        }
+        Ok(())
+    }
+
+    /// Parses the guilds of `level_save`. With --keep-going the guilds that cannot be parsed are
+    /// skipped and returned as failures for [`GlobalArgs::check_failures`], otherwise the first
+    /// one fails.
+    fn get_guilds(
+        &self,
+        level_save: &PalSave,
+        version: GameVersion,
+    ) -> anyhow::Result<(Vec<(Uuid, GroupGuildSave)>, Vec<String>)> {
+        if !self.keep_going {
+            return Ok((get_guilds(level_save, version)?, Vec::new()));
+        }
+        let (groups, unparsed_guilds) = get_guilds_skipping_failures(level_save, version);
+        let failures = unparsed_guilds
+            .iter()
+            .map(|(guild_id, error)| format!("Guild {}: {:#}", guild_id, error))
+            .collect();
+        Ok((groups, failures))
+    }
+
+    /// Fails listing `failures`, the errors --keep-going skipped, after the output of the command.
+    fn check_failures(&self, failures: &[String]) -> anyhow::Result<()> {
+        if failures.is_empty() {
+            return Ok(());
+        }
+        anyhow::bail!(
+            "{} failures skipped with --keep-going:\n{}",
+            failures.len(),
+            failures
+                .iter()
+                .map(|failure| format!("- {}", failure))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    /// Confirmation prompts answered on stdin, or skipped by --assume-yes.
+    fn confirm(&self) -> Confirm {
+        Confirm::stdin(self.assume_yes)
+    }
+
+    /// Colors of text output, when stdout is a terminal and --no-color is not passed.
+    fn colors(&self) -> Colors {
+        Colors::detect(self.no_color)
+    }
+
+    fn json_style(&self) -> JsonStyle {
+        self.json_style.unwrap_or_else(JsonStyle::detect)
+    }
+
+    fn game_version(&self, level_save: &PalSave) -> GameVersion {
+        let game_version = self
+            .game_version
+            .unwrap_or_else(|| detect_game_version(&level_save.save.header));
+        info!("Using guild data layout {}", game_version);
+        game_version
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Convert a .sav file to uesave JSON
+    ToUesaveJson {
+        /// Input .sav file
+        input: PathBuf,
+        /// Output JSON file (stdout if omitted)
+        output: Option<PathBuf>,
+    },
+    /// Convert uesave JSON back to a .sav file
+    FromUesaveJson {
+        /// Input JSON file
+        input: PathBuf,
+        /// Output .sav file
+        output: PathBuf,
+        /// Compression type of the output: 0x30 (None), 0x31 (Zlib) or 0x32 (DoubleZlib)
+        #[arg(long, default_value = "0x32", value_parser = parse_compression_type)]
+        compression_type: CompressionType,
+    },
+    /// Print guild statistics (member count distribution, base camp levels, players)
+    Stats {
+        /// Input directory containing Level.sav, or a .tar.gz backup of one
+        input: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Report guilds with more members than this
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_MEMBER_CAP)]
+        member_cap: usize,
+        /// Also print each guild and member name with its raw FString bytes in hex and whether
+        /// it was stored as unicode, to diagnose garbled names
+        #[arg(long)]
+        debug_names: bool,
+    },
+    /// Print how many bytes the RawData of characters and groups take in Level.sav, and the
+    /// largest ones
+    SizeReport {
+        /// Input directory containing Level.sav, or a .tar.gz backup of one
+        input: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Number of the largest RawData to list
+        #[arg(long, value_name = "N", default_value_t = DEFAULT_TOP)]
+        top: usize,
+    },
+    /// Print how many item containers Level.sav has and how many slots they have, without changing
+    /// anything
+    ListItemContainers {
+        /// Input directory containing Level.sav, or a .tar.gz backup of one
+        input: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print the compression type, sizes and compression ratio of the Level.sav of every world in
+    /// a directory
+    BatchReport {
+        /// Directory of world directories, or a world directory itself
+        input: PathBuf,
+        #[arg(long, value_enum, default_value_t = BatchReportFormat::Table)]
+        format: BatchReportFormat,
+    },
+    /// Check a .sav file against the SHA-256 written by --emit-hash
+    VerifyHash { input: PathBuf, hash_file: PathBuf },
+    /// Print the compression and header metadata of a .sav file for bug reports
+    HeaderInfo {
+        /// Input .sav file
+        input: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Apply every non-destructive fix (missing character saves, guild admins, character group
+    /// ids) with a single backup and a single write
+    RepairAll {
+        /// Input directory containing the save files (Level.sav and Players directory)
+        input: PathBuf,
+        /// Write the repaired save files to this directory instead of overwriting the input
+        #[arg(long, value_name = "PATH")]
+        output_dir: Option<PathBuf>,
+        /// Allow --output-dir to overwrite a non-empty directory
+        #[arg(long)]
+        force: bool,
+        /// Format of the printed report of the changes
+        #[arg(long, value_enum, value_name = "FORMAT", default_value_t = ReportFormat::Text)]
+        report_format: ReportFormat,
+    },
+    /// Extract one player's save, guild and characters into a small save directory that
+    /// reproduces their issue without sharing the whole server
+    ExtractPlayerBundle {
+        /// Input directory containing the save files (Level.sav and Players directory)
+        input: PathBuf,
+        /// PlayerUId of the player
+        player: Uuid,
+        /// Output directory of the bundle
+        output: PathBuf,
+        /// Allow overwriting a non-empty output directory
+        #[arg(long)]
+        force: bool,
+    },
+    /// Apply a patch written by --emit-patch to Level.sav
+    ApplyPatch {
+        /// Input directory containing Level.sav
+        input: PathBuf,
+        /// Patch file
+        patch: PathBuf,
+        /// Copy Level.sav to Level.sav.bak before overwriting it
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Undo a fix with the log written by --undo-log
+    Undo {
+        /// Undo log file
+        log: PathBuf,
+        /// Input directory containing Level.sav
+        input: PathBuf,
+        /// Copy Level.sav to Level.sav.bak before overwriting it
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Rename a guild in Level.sav
+    RenameGuild {
+        /// Input directory containing Level.sav
+        input: PathBuf,
+        /// Group id of the guild
+        #[arg(long, value_name = "UUID")]
+        guild: Uuid,
+        /// New guild name
+        #[arg(long)]
+        name: String,
+    },
+    /// Set the BaseCampLevel of a guild in Level.sav, e.g. when it is stale after its bases were
+    /// deleted. 0 clears it
+    SetBaseCampLevel {
+        /// Input directory containing Level.sav
+        input: PathBuf,
+        /// Group id of the guild
+        guild: Uuid,
+        /// New base camp level
+        #[arg(value_parser = clap::value_parser!(u32).range(0..=MAX_BASE_CAMP_LEVEL as i64))]
+        level: u32,
+    },
+    /// Write a copy of a save with player, character and guild names replaced by pseudonyms
+    Anonymize {
+        /// Input directory containing the save files
+        input: PathBuf,
+        /// Output directory for the anonymized save
+        output: PathBuf,
+        /// Also replace PlayerUIds, consistently across Level.sav and the player saves
+        #[arg(long)]
+        remap_uids: bool,
+        /// Allow overwriting a non-empty output directory
+        #[arg(long)]
+        force: bool,
+    },
+    /// Move the members and characters of a guild to another guild and delete it from Level.sav
+    MergeGuilds {
+        /// Input directory containing Level.sav
+        input: PathBuf,
+        /// Group id of the guild to delete
+        source: Uuid,
+        /// Group id of the guild that receives the members, keeping its name and admin
+        destination: Uuid,
+        /// Copy Level.sav to Level.sav.bak before overwriting it
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Delete the guilds without members and without InstanceIds from Level.sav
+    PruneEmptyGuilds {
+        /// Input directory containing Level.sav
+        input: PathBuf,
+        /// Copy Level.sav to Level.sav.bak before overwriting it
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Rewrite the guilds whose FStrings have a length that does not end at their terminator, with
+    /// the right lengths
+    RepairFstrings {
+        /// Input directory containing Level.sav
+        input: PathBuf,
+        /// Copy Level.sav to Level.sav.bak before overwriting it
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Compare the group ids of characters with the groups in Level.sav, listing group ids without
+    /// a group and guilds without characters
+    GroupReconcile {
+        /// Input directory containing Level.sav, or a .tar.gz backup of one
+        input: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+        /// Also check that the PlayerUId of every InstanceIds entry of a guild is one of its
+        /// members
+        #[arg(long)]
+        check_instance_ids: bool,
+    },
+    /// List the guilds with their member count, base camp level and admin
+    List {
+        /// Input directory containing Level.sav, or a .tar.gz backup of one
+        input: PathBuf,
+        /// Order the guilds by this instead of their order in Level.sav
+        #[arg(long, value_enum, value_name = "KEY")]
+        sort: Option<GuildSortKey>,
+        /// Sort in descending order
+        #[arg(long, requires = "sort")]
+        reverse: bool,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print how many pals and players each guild has, by the group id of every character
+    PalCount {
+        /// Input directory containing Level.sav, or a .tar.gz backup of one
+        input: PathBuf,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Find guild members by a part of their name or the first hex digits of their PlayerUId
+    FindPlayer {
+        /// Input directory containing Level.sav, or a .tar.gz backup of one
+        input: PathBuf,
+        /// Name substring (case-insensitive) or PlayerUId prefix
+        query: String,
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Write the RawData of a character in Level.sav to a file
+    ExportCharacterBin {
+        /// Input directory containing Level.sav, or a .tar.gz backup of one
+        input: PathBuf,
+        /// InstanceId of the character
+        instance_id: Uuid,
+        /// Output file
+        output: PathBuf,
+    },
+    /// Put a file written by export-character-bin back into Level.sav, replacing the character
+    /// with the same InstanceId or adding it
+    ImportCharacterBin {
+        /// Input directory containing Level.sav
+        input: PathBuf,
+        /// InstanceId of the character
+        instance_id: Uuid,
+        /// File written by export-character-bin
+        blob: PathBuf,
+        /// PlayerUId of the character when it is added. Without it the character is added as a pal
+        #[arg(long, value_name = "UUID")]
+        player: Option<Uuid>,
+        /// Copy Level.sav to Level.sav.bak before overwriting it
+        #[arg(long)]
+        backup: bool,
+    },
+    /// Write the RawData of the first guild and the first player character in Level.sav to
+    /// fixture files like those in assets/, for the round-trip tests of a new game version
+    MakeFixtures {
+        /// Input directory containing Level.sav, or a .tar.gz backup of one
+        input: PathBuf,
+        /// Output directory of the fixtures
+        output: PathBuf,
+        /// Allow overwriting a non-empty output directory
+        #[arg(long)]
+        force: bool,
+    },
+    /// Change the InstanceId of a character in Level.sav, keeping its RawData, for a player whose
+    /// IndividualId.InstanceId was regenerated
+    RekeyCharacter {
+        /// Input directory containing Level.sav
+        input: PathBuf,
+        /// Current InstanceId of the character
+        old_instance_id: Uuid,
+        /// New InstanceId, which no character may have
+        new_instance_id: Uuid,
+        /// Copy Level.sav to Level.sav.bak before overwriting it
+        #[arg(long)]
+        backup: bool,
+    },
+}
+
+/// Fixes the save files in a directory when no subcommand is given
+#[derive(clap::Args, Debug)]
+struct FixArgs {
     /// Input directory containing the save files (Level.sav and Players directory)
-    input: String,
+    #[arg(required = true)]
+    input: Option<PathBuf>,
+
+    /// Promote the first member to admin in guilds whose admin is not a member
+    #[arg(long)]
+    fix_admin: bool,
+
+    /// Move player characters whose group id differs from the guild they are a member of
+    #[arg(long)]
+    fix_group_ids: bool,
+
+    /// Copy Level.sav to Level.sav.bak before overwriting it
+    #[arg(long)]
+    backup: bool,
+
+    /// Write the fixed save files to this directory instead of overwriting the input
+    #[arg(long, value_name = "PATH")]
+    output_dir: Option<PathBuf>,
+
+    /// Allow --output-dir to overwrite a non-empty directory
+    #[arg(long)]
+    force: bool,
+
+    /// Only fix this player (PlayerUId). Can be repeated
+    #[arg(long = "player", value_name = "UUID")]
+    players: Vec<Uuid>,
+
+    /// Do not fix this player (PlayerUId), even if their character save is missing. Can be repeated
+    #[arg(long = "skip-player", value_name = "UUID")]
+    skip_players: Vec<Uuid>,
+
+    /// Also look for player saves in subdirectories of Players
+    #[arg(long)]
+    recursive_players: bool,
+
+    /// Character save template JSON used for missing character saves, instead of the built-in one
+    #[arg(long, value_name = "PATH")]
+    template: Option<PathBuf>,
+
+    /// Compression type of the written Level.sav: 0x30 (None), 0x31 (Zlib) or 0x32 (DoubleZlib),
+    /// defaults to the input's
+    #[arg(long, value_name = "TYPE", value_parser = parse_compression_type)]
+    write_compression: Option<CompressionType>,
+
+    /// Append a short PlayerUId to the names of new characters when several of them share a name
+    #[arg(long)]
+    disambiguate_nicknames: bool,
+
+    /// Build new characters from a player character of the same guild instead of only the
+    /// template, so they get the fields of the current game version
+    #[arg(long)]
+    seed_from_sibling: bool,
+
+    /// Write the changes to this patch file for apply-patch instead of writing Level.sav
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["output_dir", "backup"])]
+    emit_patch: Option<PathBuf>,
+
+    /// Skip player saves that take longer than this many seconds to parse
+    #[arg(long, value_name = "SECS")]
+    parse_timeout: Option<u64>,
+
+    /// Read at most this many player saves at a time, which bounds the open file handles
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_OPEN_FILES)]
+    max_open_files: usize,
+
+    /// Print a report of the changes in this format
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    report_format: Option<ReportFormat>,
+
+    /// Report guilds without members, which can be deleted
+    #[arg(long)]
+    report_empty_guilds: bool,
+
+    /// Write a checklist of manual steps for the problems the fix leaves alone to this file
+    #[arg(long, value_name = "PATH")]
+    emit_remediation: Option<PathBuf>,
+
+    /// Skip the players of player saves that share an InstanceId instead of refusing to fix
+    #[arg(long)]
+    skip_duplicate_instance_ids: bool,
+
+    /// Leave this guild (group id) and its members untouched. Can be repeated
+    #[arg(long = "exclude-guild", value_name = "UUID")]
+    exclude_guilds: Vec<Uuid>,
+
+    /// Before writing Level.sav, write the changes that undo the fix to this file for undo
+    #[arg(long, value_name = "PATH", conflicts_with = "emit_patch")]
+    undo_log: Option<PathBuf>,
 }
 
 fn main() -> anyhow::Result<()> {
     let args = Args::parse();
+    log::set_quiet(args.global.quiet);
+    #[cfg(feature = "events")]
+    if let Some(path) = &args.global.events {
+        events::set_sink(Some(events::connect(path)?));
+    }
+    run_dumping_errors(&args)
+}
 
+/// Runs the command, writing a diagnostics bundle to --dump-on-error if it fails.
+fn run_dumping_errors(args: &Args) -> anyhow::Result<()> {
+    let Err(error) = run(args) else {
+        emit(Event::Finished { error: None });
+        return Ok(());
+    };
+    emit(Event::Finished {
+        error: Some(format!("{:#}", error)),
+    });
+    if let Some(dir) = &args.global.dump_on_error {
+        match write_error_dump(dir, &error, &dump_save_paths(args), &args.global.types) {
+            Ok(_) => info!("Diagnostics written to {}", dir.display()),
+            Err(dump_error) => info!("Failed to write diagnostics: {:#}", dump_error),
+        }
+    }
+    Err(error)
+}
+
+/// The saves whose headers go into the --dump-on-error bundle: the input .sav file, or the
+/// Level.sav of the input directory.
+fn dump_save_paths(args: &Args) -> Vec<PathBuf> {
+    match &args.command {
+        Some(Command::ToUesaveJson { input, .. }) | Some(Command::HeaderInfo { input, .. }) => {
+            vec![input.clone()]
+        }
+        Some(Command::FromUesaveJson { .. })
+        | Some(Command::BatchReport { .. })
+        | Some(Command::VerifyHash { .. }) => Vec::new(),
+        Some(Command::Stats { input, .. })
+        | Some(Command::SizeReport { input, .. })
+        | Some(Command::ListItemContainers { input, .. })
+        | Some(Command::RepairAll { input, .. })
+        | Some(Command::ExtractPlayerBundle { input, .. })
+        | Some(Command::ApplyPatch { input, .. })
+        | Some(Command::Undo { input, .. })
+        | Some(Command::RenameGuild { input, .. })
+        | Some(Command::SetBaseCampLevel { input, .. })
+        | Some(Command::Anonymize { input, .. })
+        | Some(Command::MergeGuilds { input, .. })
+        | Some(Command::PruneEmptyGuilds { input, .. })
+        | Some(Command::RepairFstrings { input, .. })
+        | Some(Command::GroupReconcile { input, .. })
+        | Some(Command::List { input, .. })
+        | Some(Command::PalCount { input, .. })
+        | Some(Command::FindPlayer { input, .. })
+        | Some(Command::ExportCharacterBin { input, .. })
+        | Some(Command::ImportCharacterBin { input, .. })
+        | Some(Command::MakeFixtures { input, .. })
+        | Some(Command::RekeyCharacter { input, .. }) => vec![input.join("Level.sav")],
+        None => args
+            .fix
+            .input
+            .iter()
+            .map(|input| input.join("Level.sav"))
+            .collect(),
+    }
+}
+
+fn run(args: &Args) -> anyhow::Result<()> {
+    match &args.command {
+        Some(Command::ToUesaveJson { input, output }) => {
+            let pal_save = args.global.read_save_file(input)?;
+            check_struct_depth(&pal_save, args.global.max_depth)
+                .with_context(|| format!("Failed to convert {}", input.display()))?;
+            match output {
+                Some(output) => {
+                    write_uesave_json(
+                        BufWriter::new(std::fs::File::create(output)?),
+                        &pal_save,
+                        args.global.json_style(),
+                    )?;
+                    info!("{} written successfully", output.display());
+                }
+                None => write_uesave_json(
+                    std::io::stdout().lock(),
+                    &pal_save,
+                    args.global.json_style(),
+                )?,
+            }
+        }
+        Some(Command::FromUesaveJson {
+            input,
+            output,
+            compression_type,
+        }) => {
+            let pal_save = read_uesave_json(
+                std::io::BufReader::new(
+                    std::fs::File::open(input)
+                        .with_context(|| format!("Failed to open {}", input.display()))?,
+                ),
+                *compression_type,
+            )?;
+            let mut writer = BufWriter::new(std::fs::File::create(output)?);
+            write_save_file(&mut writer, &pal_save)?;
+            info!("{} written successfully", output.display());
+        }
+        Some(Command::Stats {
+            input,
+            format,
+            member_cap,
+            debug_names,
+        }) => {
+            if *debug_names && *format == OutputFormat::Json {
+                anyhow::bail!(
+                    "--debug-names prints text and cannot be combined with --format json"
+                );
+            }
+            let level_save = args.global.read_level_save_or_tar_gz(input)?;
+            let version = args.global.game_version(&level_save);
+            if *debug_names {
+                // Before parsing the guilds, so garbled names can be seen when that fails
+                write_debug_names(&mut std::io::stdout().lock(), &level_save, version)?;
+            }
+            // Failures are listed once, after the statistics of the other guilds
+            let (groups, failures) = args.global.get_guilds(&level_save, version)?;
+            let stats =
+                compute_guild_stats(&groups, count_player_characters(&level_save), *member_cap);
+            match format {
+                OutputFormat::Text => print_guild_stats(&stats, args.global.colors()),
+                OutputFormat::Json => {
+                    write_json(std::io::stdout().lock(), &stats, args.global.json_style())?;
+                    println!();
+                }
+            }
+            args.global.check_failures(&failures)?;
+            args.global.check_warnings(stats.warning_count())?;
+        }
+        Some(Command::SizeReport { input, format, top }) => {
+            let level_save = args.global.read_level_save_or_tar_gz(input)?;
+            let report = compute_size_report(&level_save, *top)?;
+            match format {
+                OutputFormat::Text => print_size_report(&report, args.global.colors()),
+                OutputFormat::Json => {
+                    write_json(std::io::stdout().lock(), &report, args.global.json_style())?;
+                    println!();
+                }
+            }
+        }
+        Some(Command::ListItemContainers { input, format }) => {
+            let level_save = args.global.read_level_save_or_tar_gz(input)?;
+            let report = list_item_containers(&level_save);
+            match format {
+                OutputFormat::Text => print_item_container_report(&report),
+                OutputFormat::Json => {
+                    write_json(std::io::stdout().lock(), &report, args.global.json_style())?;
+                    println!();
+                }
+            }
+        }
+        Some(Command::HeaderInfo { input, format }) => {
+            let pal_save = args.global.read_save_file(input)?;
+            let info = read_header_info(input, &pal_save)?;
+            match format {
+                OutputFormat::Text => write_header_info(&mut std::io::stdout().lock(), &info)?,
+                OutputFormat::Json => {
+                    write_json(std::io::stdout().lock(), &info, args.global.json_style())?;
+                    println!();
+                }
+            }
+        }
+        Some(Command::RepairAll {
+            input,
+            output_dir,
+            force,
+            report_format,
+        }) => {
+            let report = repair_all(&args.global, input, output_dir.as_deref(), *force)?;
+            args.global.print_fix_report(&report, *report_format)?;
+            args.global.check_failures(&report.failures())?;
+        }
+        Some(Command::ExtractPlayerBundle {
+            input,
+            player,
+            output,
+            force,
+        }) => {
+            let level_save = args.global.read_level_save(input)?;
+            let game_version = args.global.game_version(&level_save);
+            let bundle =
+                extract_player_bundle(input, level_save, game_version, *player, output, *force)?;
+            println!("{}", bundle.player_sav_path.display());
+            println!("{}", bundle.level_sav_path.display());
+            println!("{}", bundle.guild_path.display());
+            bundle.character_paths.iter().for_each(|path| {
+                println!("{}", path.display());
+            });
+        }
+        Some(Command::ApplyPatch {
+            input,
+            patch,
+            backup,
+        }) => {
+            let patch = read_patch_file(patch)?;
+            let mut store = FsSaveStore {
+                dir: input.clone(),
+                recursive_players: false,
+            };
+            apply_patch_store(&args.global, &patch, *backup, &mut store)?;
+        }
+        Some(Command::Undo { log, input, backup }) => {
+            let undo_log = read_patch_file(log)?;
+            let mut store = FsSaveStore {
+                dir: input.clone(),
+                recursive_players: false,
+            };
+            apply_patch_store(&args.global, &undo_log, *backup, &mut store)?;
+        }
+        Some(Command::RenameGuild { input, guild, name }) => {
+            rename_guild(
+                &args.global,
+                &mut args.global.confirm(),
+                input,
+                *guild,
+                name,
+            )?;
+        }
+        Some(Command::SetBaseCampLevel {
+            input,
+            guild,
+            level,
+        }) => {
+            set_base_camp_level(
+                &args.global,
+                &mut args.global.confirm(),
+                input,
+                *guild,
+                *level,
+            )?;
+        }
+        Some(Command::Anonymize {
+            input,
+            output,
+            remap_uids,
+            force,
+        }) => {
+            let level_save = args.global.read_level_save(input)?;
+            let game_version = args.global.game_version(&level_save);
+            anonymize_save_directory(input, level_save, game_version, output, *remap_uids, *force)?;
+        }
+        Some(Command::MergeGuilds {
+            input,
+            source,
+            destination,
+            backup,
+        }) => {
+            let mut store = FsSaveStore {
+                dir: input.clone(),
+                recursive_players: false,
+            };
+            merge_guilds(
+                &args.global,
+                &mut args.global.confirm(),
+                &mut store,
+                *source,
+                *destination,
+                *backup,
+            )?;
+        }
+        Some(Command::PruneEmptyGuilds { input, backup }) => {
+            let mut store = FsSaveStore {
+                dir: input.clone(),
+                recursive_players: false,
+            };
+            prune_empty_guilds(
+                &args.global,
+                &mut args.global.confirm(),
+                &mut store,
+                *backup,
+            )?;
+        }
+        Some(Command::RepairFstrings { input, backup }) => {
+            let mut store = FsSaveStore {
+                dir: input.clone(),
+                recursive_players: false,
+            };
+            let mut level_save = store.read_level(&args.global.types)?;
+            args.global.after_read("Level.sav", &level_save);
+            let game_version = args.global.game_version(&level_save);
+            let (patch, repairs) = repair_fstrings_patch(&level_save, game_version);
+            if repairs.is_empty() {
+                info!("No FString length to correct, Level.sav was not changed");
+                return Ok(());
+            }
+            if !args.global.apply_changes(&mut level_save, &patch)? {
+                return Ok(());
+            }
+            repairs.iter().for_each(|repair| {
+                println!(
+                    "Guild {} ({}): {} FString lengths corrected",
+                    repair.guild_id, repair.guild_name, repair.repaired_count
+                );
+            });
+            info!(
+                "{} FString lengths corrected in {} guilds",
+                repairs
+                    .iter()
+                    .map(|repair| repair.repaired_count)
+                    .sum::<usize>(),
+                repairs.len()
+            );
+            if *backup {
+                store.backup_level()?;
+            }
+            args.global
+                .write_level(&mut store, &level_save, level_save.compression_type)?;
+            info!("Level.sav written successfully");
+        }
+        Some(Command::List {
+            input,
+            sort,
+            reverse,
+            format,
+        }) => {
+            let level_save = args.global.read_level_save_or_tar_gz(input)?;
+            let version = args.global.game_version(&level_save);
+            let (groups, failures) = args.global.get_guilds(&level_save, version)?;
+            let entries = list_guilds(&groups, *sort, *reverse);
+            match format {
+                OutputFormat::Text => print_guild_list(&entries),
+                OutputFormat::Json => {
+                    write_json(std::io::stdout().lock(), &entries, args.global.json_style())?;
+                    println!();
+                }
+            }
+            args.global.check_failures(&failures)?;
+        }
+        Some(Command::PalCount { input, format }) => {
+            let level_save = args.global.read_level_save_or_tar_gz(input)?;
+            let report = count_pals(&level_save, args.global.game_version(&level_save));
+            match format {
+                OutputFormat::Text => print_pal_count_report(&report),
+                OutputFormat::Json => {
+                    write_json(std::io::stdout().lock(), &report, args.global.json_style())?;
+                    println!();
+                }
+            }
+        }
+        Some(Command::GroupReconcile {
+            input,
+            format,
+            check_instance_ids,
+        }) => {
+            let level_save = args.global.read_level_save_or_tar_gz(input)?;
+            let mut reconciliation = reconcile_groups(&level_save);
+            if *check_instance_ids {
+                let version = args.global.game_version(&level_save);
+                reconciliation.instance_ids = Some(check_guild_instance_ids(&level_save, version));
+            }
+            match format {
+                OutputFormat::Text => {
+                    print_group_reconciliation(&reconciliation, args.global.colors())
+                }
+                OutputFormat::Json => {
+                    write_json(
+                        std::io::stdout().lock(),
+                        &reconciliation,
+                        args.global.json_style(),
+                    )?;
+                    println!();
+                }
+            }
+            args.global.check_warnings(reconciliation.warning_count())?;
+        }
+        Some(Command::VerifyHash { input, hash_file }) => {
+            let hash = verify_hash(input, hash_file)?;
+            println!("{}: OK ({})", input.display(), hash);
+        }
+        Some(Command::BatchReport { input, format }) => {
+            let rows = batch_report(input)?;
+            if rows.is_empty() {
+                info!("No Level.sav found in {}", input.display());
+            }
+            write_batch_report(&mut std::io::stdout().lock(), &rows, *format)?;
+        }
+        Some(Command::FindPlayer {
+            input,
+            query,
+            format,
+        }) => {
+            let level_save = args.global.read_level_save_or_tar_gz(input)?;
+            let (groups, _) =
+                get_guilds_skipping_failures(&level_save, args.global.game_version(&level_save));
+            let matches = find_players(&groups, query);
+            match format {
+                OutputFormat::Text => {
+                    if matches.is_empty() {
+                        info!("No guild member matches {}", query);
+                    }
+                    print_player_matches(&matches);
+                }
+                OutputFormat::Json => {
+                    write_json(std::io::stdout().lock(), &matches, args.global.json_style())?;
+                    println!();
+                }
+            }
+        }
+        Some(Command::ExportCharacterBin {
+            input,
+            instance_id,
+            output,
+        }) => {
+            let level_save = args.global.read_level_save_or_tar_gz(input)?;
+            let raw_data = export_character_raw_data(&level_save, *instance_id)?;
+            std::fs::write(output, &raw_data)
+                .with_context(|| format!("Failed to write {}", output.display()))?;
+            info!(
+                "{} bytes of character {} written to {}",
+                raw_data.len(),
+                instance_id,
+                output.display()
+            );
+        }
+        Some(Command::ImportCharacterBin {
+            input,
+            instance_id,
+            blob,
+            player,
+            backup,
+        }) => {
+            let raw_data = std::fs::read(blob)
+                .with_context(|| format!("Failed to open {}", blob.display()))?;
+            let mut store = FsSaveStore {
+                dir: input.clone(),
+                recursive_players: false,
+            };
+            let mut level_save = store.read_level(&args.global.types)?;
+            args.global.after_read("Level.sav", &level_save);
+            let (patch, replaced) = import_character_patch(
+                &level_save,
+                *instance_id,
+                player.unwrap_or_default(),
+                raw_data,
+            )
+            .with_context(|| format!("Failed to import {}", blob.display()))?;
+            if !args.global.apply_changes(&mut level_save, &patch)? {
+                return Ok(());
+            }
+            if replaced {
+                info!("Character {} replaced", instance_id);
+            } else {
+                info!("Character {} added", instance_id);
+            }
+            if *backup {
+                store.backup_level()?;
+            }
+            args.global
+                .write_level(&mut store, &level_save, level_save.compression_type)?;
+            info!("Level.sav written successfully");
+        }
+        Some(Command::MakeFixtures {
+            input,
+            output,
+            force,
+        }) => {
+            let level_save = args.global.read_level_save_or_tar_gz(input)?;
+            let fixtures = make_fixtures(&level_save, output, *force)?;
+            for path in fixtures.guild_path.iter().chain(&fixtures.character_path) {
+                println!("{}", path.display());
+            }
+        }
+        Some(Command::RekeyCharacter {
+            input,
+            old_instance_id,
+            new_instance_id,
+            backup,
+        }) => {
+            let mut store = FsSaveStore {
+                dir: input.clone(),
+                recursive_players: false,
+            };
+            let mut level_save = store.read_level(&args.global.types)?;
+            args.global.after_read("Level.sav", &level_save);
+            let patch = rekey_character_patch(&level_save, *old_instance_id, *new_instance_id)?;
+            if !args.global.apply_changes(&mut level_save, &patch)? {
+                return Ok(());
+            }
+            info!(
+                "Character {} re-keyed to {}",
+                old_instance_id, new_instance_id
+            );
+            if *backup {
+                store.backup_level()?;
+            }
+            args.global
+                .write_level(&mut store, &level_save, level_save.compression_type)?;
+            info!("Level.sav written successfully");
+        }
+        None => {
+            let report = fix_save_directory(&args.global, &args.fix)?;
+            if let Some(report_format) = args.fix.report_format {
+                args.global.print_fix_report(&report, report_format)?;
+            }
+            args.global.check_failures(&report.failures())?;
+
+            if !args.global.quiet {
+                info!("All done! Press enter to exit...");
+                let mut input = String::new();
+                std::io::stdin().read_line(&mut input).unwrap();
+            }
+        }
+    }
+    Ok(())
+}
+
+fn fix_save_directory(global: &GlobalArgs, args: &FixArgs) -> anyhow::Result<FixReport> {
+    let input = args.input.as_deref().context("Missing input directory")?;
+    if let Some(player_uid) = args
+        .players
+        .iter()
+        .find(|player_uid| args.skip_players.contains(player_uid))
+    {
+        return Err(anyhow::anyhow!(
+            "Player {} is given to both --player and --skip-player",
+            player_uid
+        ));
+    }
+    // Fail before touching any file if the template is unusable
+    let template: Template = match &args.template {
+        Some(path) => {
+            let source = std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            load_template(&source).with_context(|| format!("Failed to load {}", path.display()))?
+        }
+        None => load_template(DEFAULT_TEMPLATE)?,
+    };
+
+    // 0. Copy save files when writing to a different directory
+    let sav_directory: &Path = match &args.output_dir {
+        Some(output_dir) if !global.dry_run => {
+            copy_save_directory(input, output_dir, args.force)?;
+            info!("Save files copied to {}", output_dir.display());
+            output_dir
+        }
+        _ => input,
+    };
+
+    let mut store = FsSaveStore {
+        dir: sav_directory.to_path_buf(),
+        recursive_players: args.recursive_players,
+    };
+    let report = fix_save_store(global, args, &template, &mut store)?;
+    if let Some(path) = &args.emit_remediation {
+        std::fs::write(path, render_remediation(&report, sav_directory))
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        info!("Manual steps written to {}", path.display());
+    }
+    Ok(report)
+}
+
+/// Steps 1 to 8 of the fix, on any [`SaveStore`].
+fn fix_save_store(
+    global: &GlobalArgs,
+    args: &FixArgs,
+    template: &Template,
+    store: &mut dyn SaveStore,
+) -> anyhow::Result<FixReport> {
     // 1. Read save files
-    let sav_directory = std::path::Path::new(&args.input);
-    let level_sav_path: PathBuf = sav_directory.join("Level.sav");
-    let player_sav_paths: Vec<PathBuf> = std::fs::read_dir(sav_directory.join("Players"))
-        .expect("Failed to read Players directory")
-        .filter_map(|entry| entry.map(|entry| entry.path()).ok())
-        .filter(|path| path.extension().map(|ext| ext == "sav").unwrap_or(false))
+    let player_sav_names: Vec<String> = store.list_players()?;
+    let mut level_save = store.read_level(&global.types)?;
+    global.after_read("Level.sav", &level_save);
+    info!("Level.sav read successfully");
+    emit(Event::LevelRead);
+    // Player names from LevelMeta.sav take precedence over the possibly stale guild names
+    let level_meta_player_names = match store.read_level_meta(&global.types)? {
+        Some(level_meta) => {
+            global.after_read("LevelMeta.sav", &level_meta);
+            info!("LevelMeta.sav read successfully");
+            get_level_meta_player_names(&level_meta)
+        }
+        None => HashMap::new(),
+    };
+    // Player saves are read --max-open-files at a time. Each one is parsed on its own worker
+    // thread with --parse-timeout, so one pathological save cannot hang the run
+    let reader: &dyn SaveStore = store;
+    let parse_timeout = args.parse_timeout.map(Duration::from_secs);
+    let read_results = read_bounded(
+        &player_sav_names,
+        args.max_open_files,
+        |name| -> anyhow::Result<Option<PalSave>> {
+            let bytes = reader
+                .read_player_bytes(name)
+                .with_context(|| format!("Failed to read player save {}", name))?;
+            let types = global.types.clone();
+            parse_with_timeout(parse_timeout, move || {
+                read_save_file_with_extra_types(Cursor::new(bytes), &types)
+            })
+            .with_context(|| format!("Failed to read player save {}", name))
+        },
+    );
+    let mut timed_out_player_savs = Vec::new();
+    let mut non_player_savs = Vec::new();
+    let mut unreadable_player_savs = Vec::new();
+    let player_saves: Vec<(String, PalSave)> = player_sav_names
+        .iter()
+        .zip(read_results)
+        .map(
+            |(name, read_result)| -> anyhow::Result<Option<(String, PalSave)>> {
+                let read_result = match read_result {
+                    Err(error) if global.keep_going => {
+                        info!("{:#}, skipping", error);
+                        emit(Event::PlayerSaveSkipped {
+                            name: name.clone(),
+                            reason: format!("{:#}", error),
+                        });
+                        unreadable_player_savs.push((name.clone(), format!("{:#}", error)));
+                        return Ok(None);
+                    }
+                    read_result => read_result,
+                };
+                let Some(pal_save) = read_result? else {
+                    info!(
+                        "Parsing {} exceeded {} s — skipping",
+                        name,
+                        args.parse_timeout.unwrap_or_default()
+                    );
+                    emit(Event::PlayerSaveSkipped {
+                        name: name.clone(),
+                        reason: "parse timed out".to_string(),
+                    });
+                    timed_out_player_savs.push(name.clone());
+                    return Ok(None);
+                };
+                global.after_read(name, &pal_save);
+                if get_player_individual_id(&pal_save).is_none() {
+                    info!(
+                        "Warning: {} has no SaveData.IndividualId and is not a player save, skipping",
+                        name
+                    );
+                    emit(Event::PlayerSaveSkipped {
+                        name: name.clone(),
+                        reason: "not a player save".to_string(),
+                    });
+                    non_player_savs.push(name.clone());
+                    return Ok(None);
+                }
+                emit(Event::PlayerSaveRead { name: name.clone() });
+                Ok(Some((name.clone(), pal_save)))
+            },
+        )
+        .collect::<anyhow::Result<Vec<_>>>()?
+        .into_iter()
+        .flatten()
         .collect();
-    let mut level_save = read_save_file(std::fs::File::open(&level_sav_path)?)?;
-    println!("Level.sav read successfully");
-    let player_saves: Vec<PalSave> = player_sav_paths
+    if !non_player_savs.is_empty() {
+        info!(
+            "Warning: {} files in Players are not player saves and were skipped",
+            non_player_savs.len()
+        );
+    }
+    info!("Player saves read successfully");
+    // 1-1. Saves of one world share the custom formats of their headers. A player save whose
+    // differ likely comes from another game version
+    let custom_format_mismatches: Vec<(String, String)> = player_saves
         .iter()
-        .map(|path| read_save_file(std::fs::File::open(path)?))
-        .collect::<Result<Vec<_>, _>>()?;
-    println!("Player saves read successfully");
+        .filter_map(|(name, pal_save)| {
+            let mismatch = custom_format_mismatch(&pal_save.save.header, &level_save.save.header)?;
+            info!(
+                "Warning: {} has {}, it may be from another game version",
+                name, mismatch
+            );
+            Some((name.clone(), mismatch))
+        })
+        .collect();
 
     // 2. Parse guild data from GroupSaveDataMap.RawData
-    let groups: Vec<(Uuid, GroupGuildSave)> = get_group_save_data_map(&level_save)
+    let (groups, unparsed_guilds) =
+        get_guilds_skipping_failures(&level_save, global.game_version(&level_save));
+    // Guilds excluded with --exclude-guild are set aside, so neither they nor their members are
+    // fixed
+    let (excluded_groups, mut groups): (Vec<_>, Vec<_>) = groups
+        .into_iter()
+        .partition(|(guild_id, _)| args.exclude_guilds.contains(guild_id));
+    excluded_groups.iter().for_each(|(guild_id, group)| {
+        info!(
+            "Guild {}({}) excluded by --exclude-guild",
+            group.GuildName, guild_id
+        );
+    });
+    args.exclude_guilds
         .iter()
-        .filter(|entry| is_group_type_guild(entry))
-        .map(|entry| {
-            let uesave::PropertyValue::Struct(uesave::StructValue::Guid(guild_id)) = entry.key
-            else {
-                panic!()
-            };
-            (guild_id, parse_raw_group_guild_save(entry))
-        })
-        .collect();
+        .filter(|guild_id| !excluded_groups.iter().any(|(id, _)| id == *guild_id))
+        .for_each(|guild_id| {
+            info!(
+                "Warning: --exclude-guild {} is not a guild that could be parsed",
+                guild_id
+            );
+        });
+    unparsed_guilds.iter().for_each(|(guild_id, error)| {
+        info!(
+            "Guild {} could not be parsed, skipping: {:#}",
+            guild_id, error
+        );
+        emit(Event::GuildUnparsed {
+            guild_id: *guild_id,
+            error: format!("{:#}", error),
+        });
+    });
+    let mut report = FixReport {
+        unparsed_guild_ids: unparsed_guilds
+            .iter()
+            .map(|(guild_id, _)| *guild_id)
+            .collect(),
+        timed_out_player_savs,
+        non_player_savs,
+        unreadable_player_savs,
+        custom_format_mismatches,
+        ..Default::default()
+    };
+    // Changes to Level.sav, made in step 8 or written instead of it with --emit-patch
+    let mut patch = Patch::default();
     // Print guild infomation
-    groups.iter().for_each(|(_, group)| {
-        println!(
+    groups.iter().for_each(|(guild_id, group)| {
+        info!(
             "Guild {}({}) has {} members",
             group.GuildName,
             group.UnknownUuid,
             group.GuildPlayerInfo.len()
         );
+        emit(Event::GuildParsed {
+            guild_id: *guild_id,
+            name: group.GuildName.clone(),
+            member_count: group.GuildPlayerInfo.len(),
+        });
         group.GuildPlayerInfo.iter().for_each(|player_info| {
-            println!("- {}({})", player_info.PlayerName, player_info.PlayerUId);
+            info!("- {}({})", player_info.PlayerName, player_info.PlayerUId);
         });
     });
-    println!("Guilds parsed successfully");
+    info!("Guilds parsed successfully");
+    // Guilds whose members all left have no one to fix; they are only reported
+    if args.report_empty_guilds {
+        groups
+            .iter()
+            .filter(|(_, group)| group.GuildPlayerInfo.is_empty())
+            .for_each(|(guild_id, group)| {
+                info!(
+                    "Guild {}({}) has no members and can be deleted",
+                    group.GuildName, group.UnknownUuid
+                );
+                report.empty_guild_ids.push(*guild_id);
+            });
+    }
 
-    // 3. Parse character data from CharacterSaveParameterMap.RawData
-    let character_save_parameter_map: HashSet<Uuid> = get_character_save_parameter_map(&level_save)
+    // 2-1. Check if guild admin is a guild member
+    // An empty guild has no member to be admin, which is not worth a message per run
+    groups.iter_mut().for_each(|(guild_id, group)| {
+        if is_admin_in_guild(group) || group.GuildPlayerInfo.is_empty() {
+            return;
+        }
+        info!(
+            "Guild {}({}) admin {} is not a member",
+            group.GuildName,
+            group.UnknownUuid,
+            guild_admin_label(group.AdminPlayerUId)
+        );
+        if !args.fix_admin {
+            return;
+        }
+        if let Some(new_admin) = fix_guild_admin(group) {
+            info!("- Promoted {} to admin", new_admin);
+            report.fixed_admin_guild_ids.push(*guild_id);
+        }
+    });
+    get_group_save_data_map(&level_save)
         .iter()
-        .map(|entry| {
-            let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref key)) = entry.key
-            else {
-                panic!()
-            };
-            let uesave::Property::Struct {
-                value: uesave::StructValue::Guid(instance_id),
-                ..
-            } = &key["InstanceId"]
+        .for_each(|entry| {
+            let uesave::PropertyValue::Struct(uesave::StructValue::Guid(guild_id)) = entry.key
             else {
-                panic!()
+                return;
             };
-            instance_id.clone()
-        })
-        .collect();
-    println!("CharacterSaveParameterMap parsed successfully");
+            if !report.fixed_admin_guild_ids.contains(&guild_id) {
+                return;
+            }
+            let (_, group) = groups.iter().find(|(id, _)| *id == guild_id).unwrap();
+            patch.ops.push(PatchOp::SetGuildRawData {
+                guild_id,
+                raw_data: write_group_guild_save(group),
+            });
+        });
 
-    // 4. Parse player individual ids from Player saves
-    let player_individual_ids: Vec<(Uuid, Uuid)> = player_saves
-        .iter()
-        .map(|pal_save| {
-            let uesave::Property::Struct {
-                value: uesave::StructValue::Struct(save_data),
-                ..
-            } = &pal_save.save.root.properties["SaveData"]
-            else {
-                panic!()
-            };
-            let uesave::Property::Struct {
-                value: uesave::StructValue::Struct(individual_id),
-                ..
-            } = &save_data["IndividualId"]
-            else {
-                panic!()
-            };
+    // 3. Parse character data from CharacterSaveParameterMap.RawData
+    // InstanceId -> PlayerUId of every character, None for pals
+    let character_save_parameter_map = get_character_owners(&level_save);
+    info!("CharacterSaveParameterMap parsed successfully");
 
-            let player_uid = {
-                let uesave::Property::Struct {
-                    value: uesave::StructValue::Guid(player_uid),
-                    ..
-                } = &individual_id["PlayerUId"]
-                else {
-                    panic!()
-                };
-                player_uid.clone()
-            };
-            let instance_id = {
-                let uesave::Property::Struct {
-                    value: uesave::StructValue::Guid(instance_id),
-                    ..
-                } = &individual_id["InstanceId"]
-                else {
-                    panic!()
-                };
-                instance_id.clone()
-            };
+    // 3-1. Move player characters to the guild they are a member of
+    if args.fix_group_ids {
+        let header = &level_save.save.header;
+        let corrections: Vec<(Uuid, Uuid, Vec<u8>)> = get_character_save_parameter_map(&level_save)
+            .iter()
+            .filter_map(|entry| {
+                let (player_uid, instance_id) = get_character_key_ids(entry)?;
+                let (guild_id, _) = groups.iter().find(|(_, group)| {
+                    group
+                        .GuildPlayerInfo
+                        .iter()
+                        .any(|player_info| player_info.PlayerUId == player_uid)
+                })?;
+                let mut character_save_parameter =
+                    match parse_raw_character_save_parameter(header)(entry) {
+                        Ok(character_save_parameter) => character_save_parameter,
+                        Err(error) => {
+                            info!("Character {} could not be parsed: {:#}", instance_id, error);
+                            return None;
+                        }
+                    };
+                if character_save_parameter.group_id == *guild_id {
+                    return None;
+                }
+                info!(
+                    "Character {} of player {} is in group {}, moving to guild {}",
+                    instance_id, player_uid, character_save_parameter.group_id, guild_id
+                );
+                character_save_parameter.group_id = *guild_id;
+                Some((
+                    instance_id,
+                    *guild_id,
+                    write_raw_character_save_parameter(header)(&character_save_parameter),
+                ))
+            })
+            .collect();
+        corrections
+            .into_iter()
+            .for_each(|(instance_id, guild_id, raw_data)| {
+                patch.ops.push(PatchOp::SetCharacterRawData {
+                    instance_id,
+                    raw_data,
+                });
+                report.corrected_group_ids.push((instance_id, guild_id));
+            });
+    }
 
-            (player_uid, instance_id)
+    // 4. Parse player individual ids from Player saves. Every save left after step 1 has one
+    let player_individual_ids: Vec<(&str, Uuid, Uuid)> = player_saves
+        .iter()
+        .filter_map(|(name, pal_save)| {
+            let (player_uid, instance_id) = get_player_individual_id(pal_save)?;
+            Some((name.as_str(), player_uid, instance_id))
         })
         .collect();
+    // 4-1. Player saves that share an InstanceId, e.g. one copied over another player's, would
+    // both claim the same character. None of them is fixed until the copy is removed
+    let mut instance_id_counts: HashMap<Uuid, usize> = HashMap::new();
     player_individual_ids
         .iter()
-        .for_each(|(player_uid, instance_id)| {
-            println!("Player {} has individual id {}", player_uid, instance_id);
+        .for_each(|(_, _, instance_id)| *instance_id_counts.entry(*instance_id).or_default() += 1);
+    let (duplicate_instance_ids, player_individual_ids): (Vec<_>, Vec<_>) = player_individual_ids
+        .into_iter()
+        .partition(|(_, _, instance_id)| instance_id_counts[instance_id] > 1);
+    if !duplicate_instance_ids.is_empty() {
+        let duplicates: Vec<String> = duplicate_instance_ids
+            .iter()
+            .map(|(name, player_uid, instance_id)| {
+                format!("{} (player {}) has id {}", name, player_uid, instance_id)
+            })
+            .collect();
+        if !args.skip_duplicate_instance_ids {
+            return Err(anyhow::anyhow!(
+                "{} player saves share an InstanceId with another one. Remove the copied saves, \
+                 or skip their players with --skip-duplicate-instance-ids:\n{}",
+                duplicate_instance_ids.len(),
+                duplicates
+                    .iter()
+                    .map(|duplicate| format!("- {}", duplicate))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            ));
+        }
+        duplicates.iter().for_each(|duplicate| {
+            info!(
+                "Warning: {}, which another player save also has, skipping",
+                duplicate
+            );
         });
-    println!("Player individual ids parsed successfully");
-
-    // 5. Check if player does not have a character save
-    let player_without_character_save: Vec<(Uuid, Uuid)> = player_individual_ids
+        report.duplicate_instance_id_savs = duplicate_instance_ids
+            .iter()
+            .map(|(name, player_uid, instance_id)| (name.to_string(), *player_uid, *instance_id))
+            .collect();
+    }
+    let player_individual_ids: Vec<(Uuid, Uuid)> = player_individual_ids
+        .into_iter()
+        .map(|(_, player_uid, instance_id)| (player_uid, instance_id))
+        .collect();
+    player_individual_ids
+        .iter()
+        .for_each(|(player_uid, instance_id)| {
+            info!("Player {} has individual id {}", player_uid, instance_id);
+        });
+    info!("Player individual ids parsed successfully");
+
+    // 5. Check if player does not have a character save
+    // A character with the player's InstanceId but another PlayerUId is corruption that needs
+    // manual attention; it must neither be taken as theirs nor duplicated
+    player_individual_ids
         .iter()
-        .filter(|(_, instance_id)| !character_save_parameter_map.contains(instance_id))
+        .for_each(|(player_uid, instance_id)| {
+            let Some(owner_uid) = character_save_parameter_map.get(instance_id) else {
+                return;
+            };
+            match owner_uid {
+                Some(owner_uid) if owner_uid == player_uid => return,
+                Some(owner_uid) => info!(
+                    "Player {} has individual id {}, but that character belongs to player {}. Fix it manually",
+                    player_uid, instance_id, owner_uid
+                ),
+                None => info!(
+                    "Player {} has individual id {}, but that character is a pal. Fix it manually",
+                    player_uid, instance_id
+                ),
+            }
+            // Pals are reported with a nil owner
+            report.instance_id_collisions.push((
+                *player_uid,
+                *instance_id,
+                owner_uid.unwrap_or_default(),
+            ));
+        });
+    let mut player_without_character_save: Vec<(Uuid, Uuid)> = player_individual_ids
+        .iter()
+        .filter(|(_, instance_id)| !character_save_parameter_map.contains_key(instance_id))
         .map(|(player_uid, instance_id)| (player_uid.clone(), instance_id.clone()))
         .collect();
+    // Sort by InstanceId so that new character saves are appended in the same order every run,
+    // regardless of the order Players directory is listed in
+    player_without_character_save.sort_by_key(|(_, instance_id)| *instance_id);
     player_without_character_save
         .iter()
         .for_each(|(player_uid, instance_id)| {
-            println!(
+            info!(
                 "Player {} has no character save with id {}",
                 player_uid, instance_id
             );
         });
-    // 5-1. When all players have a character save, exit
-    if player_without_character_save.is_empty() {
-        println!("All players have a character save. Exiting...");
-        return Ok(());
-    }
-
-    // 6. Create a new character save for each player without a character save
-    let template_character_save =
-        include_str!("../templates/PalIndividualCharacterSaveParameter.json");
-    let template_character_save: IndexMap<String, uesave::Property> =
-        serde_json::from_str(template_character_save)?;
-    let create_new_character_save = |nickname: &str, guild_id: &Uuid| -> CharacterSaveParameter {
-        let mut character_save = template_character_save.clone();
-        let uesave::Property::Struct {
-            value: uesave::StructValue::Struct(properties),
-            ..
-        } = character_save.get_mut("SaveParameter").unwrap()
-        else {
-            panic!()
+    player_without_character_save.retain(|(player_uid, _)| {
+        if args.skip_players.contains(player_uid) {
+            info!("Player {} skipped by --skip-player", player_uid);
+            return false;
+        }
+        if !args.players.is_empty() && !args.players.contains(player_uid) {
+            info!("Player {} skipped, not selected by --player", player_uid);
+            return false;
+        }
+        true
+    });
+    player_without_character_save.retain(|(player_uid, _)| {
+        let Some((guild_id, group)) = excluded_groups.iter().find(|(_, group)| {
+            group
+                .GuildPlayerInfo
+                .iter()
+                .any(|player_info| player_info.PlayerUId == *player_uid)
+        }) else {
+            return true;
         };
-        properties.insert(
-            "NickName".into(),
-            uesave::Property::Str {
-                id: None,
-                value: nickname.into(),
-            },
+        info!(
+            "Player {} skipped, member of guild {}({}) excluded by --exclude-guild",
+            player_uid, group.GuildName, guild_id
         );
-        CharacterSaveParameter {
-            properties: character_save,
-            group_id: guild_id.clone(),
+        false
+    });
+    player_without_character_save.retain(|(player_uid, _)| {
+        let has_guild = groups.iter().any(|(_, group)| {
+            group
+                .GuildPlayerInfo
+                .iter()
+                .any(|player_info| player_info.PlayerUId == *player_uid)
+        });
+        if !has_guild {
+            info!(
+                "Player {} is not a member of any parsed guild, skipping",
+                player_uid
+            );
+            report.guildless_player_uids.push(*player_uid);
         }
-    };
-    let new_character_saves: Vec<uesave::MapEntry> = player_without_character_save
-        .iter()
-        .map(|(player_uid, instance_id)| {
-            let key = {
-                let mut key: IndexMap<String, uesave::Property> = IndexMap::new();
-                key.insert(
-                    "PlayerUId".into(),
-                    uesave::Property::Struct {
-                        id: None,
-                        value: uesave::StructValue::Guid(player_uid.clone()),
-                        struct_type: uesave::StructType::Guid,
-                        struct_id: Uuid::nil(),
-                    },
-                );
-                key.insert(
-                    "InstanceId".into(),
-                    uesave::Property::Struct {
-                        id: None,
-                        value: uesave::StructValue::Guid(instance_id.clone()),
-                        struct_type: uesave::StructType::Guid,
-                        struct_id: Uuid::nil(),
-                    },
-                );
-                key.insert(
-                    "DebugName".into(),
-                    uesave::Property::Str {
-                        id: None,
-                        value: "".into(),
-                    },
-                );
-                uesave::PropertyValue::Struct(uesave::StructValue::Struct(key))
-            };
-            let value = {
-                let character_save_parameter = groups
+        has_guild
+    });
+    // 5-1. When all players have a character save and nothing else was fixed, exit
+    if player_without_character_save.is_empty() && !report.has_changes() {
+        match (report.guildless_player_uids.len(), groups.is_empty()) {
+            (0, _) => info!("All players have a character save. Exiting..."),
+            (count, true) => info!(
+                "{} players are missing character saves but no guilds exist to associate them with. Exiting...",
+                count
+            ),
+            (count, false) => info!(
+                "{} players are missing character saves but are not members of any parsed guild. Exiting...",
+                count
+            ),
+        }
+        if let Some(path) = &args.emit_patch {
+            write_patch(global, path, &patch)?;
+        }
+        print_unparsed_guilds(&unparsed_guilds);
+        return Ok(report);
+    }
+
+    // 6. Create a new character save for each player without a character save
+    let character_debug_names = get_character_debug_names(&level_save);
+    let (guild_ids, mut nicknames): (Vec<Uuid>, Vec<(Uuid, String)>) =
+        player_without_character_save
+            .iter()
+            .map(|(player_uid, _)| {
+                groups
                     .iter()
                     .find_map(|(guild_id, group)| {
                         group
@@ -228,45 +1826,1457 @@ fn main() -> anyhow::Result<()> {
                             .iter()
                             .find(|player_info| player_info.PlayerUId == *player_uid)
                             .map(|player_info| {
-                                create_new_character_save(&player_info.PlayerName, guild_id)
+                                let (nickname, source) = pick_nickname(
+                                    *player_uid,
+                                    level_meta_player_names.get(player_uid).map(String::as_str),
+                                    &player_info.PlayerName,
+                                    character_debug_names.get(player_uid).map(String::as_str),
+                                );
+                                match source {
+                                    NicknameSource::LevelMeta => info!(
+                                        "Player {} is named {} in LevelMeta.sav, using it instead of guild name {}",
+                                        player_uid, nickname, player_info.PlayerName
+                                    ),
+                                    NicknameSource::Guild => {}
+                                    NicknameSource::DebugName => info!(
+                                        "Player {} has no guild name, using {} from the key of another character of theirs",
+                                        player_uid, nickname
+                                    ),
+                                    NicknameSource::PlayerUId => info!(
+                                        "Player {} has no name, naming their character {}",
+                                        player_uid, nickname
+                                    ),
+                                }
+                                (*guild_id, (*player_uid, nickname))
                             })
                     })
-                    .unwrap();
-                let mut value: IndexMap<String, uesave::Property> = IndexMap::new();
-                value.insert(
-                    "RawData".into(),
-                    uesave::Property::Array {
-                        array_type: uesave::PropertyType::ByteProperty,
-                        id: None,
-                        value: uesave::ValueArray::Base(uesave::ValueVec::Byte(
-                            uesave::ByteArray::Byte(write_raw_character_save_parameter(
-                                &level_save.save.header,
-                            )(
-                                &character_save_parameter
-                            )),
-                        )),
-                    },
-                );
-                uesave::PropertyValue::Struct(uesave::StructValue::Struct(value))
+                    // Players who are not a member of any parsed guild were dropped in step 5
+                    .unwrap()
+            })
+            .unzip();
+    if args.disambiguate_nicknames {
+        disambiguate_nicknames(&mut nicknames);
+    }
+    player_without_character_save
+        .iter()
+        .zip(&guild_ids)
+        .zip(&nicknames)
+        .for_each(|(((player_uid, instance_id), guild_id), (_, nickname))| {
+            let sibling = args
+                .seed_from_sibling
+                .then(|| find_player_character_in_group(&level_save, *guild_id))
+                .flatten();
+            let character_save_parameter = match &sibling {
+                Some(sibling) => {
+                    info!(
+                        "Character of player {} seeded from a character of guild {}",
+                        player_uid, guild_id
+                    );
+                    template.create_character_save_from_sibling(nickname, *guild_id, sibling)
+                }
+                None => {
+                    if args.seed_from_sibling {
+                        info!(
+                            "Guild {} has no player character to seed from, using the template",
+                            guild_id
+                        );
+                    }
+                    template.create_character_save(nickname, *guild_id)
+                }
             };
-            uesave::MapEntry { key, value }
-        })
-        .collect::<Vec<_>>();
-    println!("New character saves created successfully");
+            patch.ops.push(PatchOp::AppendCharacterSave {
+                player_uid: *player_uid,
+                instance_id: *instance_id,
+                raw_data: write_raw_character_save_parameter(&level_save.save.header)(
+                    &character_save_parameter,
+                ),
+            });
+            emit(Event::CharacterSaveAdded {
+                player_uid: *player_uid,
+                instance_id: *instance_id,
+            });
+        });
+    info!("New character saves created successfully");
 
-    // 7. Append new character saves to CharacterSaveParameterMap
-    get_character_save_parameter_map_mut(&mut level_save).extend(new_character_saves);
-    println!("New character saves appended successfully");
+    // 7. New character saves are appended to CharacterSaveParameterMap with the other changes
+    report.added_character_saves = player_without_character_save;
 
     // 8. Write Level.sav
-    let level_sav_file = std::fs::File::create(level_sav_path)?;
-    let mut level_sav_writer = BufWriter::new(level_sav_file);
-    write_save_file(&mut level_sav_writer, &level_save)?;
-    drop(level_sav_writer);
-    println!("Level.sav written successfully");
-
-    println!("All done! Press enter to exit...");
-    let mut input = String::new();
-    std::io::stdin().read_line(&mut input).unwrap();
+    if let Some(path) = &args.emit_patch {
+        write_patch(global, path, &patch)?;
+        print_unparsed_guilds(&unparsed_guilds);
+        return Ok(report);
+    }
+    // Computed from Level.sav before the changes are made to it
+    let undo_log = match &args.undo_log {
+        Some(path) => Some((path, inverse_patch(&level_save, &patch)?)),
+        None => None,
+    };
+    if !global.apply_changes(&mut level_save, &patch)? {
+        print_unparsed_guilds(&unparsed_guilds);
+        return Ok(report);
+    }
+    info!("{} changes made to Level.sav", patch.ops.len());
+    if let Some((path, undo_log)) = &undo_log {
+        write_undo_log(global, path, undo_log)?;
+    }
+    if args.backup {
+        store.backup_level()?;
+    }
+    global.write_level(
+        store,
+        &level_save,
+        args.write_compression
+            .unwrap_or(level_save.compression_type),
+    )?;
+    info!("Level.sav written successfully");
+
+    print_unparsed_guilds(&unparsed_guilds);
+    Ok(report)
+}
+
+/// Runs every non-destructive fix in one pass with one write. Destructive operations are not
+/// part of this and stay opt-in.
+fn repair_all(
+    global: &GlobalArgs,
+    input: &Path,
+    output_dir: Option<&Path>,
+    force: bool,
+) -> anyhow::Result<FixReport> {
+    let args = FixArgs {
+        input: Some(input.to_path_buf()),
+        fix_admin: true,
+        fix_group_ids: true,
+        // A copy in --output-dir already leaves the input as a backup
+        backup: output_dir.is_none(),
+        output_dir: output_dir.map(Path::to_path_buf),
+        force,
+        players: Vec::new(),
+        skip_players: Vec::new(),
+        recursive_players: false,
+        template: None,
+        write_compression: None,
+        disambiguate_nicknames: false,
+        seed_from_sibling: false,
+        emit_patch: None,
+        parse_timeout: None,
+        max_open_files: DEFAULT_MAX_OPEN_FILES,
+        report_format: None,
+        report_empty_guilds: false,
+        emit_remediation: None,
+        skip_duplicate_instance_ids: false,
+        exclude_guilds: Vec::new(),
+        undo_log: None,
+    };
+    fix_save_directory(global, &args)
+}
+
+fn rename_guild(
+    global: &GlobalArgs,
+    confirm: &mut Confirm,
+    input: &Path,
+    guild_id: Uuid,
+    name: &str,
+) -> anyhow::Result<Patch> {
+    let level_sav_path = input.join("Level.sav");
+    let mut level_save = global.read_level_save(input)?;
+    let game_version = global.game_version(&level_save);
+    let (_, mut group) = find_guild(&level_save, game_version, guild_id)?;
+    let mut patch = Patch::default();
+    if group.GuildName != name {
+        let action = format!(
+            "Rename guild {} from {} to {}",
+            guild_id, group.GuildName, name
+        );
+        if !global.dry_run && !confirm.ask(&action, 1, "guild")? {
+            info!("Aborted, Level.sav was not changed");
+            return Ok(patch);
+        }
+        group.GuildName = name.to_string();
+        patch.ops.push(PatchOp::SetGuildRawData {
+            guild_id,
+            raw_data: write_group_guild_save(&group),
+        });
+    }
+    let changed = global.apply_changes(&mut level_save, &patch)?;
+    let compression_type = level_save.compression_type;
+    global.write_save_file_if_changed(&level_sav_path, &level_save, changed, compression_type)?;
+    Ok(patch)
+}
+
+fn set_base_camp_level(
+    global: &GlobalArgs,
+    confirm: &mut Confirm,
+    input: &Path,
+    guild_id: Uuid,
+    level: u32,
+) -> anyhow::Result<Patch> {
+    let level_sav_path = input.join("Level.sav");
+    let mut level_save = global.read_level_save(input)?;
+    let game_version = global.game_version(&level_save);
+    let (_, mut group) = find_guild(&level_save, game_version, guild_id)?;
+    let mut patch = Patch::default();
+    if group.BaseCampLevel != level {
+        let action = format!(
+            "Set the base camp level of guild {}({}) from {} to {}",
+            group.GuildName, guild_id, group.BaseCampLevel, level
+        );
+        if !global.dry_run && !confirm.ask(&action, 1, "guild")? {
+            info!("Aborted, Level.sav was not changed");
+            return Ok(patch);
+        }
+        group.BaseCampLevel = level;
+        patch.ops.push(PatchOp::SetGuildRawData {
+            guild_id,
+            raw_data: write_group_guild_save(&group),
+        });
+    }
+    let changed = global.apply_changes(&mut level_save, &patch)?;
+    let compression_type = level_save.compression_type;
+    global.write_save_file_if_changed(&level_sav_path, &level_save, changed, compression_type)?;
+    Ok(patch)
+}
+
+fn merge_guilds(
+    global: &GlobalArgs,
+    confirm: &mut Confirm,
+    store: &mut dyn SaveStore,
+    source: Uuid,
+    destination: Uuid,
+    backup: bool,
+) -> anyhow::Result<Patch> {
+    let mut level_save = store.read_level(&global.types)?;
+    global.after_read("Level.sav", &level_save);
+    let game_version = global.game_version(&level_save);
+    let (_, source_group) = find_guild(&level_save, game_version, source)?;
+    let (_, destination_group) = find_guild(&level_save, game_version, destination)?;
+    let action = format!(
+        "Merge guild {}({}) into {}({}) and delete {}",
+        source_group.GuildName, source, destination_group.GuildName, destination, source
+    );
+    let (patch, moved_characters, failures) = merge_guild_patch(
+        &level_save,
+        game_version,
+        source,
+        destination,
+        global.keep_going,
+    )?;
+    if !global.dry_run && !confirm.ask(&action, source_group.GuildPlayerInfo.len(), "members")? {
+        info!("Aborted, Level.sav was not changed");
+        return Ok(Patch::default());
+    }
+    if !global.apply_changes(&mut level_save, &patch)? {
+        global.check_failures(&failures)?;
+        return Ok(patch);
+    }
+    info!(
+        "Guild {} merged into {}, {} characters moved",
+        source,
+        destination,
+        moved_characters.len()
+    );
+    if backup {
+        store.backup_level()?;
+    }
+    global.write_level(store, &level_save, level_save.compression_type)?;
+    info!("Level.sav written successfully");
+    global.check_failures(&failures)?;
+    Ok(patch)
+}
+
+fn prune_empty_guilds(
+    global: &GlobalArgs,
+    confirm: &mut Confirm,
+    store: &mut dyn SaveStore,
+    backup: bool,
+) -> anyhow::Result<Patch> {
+    let mut level_save = store.read_level(&global.types)?;
+    global.after_read("Level.sav", &level_save);
+    let game_version = global.game_version(&level_save);
+    let (patch, empty_guilds) = prune_empty_guilds_patch(&level_save, game_version);
+    if empty_guilds.is_empty() {
+        info!("No empty guilds, Level.sav was not changed");
+        return Ok(patch);
+    }
+    empty_guilds.iter().for_each(|(guild_id, name)| {
+        info!(
+            "Guild {}({}) has no members and no InstanceIds",
+            name, guild_id
+        );
+    });
+    let action = "Delete the guilds without members and InstanceIds";
+    if !global.dry_run && !confirm.ask(action, empty_guilds.len(), "guilds")? {
+        info!("Aborted, Level.sav was not changed");
+        return Ok(Patch::default());
+    }
+    if !global.apply_changes(&mut level_save, &patch)? {
+        return Ok(patch);
+    }
+    empty_guilds.iter().for_each(|(guild_id, name)| {
+        println!("Removed guild {} ({})", guild_id, name);
+    });
+    if backup {
+        store.backup_level()?;
+    }
+    global.write_level(store, &level_save, level_save.compression_type)?;
+    info!("Level.sav written successfully");
+    Ok(patch)
+}
+
+fn write_patch(global: &GlobalArgs, path: &Path, patch: &Patch) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    write_json(BufWriter::new(file), patch, global.json_style())?;
+    info!(
+        "{} changes written to {} instead of Level.sav",
+        patch.ops.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// Written before Level.sav, so a log that cannot be written stops the fix before the save
+/// changes. A log left by a failed Level.sav write undoes nothing: applying it either fails or
+/// restores what is already there.
+fn write_undo_log(global: &GlobalArgs, path: &Path, undo_log: &Patch) -> anyhow::Result<()> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    write_json(BufWriter::new(file), undo_log, global.json_style())?;
+    info!("Undo log written to {}", path.display());
+    Ok(())
+}
+
+fn read_patch_file(path: &Path) -> anyhow::Result<Patch> {
+    read_patch(std::io::BufReader::new(
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+    ))
+    .with_context(|| format!("Failed to read patch {}", path.display()))
+}
+
+/// Replays a patch written by --emit-patch on the Level.sav of `store`.
+fn apply_patch_store(
+    global: &GlobalArgs,
+    patch: &Patch,
+    backup: bool,
+    store: &mut dyn SaveStore,
+) -> anyhow::Result<()> {
+    let mut level_save = store.read_level(&global.types)?;
+    global.after_read("Level.sav", &level_save);
+    let changed = global.apply_changes(&mut level_save, patch)?;
+    if global.dry_run {
+        return Ok(());
+    }
+    info!("{} changes applied", patch.ops.len());
+    if !changed && !global.force_write {
+        info!("No changes to write to Level.sav");
+        return Ok(());
+    }
+    if backup {
+        store.backup_level()?;
+    }
+    global.write_level(store, &level_save, level_save.compression_type)?;
+    info!("Level.sav written successfully");
     Ok(())
 }
+
+fn print_unparsed_guilds(unparsed_guilds: &[(Uuid, anyhow::Error)]) {
+    if unparsed_guilds.is_empty() {
+        return;
+    }
+    info!("These guilds could not be parsed and need manual attention:");
+    unparsed_guilds.iter().for_each(|(guild_id, error)| {
+        info!("- {}: {:#}", guild_id, error);
+    });
+}
+
+#[test]
+pub fn test_fix_save_directory_output_dir() {
+    use crate::fixtures;
+
+    let input = fixtures::temp_dir("output_dir_input");
+    let output = fixtures::temp_dir("output_dir_output");
+    let broken_players = fixtures::write_broken_save_directory(&input, 1);
+    let input_level_sav = std::fs::read(input.join("Level.sav")).unwrap();
+
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        input.to_str().unwrap(),
+        "--output-dir",
+        output.to_str().unwrap(),
+    ]);
+    fix_save_directory(&args.global, &args.fix).unwrap();
+
+    assert_eq!(
+        input_level_sav,
+        std::fs::read(input.join("Level.sav")).unwrap()
+    );
+    let output_level_save = sav::read_save_file_path(&output.join("Level.sav")).unwrap();
+    assert_eq!(
+        fixtures::character_instance_ids(&output_level_save),
+        vec![broken_players[0].1]
+    );
+
+    // A second run must not overwrite the now non-empty output directory
+    assert!(fix_save_directory(&args.global, &args.fix).is_err());
+}
+
+#[test]
+pub fn test_fix_save_directory_is_deterministic() {
+    use crate::fixtures;
+
+    let input = fixtures::temp_dir("deterministic_input");
+    let mut broken_players = fixtures::write_broken_save_directory(&input, 3);
+    let outputs: Vec<PathBuf> = (0..2)
+        .map(|i| {
+            let output = fixtures::temp_dir(&format!("deterministic_output_{}", i));
+            let args = Args::parse_from([
+                "palworld-guild-save-fix",
+                input.to_str().unwrap(),
+                "--output-dir",
+                output.to_str().unwrap(),
+            ]);
+            fix_save_directory(&args.global, &args.fix).unwrap();
+            output
+        })
+        .collect();
+
+    assert_eq!(
+        std::fs::read(outputs[0].join("Level.sav")).unwrap(),
+        std::fs::read(outputs[1].join("Level.sav")).unwrap()
+    );
+    let level_save = sav::read_save_file_path(&outputs[0].join("Level.sav")).unwrap();
+    broken_players.sort_by_key(|(_, instance_id)| *instance_id);
+    assert_eq!(
+        fixtures::character_instance_ids(&level_save),
+        broken_players
+            .iter()
+            .map(|(_, instance_id)| *instance_id)
+            .collect::<Vec<_>>()
+    );
+}
+
+#[test]
+pub fn test_fix_save_directory_skip_player() {
+    use crate::fixtures;
+
+    let input = fixtures::temp_dir("skip_player_input");
+    let output = fixtures::temp_dir("skip_player_output");
+    let broken_players = fixtures::write_broken_save_directory(&input, 2);
+    let skipped = broken_players[0].0.to_string();
+
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        input.to_str().unwrap(),
+        "--output-dir",
+        output.to_str().unwrap(),
+        "--skip-player",
+        skipped.as_str(),
+        "--player",
+        skipped.as_str(),
+    ]);
+    assert!(fix_save_directory(&args.global, &args.fix).is_err());
+
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        input.to_str().unwrap(),
+        "--output-dir",
+        output.to_str().unwrap(),
+        "--skip-player",
+        skipped.as_str(),
+    ]);
+    fix_save_directory(&args.global, &args.fix).unwrap();
+
+    let level_save = sav::read_save_file_path(&output.join("Level.sav")).unwrap();
+    assert_eq!(
+        fixtures::character_instance_ids(&level_save),
+        vec![broken_players[1].1]
+    );
+}
+
+#[test]
+pub fn test_fix_save_directory_skips_unparsable_guild() {
+    use crate::fixtures;
+
+    let input = fixtures::temp_dir("unparsable_guild_input");
+    let output = fixtures::temp_dir("unparsable_guild_output");
+    let broken_players = fixtures::write_broken_save_directory(&input, 1);
+    let mut level_save = sav::read_save_file_path(&input.join("Level.sav")).unwrap();
+    let mut corrupt_guild = std::fs::read("assets/guild_0.bin").unwrap();
+    corrupt_guild.truncate(100);
+    fixtures::add_guild_raw(&mut level_save, Uuid::from_u128(0x200), corrupt_guild);
+    fixtures::write_save(&input.join("Level.sav"), &level_save);
+
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        input.to_str().unwrap(),
+        "--output-dir",
+        output.to_str().unwrap(),
+    ]);
+    fix_save_directory(&args.global, &args.fix).unwrap();
+
+    let level_save = sav::read_save_file_path(&output.join("Level.sav")).unwrap();
+    assert_eq!(
+        fixtures::character_instance_ids(&level_save),
+        vec![broken_players[0].1]
+    );
+}
+
+#[test]
+pub fn test_rename_guild_without_changes_does_not_write() {
+    use crate::fixtures;
+
+    let dir = fixtures::temp_dir("rename_guild_no_op");
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    let level_sav_path = dir.join("Level.sav");
+    fixtures::write_save(&level_sav_path, &level_save);
+    let modified = std::fs::metadata(&level_sav_path)
+        .unwrap()
+        .modified()
+        .unwrap();
+    std::thread::sleep(std::time::Duration::from_millis(10));
+
+    let guild_id = Uuid::from_u128(0x100).to_string();
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        "rename-guild",
+        dir.to_str().unwrap(),
+        "--guild",
+        guild_id.as_str(),
+        "--name",
+        group_guild_save.GuildName.as_str(),
+    ]);
+    let Some(Command::RenameGuild { input, guild, name }) = args.command else {
+        panic!()
+    };
+    let mut confirm = Confirm::with_answers(false, "".as_bytes());
+    rename_guild(&args.global, &mut confirm, &input, guild, &name).unwrap();
+    assert_eq!(
+        std::fs::metadata(&level_sav_path)
+            .unwrap()
+            .modified()
+            .unwrap(),
+        modified
+    );
+
+    let mut confirm = Confirm::with_answers(false, "y\n".as_bytes());
+    rename_guild(&args.global, &mut confirm, &input, guild, "renamed").unwrap();
+    let level_save = sav::read_save_file_path(&level_sav_path).unwrap();
+    let groups = get_guilds(&level_save, GameVersion::V0_1_3).unwrap();
+    assert_eq!(groups[0].1.GuildName, "renamed");
+}
+
+#[test]
+pub fn test_set_base_camp_level() {
+    use crate::{
+        fixtures,
+        group_guild::{read_group_guild_save, stream},
+        sav::{get_raw_data, is_group_type_guild},
+    };
+
+    let dir = fixtures::temp_dir("set_base_camp_level");
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    let level_sav_path = dir.join("Level.sav");
+    fixtures::write_save(&level_sav_path, &level_save);
+    let guild = Uuid::from_u128(0x100).to_string();
+    let args = |level: &str| {
+        Args::try_parse_from([
+            "palworld-guild-save-fix",
+            "set-base-camp-level",
+            dir.to_str().unwrap(),
+            guild.as_str(),
+            level,
+            "-y",
+        ])
+    };
+    assert_ne!(group_guild_save.BaseCampLevel, 7);
+    assert!(args(&(MAX_BASE_CAMP_LEVEL + 1).to_string()).is_err());
+
+    run(&args("7").unwrap()).unwrap();
+    let level_save = sav::read_save_file_path(&level_sav_path).unwrap();
+    let raw_data = get_group_save_data_map(&level_save)
+        .iter()
+        .find(|entry| is_group_type_guild(entry))
+        .map(|entry| get_raw_data(entry).unwrap().into_owned())
+        .unwrap();
+    let group = read_group_guild_save(&mut stream(&raw_data)).unwrap();
+    assert_eq!(group.BaseCampLevel, 7);
+    assert_eq!(group.GuildName, group_guild_save.GuildName);
+    assert_eq!(
+        group.GuildPlayerInfo.len(),
+        group_guild_save.GuildPlayerInfo.len()
+    );
+    assert_eq!(write_group_guild_save(&group), raw_data);
+}
+
+#[test]
+pub fn test_rename_guild_asks_for_confirmation() {
+    use crate::fixtures;
+
+    let dir = fixtures::temp_dir("rename_guild_confirm");
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    let level_sav_path = dir.join("Level.sav");
+    fixtures::write_save(&level_sav_path, &level_save);
+    let level_sav = std::fs::read(&level_sav_path).unwrap();
+    let guild_id = Uuid::from_u128(0x100);
+    let guild = guild_id.to_string();
+    let rename = |assume_yes: bool, answers: &'static str| {
+        let mut argv = vec![
+            "palworld-guild-save-fix",
+            "rename-guild",
+            dir.to_str().unwrap(),
+            "--guild",
+            guild.as_str(),
+            "--name",
+            "renamed",
+        ];
+        if assume_yes {
+            argv.push("-y");
+        }
+        let args = Args::parse_from(argv);
+        let mut confirm = Confirm::with_answers(args.global.assume_yes, answers.as_bytes());
+        rename_guild(&args.global, &mut confirm, &dir, guild_id, "renamed").unwrap();
+    };
+
+    rename(false, "n\n");
+    assert_eq!(std::fs::read(&level_sav_path).unwrap(), level_sav);
+
+    rename(true, "");
+    let level_save = sav::read_save_file_path(&level_sav_path).unwrap();
+    let groups = get_guilds(&level_save, GameVersion::V0_1_3).unwrap();
+    assert_eq!(groups[0].1.GuildName, "renamed");
+}
+
+#[test]
+pub fn test_rename_guild_dry_run() {
+    use crate::{
+        fixtures,
+        group_guild::{read_group_guild_save, stream},
+    };
+
+    let dir = fixtures::temp_dir("rename_guild_dry_run");
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_guild(
+        &mut level_save,
+        Uuid::from_u128(0x100),
+        &fixtures::read_guild(),
+    );
+    let level_sav_path = dir.join("Level.sav");
+    fixtures::write_save(&level_sav_path, &level_save);
+    let level_sav = std::fs::read(&level_sav_path).unwrap();
+    let guild_id = Uuid::from_u128(0x100);
+    let guild = guild_id.to_string();
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        "rename-guild",
+        dir.to_str().unwrap(),
+        "--guild",
+        guild.as_str(),
+        "--name",
+        "renamed",
+        "--dry-run",
+        "--force-write",
+    ]);
+    // No prompt, since nothing is changed
+    let mut confirm = Confirm::with_answers(false, "".as_bytes());
+    let patch = rename_guild(&args.global, &mut confirm, &dir, guild_id, "renamed").unwrap();
+
+    assert_eq!(patch.ops.len(), 1);
+    let PatchOp::SetGuildRawData {
+        guild_id: patched_guild_id,
+        raw_data,
+    } = &patch.ops[0]
+    else {
+        panic!("{:?}", patch.ops[0])
+    };
+    assert_eq!(*patched_guild_id, guild_id);
+    let group = read_group_guild_save(&mut stream(raw_data)).unwrap();
+    assert_eq!(group.GuildName, "renamed");
+    assert!(patch.ops[0]
+        .describe()
+        .starts_with(&format!("Set RawData of guild {}", guild_id)));
+    assert_eq!(std::fs::read(&level_sav_path).unwrap(), level_sav);
+}
+
+#[test]
+pub fn test_merge_guilds_asks_for_confirmation() {
+    use crate::fixtures;
+
+    let dir = fixtures::temp_dir("merge_guilds_confirm");
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x200), &group_guild_save);
+    let level_sav_path = dir.join("Level.sav");
+    fixtures::write_save(&level_sav_path, &level_save);
+    let level_sav = std::fs::read(&level_sav_path).unwrap();
+    // Dropping one of the two guilds shrinks this small save by far more than --max-shrink
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        "stats",
+        dir.to_str().unwrap(),
+        "--allow-shrink",
+    ]);
+    let merge = |answers: &'static str| {
+        let mut confirm = Confirm::with_answers(false, answers.as_bytes());
+        let mut store = FsSaveStore {
+            dir: dir.clone(),
+            recursive_players: false,
+        };
+        merge_guilds(
+            &args.global,
+            &mut confirm,
+            &mut store,
+            Uuid::from_u128(0x200),
+            Uuid::from_u128(0x100),
+            true,
+        )
+        .unwrap();
+    };
+
+    merge("");
+    assert_eq!(std::fs::read(&level_sav_path).unwrap(), level_sav);
+    assert!(!dir.join("Level.sav.bak").exists());
+
+    merge("y\n");
+    assert_eq!(std::fs::read(dir.join("Level.sav.bak")).unwrap(), level_sav);
+    let level_save = sav::read_save_file_path(&level_sav_path).unwrap();
+    let groups = get_guilds(&level_save, GameVersion::V0_1_3).unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].0, Uuid::from_u128(0x100));
+    // The guilds have the same members, which are not added twice
+    assert_eq!(
+        groups[0].1.GuildPlayerInfo.len(),
+        group_guild_save.GuildPlayerInfo.len()
+    );
+}
+
+#[test]
+pub fn test_prune_empty_guilds_asks_for_confirmation() {
+    use crate::fixtures;
+
+    let dir = fixtures::temp_dir("prune_empty_guilds_confirm");
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    let mut empty_guild = group_guild_save.clone();
+    empty_guild.GuildPlayerInfo.clear();
+    empty_guild.InstanceIds.clear();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x200), &empty_guild);
+    let level_sav_path = dir.join("Level.sav");
+    fixtures::write_save(&level_sav_path, &level_save);
+    let level_sav = std::fs::read(&level_sav_path).unwrap();
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        "prune-empty-guilds",
+        dir.to_str().unwrap(),
+        "--allow-shrink",
+    ]);
+    let prune = |answers: &'static str| {
+        let mut confirm = Confirm::with_answers(false, answers.as_bytes());
+        let mut store = FsSaveStore {
+            dir: dir.clone(),
+            recursive_players: false,
+        };
+        prune_empty_guilds(&args.global, &mut confirm, &mut store, true).unwrap()
+    };
+
+    prune("");
+    assert_eq!(std::fs::read(&level_sav_path).unwrap(), level_sav);
+    assert!(!dir.join("Level.sav.bak").exists());
+
+    assert_eq!(prune("y\n").ops.len(), 1);
+    assert_eq!(std::fs::read(dir.join("Level.sav.bak")).unwrap(), level_sav);
+    let level_save = sav::read_save_file_path(&level_sav_path).unwrap();
+    let groups = get_guilds(&level_save, GameVersion::V0_1_3).unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].0, Uuid::from_u128(0x100));
+
+    // Nothing left to prune
+    assert!(prune("").ops.is_empty());
+}
+
+#[test]
+pub fn test_write_level_refuses_to_shrink() {
+    use crate::{fixtures, sav::get_group_save_data_map_mut, save_store::MemSaveStore};
+
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_guild(
+        &mut level_save,
+        Uuid::from_u128(0x100),
+        &fixtures::read_guild(),
+    );
+    let mut store = MemSaveStore::new(&level_save, &[]).unwrap();
+    let level = store.level.clone();
+    assert_eq!(
+        store.level_uncompressed_length().unwrap(),
+        uncompressed_length(&level_save).unwrap()
+    );
+
+    // A bug that dropped every guild
+    let mut small_level_save = level_save.clone();
+    get_group_save_data_map_mut(&mut small_level_save).clear();
+    let write = |argv: &[&str], store: &mut MemSaveStore| {
+        let args = Args::parse_from(argv);
+        args.global
+            .write_level(store, &small_level_save, small_level_save.compression_type)
+    };
+    let error = write(&["palworld-guild-save-fix", "stats", "save"], &mut store).unwrap_err();
+    let message = format!("{:#}", error);
+    assert!(
+        message.contains("Refusing to write Level.sav"),
+        "{}",
+        message
+    );
+    assert!(
+        message.contains(&uncompressed_length(&level_save).unwrap().to_string()),
+        "{}",
+        message
+    );
+    assert_eq!(store.level, level);
+
+    // The original is unchanged, so a write of the same save passes
+    let args = Args::parse_from(["palworld-guild-save-fix", "stats", "save"]);
+    args.global
+        .write_level(&mut store, &level_save, level_save.compression_type)
+        .unwrap();
+
+    write(
+        &[
+            "palworld-guild-save-fix",
+            "stats",
+            "save",
+            "--max-shrink",
+            "100",
+        ],
+        &mut store,
+    )
+    .unwrap();
+    assert_ne!(store.level, level);
+    let mut store = MemSaveStore::new(&level_save, &[]).unwrap();
+    write(
+        &["palworld-guild-save-fix", "stats", "save", "--allow-shrink"],
+        &mut store,
+    )
+    .unwrap();
+    assert_ne!(store.level, level);
+}
+
+#[test]
+pub fn test_repair_all_fixes_every_problem_in_one_pass() {
+    use crate::{fixtures, group_guild::is_admin_in_guild};
+
+    let input = fixtures::temp_dir("repair_all_input");
+    let broken_players = fixtures::write_broken_save_directory(&input, 3);
+    let guild_id = Uuid::from_u128(0x100);
+
+    // Give the last broken player a character save, then move it out of its guild
+    let player_uid = broken_players[2].0.to_string();
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        input.to_str().unwrap(),
+        "--player",
+        player_uid.as_str(),
+    ]);
+    fix_save_directory(&args.global, &args.fix).unwrap();
+    let level_sav_path = input.join("Level.sav");
+    let mut level_save = sav::read_save_file_path(&level_sav_path).unwrap();
+    fixtures::set_character_group_id(&mut level_save, 0, Uuid::from_u128(0x999));
+    // And make the guild admin someone who is not a member
+    fixtures::update_guild(&mut level_save, guild_id, |group| {
+        group.AdminPlayerUId = Uuid::from_u128(0x999);
+    });
+    fixtures::write_save(&level_sav_path, &level_save);
+
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        "repair-all",
+        input.to_str().unwrap(),
+    ]);
+    let report = repair_all(&args.global, &input, None, false).unwrap();
+    assert_eq!(report.fixed_admin_guild_ids, vec![guild_id]);
+    assert_eq!(
+        report.corrected_group_ids,
+        vec![(broken_players[2].1, guild_id)]
+    );
+    assert_eq!(report.added_character_saves.len(), 2);
+    assert!(input.join("Level.sav.bak").exists());
+
+    let level_save = sav::read_save_file_path(&level_sav_path).unwrap();
+    let groups = get_guilds(&level_save, GameVersion::V0_1_3).unwrap();
+    assert!(is_admin_in_guild(&groups[0].1));
+    let mut instance_ids = fixtures::character_instance_ids(&level_save);
+    instance_ids.sort();
+    let mut expected: Vec<Uuid> = broken_players.iter().map(|(_, id)| *id).collect();
+    expected.sort();
+    assert_eq!(instance_ids, expected);
+    (0..instance_ids.len()).for_each(|index| {
+        assert_eq!(fixtures::character_group_id(&level_save, index), guild_id);
+    });
+
+    // Everything is fixed, so a second pass has nothing to do
+    let report = repair_all(&args.global, &input, None, false).unwrap();
+    assert!(!report.has_changes());
+}
+
+#[test]
+pub fn test_fix_save_directory_refuses_instance_id_collision() {
+    use crate::fixtures;
+
+    let input = fixtures::temp_dir("instance_id_collision_input");
+    let broken_players = fixtures::write_broken_save_directory(&input, 2);
+    // The character with the second player's InstanceId belongs to the first player
+    let mut level_save = sav::read_save_file_path(&input.join("Level.sav")).unwrap();
+    fixtures::add_character_save(&mut level_save, broken_players[0].0, broken_players[1].1);
+    fixtures::write_save(&input.join("Level.sav"), &level_save);
+
+    let args = Args::parse_from(["palworld-guild-save-fix", input.to_str().unwrap()]);
+    let report = fix_save_directory(&args.global, &args.fix).unwrap();
+    assert_eq!(
+        report.instance_id_collisions,
+        vec![(
+            broken_players[1].0,
+            broken_players[1].1,
+            broken_players[0].0
+        )]
+    );
+    assert_eq!(report.added_character_saves, vec![broken_players[0]]);
+
+    let level_save = sav::read_save_file_path(&input.join("Level.sav")).unwrap();
+    assert_eq!(
+        fixtures::character_instance_ids(&level_save),
+        vec![broken_players[1].1, broken_players[0].1]
+    );
+}
+
+#[test]
+pub fn test_fix_save_store_in_memory() {
+    use crate::fixtures;
+
+    let (mut store, broken_players) = fixtures::broken_mem_save_store(2);
+    let original_level = store.level.clone();
+    let report = fixtures::run_fix(&["--backup"], &mut store).unwrap();
+
+    let mut expected = broken_players.clone();
+    expected.sort_by_key(|(_, instance_id)| *instance_id);
+    assert_eq!(report.added_character_saves, expected);
+    assert_eq!(store.level_backup, Some(original_level));
+    let level_save = store.read_level(&[]).unwrap();
+    assert_eq!(
+        fixtures::character_instance_ids(&level_save),
+        expected
+            .iter()
+            .map(|(_, instance_id)| *instance_id)
+            .collect::<Vec<_>>()
+    );
+
+    // A second run has nothing to fix and writes nothing
+    let level = store.level.clone();
+    let report = fixtures::run_fix(&["--backup"], &mut store).unwrap();
+    assert!(!report.has_changes());
+    assert_eq!(store.level, level);
+}
+
+#[test]
+pub fn test_fix_save_directory_skips_non_player_savs() {
+    use crate::fixtures;
+
+    let input = fixtures::temp_dir("non_player_savs");
+    let broken_players = fixtures::write_broken_save_directory(&input, 2);
+    let players = input.join("Players");
+    // Skipped by name, and a save named like a player save without SaveData.IndividualId
+    std::fs::write(players.join("notaplayer.sav"), b"not a save").unwrap();
+    let bogus_name = fixtures::player_sav_name(&Uuid::from_u128(0xb0b));
+    fixtures::write_save(
+        &players.join(&bogus_name),
+        &fixtures::level_meta_save(&fixtures::read_level_save(), "Host"),
+    );
+
+    let args = Args::parse_from(["palworld-guild-save-fix", input.to_str().unwrap()]);
+    let report = fix_save_directory(&args.global, &args.fix).unwrap();
+    assert_eq!(report.non_player_savs, vec![bogus_name]);
+    assert_eq!(report.added_character_saves.len(), broken_players.len());
+}
+
+#[test]
+pub fn test_dump_on_error() {
+    use crate::fixtures;
+
+    let input = fixtures::temp_dir("dump_on_error_input");
+    let mut level_save = fixtures::read_level_save();
+    let mut corrupt_guild = std::fs::read("assets/guild_0.bin").unwrap();
+    corrupt_guild.truncate(100);
+    fixtures::add_guild_raw(&mut level_save, Uuid::from_u128(0x200), corrupt_guild);
+    fixtures::write_save(&input.join("Level.sav"), &level_save);
+    let dir = fixtures::temp_dir("dump_on_error").join("diagnostics");
+
+    let args = Args::parse_from(["palworld-guild-save-fix", "stats", input.to_str().unwrap()]);
+    assert!(run_dumping_errors(&args).is_err());
+    assert!(!dir.exists());
+
+    let args = Args::parse_from([
+        "palworld-guild-save-fix",
+        "stats",
+        input.to_str().unwrap(),
+        "--dump-on-error",
+        dir.to_str().unwrap(),
+    ]);
+    assert!(run_dumping_errors(&args).is_err());
+    ["error.txt", "version.txt", "Level.sav.header.txt"]
+        .iter()
+        .for_each(|name| assert!(dir.join(name).exists(), "{}", name));
+}
+
+#[test]
+pub fn test_fix_save_store_keeps_character_order() {
+    use crate::{fixtures, sav::get_character_save_parameter_map_mut};
+
+    let (mut level_save, player_saves, broken_players) = fixtures::broken_save(2);
+    // Characters of players without a save, with the second one removed
+    (0x10..0x14).for_each(|i| {
+        fixtures::add_character_save(
+            &mut level_save,
+            Uuid::from_u128(0x1000 + i),
+            Uuid::from_u128(i),
+        );
+    });
+    get_character_save_parameter_map_mut(&mut level_save).remove(1);
+    let original_instance_ids = fixtures::character_instance_ids(&level_save);
+    assert_eq!(
+        original_instance_ids,
+        [0x10, 0x12, 0x13].map(Uuid::from_u128)
+    );
+
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    fixtures::run_fix(&[], &mut store).unwrap();
+
+    // The untouched characters keep their order and the new ones come after them
+    let instance_ids = fixtures::character_instance_ids(&store.read_level(&[]).unwrap());
+    assert_eq!(instance_ids[..3], original_instance_ids);
+    let mut added_instance_ids = instance_ids[3..].to_vec();
+    added_instance_ids.sort();
+    let mut expected: Vec<Uuid> = broken_players.iter().map(|(_, id)| *id).collect();
+    expected.sort();
+    assert_eq!(added_instance_ids, expected);
+}
+
+#[test]
+pub fn test_fix_save_store_exclude_guild() {
+    use crate::fixtures;
+
+    let (mut level_save, mut player_saves, broken_players) = fixtures::broken_save(1);
+    // A second guild with one broken member
+    let excluded_player_uid = Uuid::from_u128(0xb);
+    let mut excluded_guild = fixtures::read_guild();
+    excluded_guild.GuildPlayerInfo.truncate(1);
+    excluded_guild.GuildPlayerInfo[0].PlayerUId = excluded_player_uid;
+    excluded_guild.AdminPlayerUId = Uuid::from_u128(0xdeadbeef);
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x200), &excluded_guild);
+    player_saves.push((
+        excluded_player_uid,
+        fixtures::player_save(&level_save, excluded_player_uid, Uuid::from_u128(0xb1)),
+    ));
+
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    let guild = Uuid::from_u128(0x200).to_string();
+    let report = fixtures::run_fix(
+        &["--fix-admin", "--exclude-guild", guild.as_str()],
+        &mut store,
+    )
+    .unwrap();
+    assert_eq!(report.added_character_saves, broken_players);
+    assert!(report.fixed_admin_guild_ids.is_empty());
+    // Members of the excluded guild are not taken for players without a guild
+    assert!(report.guildless_player_uids.is_empty());
+    let groups = get_guilds(&store.read_level(&[]).unwrap(), GameVersion::V0_1_3).unwrap();
+    assert_eq!(groups[1].1.AdminPlayerUId, Uuid::from_u128(0xdeadbeef));
+}
+
+#[test]
+pub fn test_fix_save_store_reports_custom_format_mismatch() {
+    use crate::fixtures;
+
+    let (level_save, mut player_saves, broken_players) = fixtures::broken_save(2);
+    // As if dropped in from a newer game version
+    player_saves[1].1.save.header.custom_format_version += 1;
+
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    let report = fixtures::run_fix(&[], &mut store).unwrap();
+    assert_eq!(
+        report.custom_format_mismatches,
+        vec![(
+            fixtures::player_sav_name(&player_saves[1].0),
+            "custom format version 4, Level.sav has 3".to_string()
+        )]
+    );
+    assert_eq!(report.added_character_saves.len(), broken_players.len());
+}
+
+#[test]
+pub fn test_fix_save_store_without_guilds() {
+    use crate::fixtures;
+
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    let player_uid = Uuid::from_u128(1);
+    let player_saves = vec![(
+        player_uid,
+        fixtures::player_save(&level_save, player_uid, Uuid::from_u128(0x11)),
+    )];
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    let original_level = store.level.clone();
+
+    let report = fixtures::run_fix(&["--fix-group-ids"], &mut store).unwrap();
+    assert_eq!(report.guildless_player_uids, vec![player_uid]);
+    assert!(report.added_character_saves.is_empty());
+    assert!(!report.has_changes());
+    assert_eq!(store.level, original_level);
+}
+
+#[test]
+pub fn test_fix_save_store_empty_guild() {
+    use crate::{fixtures, stats::compute_guild_stats};
+
+    let (mut level_save, player_saves, broken_players) = fixtures::broken_save(2);
+    let mut empty_guild = fixtures::read_guild();
+    empty_guild.GuildPlayerInfo.clear();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x200), &empty_guild);
+
+    let groups = get_guilds(&level_save, detect_game_version(&level_save.save.header)).unwrap();
+    let stats = compute_guild_stats(&groups, 0, DEFAULT_MEMBER_CAP);
+    assert_eq!(stats.member_count_histogram.get(&0), Some(&1));
+    assert_eq!(stats.smallest_guild.unwrap().member_count, 0);
+
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    let report = fixtures::run_fix(&["--fix-admin", "--report-empty-guilds"], &mut store).unwrap();
+    assert_eq!(report.empty_guild_ids, vec![Uuid::from_u128(0x200)]);
+    assert!(report.fixed_admin_guild_ids.is_empty());
+    assert_eq!(report.added_character_saves.len(), broken_players.len());
+
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    let report = fixtures::run_fix(&[], &mut store).unwrap();
+    assert!(report.empty_guild_ids.is_empty());
+}
+
+#[test]
+pub fn test_fix_save_store_duplicate_instance_ids() {
+    use crate::fixtures;
+
+    let (level_save, mut player_saves, broken_players) = fixtures::broken_save(3);
+    // The third player's save claims the first player's InstanceId, as a copied save would
+    let (copied_player_uid, _) = broken_players[2];
+    let (_, shared_instance_id) = broken_players[0];
+    player_saves[2].1 = fixtures::player_save(&level_save, copied_player_uid, shared_instance_id);
+    let names: Vec<String> = player_saves
+        .iter()
+        .map(|(player_uid, _)| fixtures::player_sav_name(player_uid))
+        .collect();
+
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    let original_level = store.level.clone();
+    let error = fixtures::run_fix(&[], &mut store).unwrap_err().to_string();
+    assert!(
+        error.starts_with("2 player saves share an InstanceId"),
+        "{}",
+        error
+    );
+    [&names[0], &names[2]]
+        .iter()
+        .for_each(|name| assert!(error.contains(name.as_str()), "{}", error));
+    assert!(!error.contains(names[1].as_str()), "{}", error);
+    assert_eq!(store.level, original_level);
+
+    let mut report = fixtures::run_fix(&["--skip-duplicate-instance-ids"], &mut store).unwrap();
+    assert_eq!(report.added_character_saves, vec![broken_players[1]]);
+    // Player saves are read in name order
+    report.duplicate_instance_id_savs.sort();
+    let mut expected = vec![
+        (names[0].clone(), broken_players[0].0, shared_instance_id),
+        (names[2].clone(), copied_player_uid, shared_instance_id),
+    ];
+    expected.sort();
+    assert_eq!(report.duplicate_instance_id_savs, expected);
+}
+
+#[test]
+pub fn test_fix_save_store_disambiguate_nicknames() {
+    use crate::fixtures;
+
+    let (mut level_save, player_saves, broken_players) = fixtures::broken_save(2);
+    fixtures::update_guild(&mut level_save, Uuid::from_u128(0x100), |group| {
+        group.GuildPlayerInfo[1].PlayerName = group.GuildPlayerInfo[0].PlayerName.clone();
+    });
+    let name = fixtures::read_guild().GuildPlayerInfo[0].PlayerName.clone();
+
+    // New characters are appended in InstanceId order, which is the reverse of broken_players
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    fixtures::run_fix(&[], &mut store).unwrap();
+    let fixed_level_save = store.read_level(&[]).unwrap();
+    assert_eq!(fixtures::character_nickname(&fixed_level_save, 0), name);
+    assert_eq!(fixtures::character_nickname(&fixed_level_save, 1), name);
+
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    fixtures::run_fix(&["--disambiguate-nicknames"], &mut store).unwrap();
+    let fixed_level_save = store.read_level(&[]).unwrap();
+    broken_players
+        .iter()
+        .rev()
+        .enumerate()
+        .for_each(|(index, (player_uid, _))| {
+            assert_eq!(
+                fixtures::character_nickname(&fixed_level_save, index),
+                format!("{} ({})", name, &player_uid.simple().to_string()[..8])
+            );
+        });
+}
+
+#[test]
+pub fn test_fix_save_store_prefers_level_meta_player_name() {
+    use crate::{fixtures, level_meta::HOST_PLAYER_UID};
+
+    let mut group_guild_save = fixtures::read_guild();
+    group_guild_save.GuildPlayerInfo[0].PlayerUId = HOST_PLAYER_UID;
+    group_guild_save.GuildPlayerInfo[0].PlayerName = "Stale".into();
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    let player_saves = vec![(
+        HOST_PLAYER_UID,
+        fixtures::player_save(&level_save, HOST_PLAYER_UID, Uuid::from_u128(1)),
+    )];
+
+    // Without LevelMeta.sav the guild name is used
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    fixtures::run_fix(&[], &mut store).unwrap();
+    let fixed_level_save = store.read_level(&[]).unwrap();
+    assert_eq!(fixtures::character_nickname(&fixed_level_save, 0), "Stale");
+
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    let mut level_meta = Vec::new();
+    write_save_file(
+        &mut level_meta,
+        &fixtures::level_meta_save(&level_save, "Fresh"),
+    )
+    .unwrap();
+    store.level_meta = Some(level_meta);
+    fixtures::run_fix(&[], &mut store).unwrap();
+    let fixed_level_save = store.read_level(&[]).unwrap();
+    assert_eq!(fixtures::character_nickname(&fixed_level_save, 0), "Fresh");
+}
+
+#[test]
+pub fn test_fix_save_store_names_character_after_key_debug_name() {
+    use crate::{fixtures, sav::get_character_save_parameter_map_mut};
+
+    let (mut level_save, player_saves, broken_players) = fixtures::broken_save(1);
+    let (player_uid, _) = broken_players[0];
+    fixtures::update_guild(&mut level_save, Uuid::from_u128(0x100), |group| {
+        group.GuildPlayerInfo[0].PlayerName = String::new();
+    });
+    // A character of the player under an InstanceId their save no longer has
+    fixtures::add_character_save(&mut level_save, player_uid, Uuid::from_u128(0x99));
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(key)) =
+        &mut get_character_save_parameter_map_mut(&mut level_save)[0].key
+    else {
+        panic!()
+    };
+    key.insert(
+        "DebugName".into(),
+        uesave::Property::Str {
+            id: None,
+            value: "Old name".into(),
+        },
+    );
+
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    fixtures::run_fix(&[], &mut store).unwrap();
+    let fixed_level_save = store.read_level(&[]).unwrap();
+    assert_eq!(
+        fixtures::character_nickname(&fixed_level_save, 1),
+        "Old name"
+    );
+}
+
+#[test]
+pub fn test_emit_patch_and_apply_patch_match_direct_fix() {
+    use crate::fixtures;
+
+    let (mut level_save, player_saves, _) = fixtures::broken_save(2);
+    fixtures::update_guild(&mut level_save, Uuid::from_u128(0x100), |group| {
+        group.AdminPlayerUId = Uuid::from_u128(0xdeadbeef);
+    });
+
+    let mut direct_store = fixtures::mem_save_store(&level_save, &player_saves);
+    fixtures::run_fix(&["--fix-admin"], &mut direct_store).unwrap();
+
+    let patch_path = fixtures::temp_dir("emit_patch").join("patch.json");
+    let mut patched_store = fixtures::mem_save_store(&level_save, &player_saves);
+    let original_level = patched_store.level.clone();
+    fixtures::run_fix(
+        &["--fix-admin", "--emit-patch", patch_path.to_str().unwrap()],
+        &mut patched_store,
+    )
+    .unwrap();
+    assert_eq!(patched_store.level, original_level);
+
+    let patch = read_patch(std::fs::File::open(&patch_path).unwrap()).unwrap();
+    assert_eq!(patch.ops.len(), 3);
+    assert!(matches!(patch.ops[0], PatchOp::SetGuildRawData { .. }));
+    let args = Args::parse_from(["palworld-guild-save-fix", "unused"]);
+    apply_patch_store(&args.global, &patch, false, &mut patched_store).unwrap();
+    assert_eq!(
+        patched_store.read_level(&[]).unwrap(),
+        direct_store.read_level(&[]).unwrap()
+    );
+
+    // Replaying the patch would duplicate the new characters
+    assert!(apply_patch_store(&args.global, &patch, false, &mut patched_store).is_err());
+}
+
+#[test]
+pub fn test_undo_log_removes_added_characters() {
+    use crate::fixtures;
+
+    let (mut level_save, player_saves, _) = fixtures::broken_save(2);
+    fixtures::update_guild(&mut level_save, Uuid::from_u128(0x100), |group| {
+        group.AdminPlayerUId = Uuid::from_u128(0xdeadbeef);
+    });
+
+    let undo_log_path = fixtures::temp_dir("undo_log").join("undo.json");
+    let mut store = fixtures::mem_save_store(&level_save, &player_saves);
+    let original_level = store.read_level(&[]).unwrap();
+
+    // A log that cannot be written stops the fix before Level.sav changes
+    let original_bytes = store.level.clone();
+    let unwritable_path = undo_log_path.with_file_name("missing").join("undo.json");
+    assert!(fixtures::run_fix(
+        &[
+            "--fix-admin",
+            "--undo-log",
+            unwritable_path.to_str().unwrap()
+        ],
+        &mut store,
+    )
+    .is_err());
+    assert_eq!(store.level, original_bytes);
+
+    let report = fixtures::run_fix(
+        &["--fix-admin", "--undo-log", undo_log_path.to_str().unwrap()],
+        &mut store,
+    )
+    .unwrap();
+    assert_eq!(report.added_character_saves.len(), 2);
+
+    // A character the game added after the fix stays
+    let mut fixed_level = store.read_level(&[]).unwrap();
+    fixtures::add_character_save(&mut fixed_level, Uuid::from_u128(9), Uuid::from_u128(0x99));
+    store
+        .write_level(&fixed_level, fixed_level.compression_type)
+        .unwrap();
+
+    let undo_log = read_patch_file(&undo_log_path).unwrap();
+    assert_eq!(undo_log.ops.len(), 3);
+    assert!(matches!(
+        undo_log.ops[0],
+        PatchOp::RemoveCharacterSave { .. }
+    ));
+    let args = Args::parse_from(["palworld-guild-save-fix", "unused"]);
+    apply_patch_store(&args.global, &undo_log, false, &mut store).unwrap();
+    let mut expected_level = original_level;
+    fixtures::add_character_save(
+        &mut expected_level,
+        Uuid::from_u128(9),
+        Uuid::from_u128(0x99),
+    );
+    assert_eq!(store.read_level(&[]).unwrap(), expected_level);
+
+    // The added characters are gone, so the log cannot be applied twice
+    assert!(apply_patch_store(&args.global, &undo_log, false, &mut store).is_err());
+}
+
+#[test]
+pub fn test_strict_fails_on_warnings() {
+    use crate::fixtures;
+
+    // A guild without characters is a warning, not an error
+    let dir = fixtures::temp_dir("strict_fails_on_warnings");
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_guild(
+        &mut level_save,
+        Uuid::from_u128(0x100),
+        &fixtures::read_guild(),
+    );
+    fixtures::write_save(&dir.join("Level.sav"), &level_save);
+    let args = |strict: &[&str]| {
+        Args::parse_from(
+            [
+                "palworld-guild-save-fix",
+                "group-reconcile",
+                dir.to_str().unwrap(),
+                "--format",
+                "json",
+            ]
+            .iter()
+            .chain(strict),
+        )
+    };
+
+    assert!(run(&args(&[])).is_ok());
+    let error = run(&args(&["--strict"])).unwrap_err();
+    assert_eq!(error.to_string(), "1 warnings found and --strict is set");
+}
+
+#[test]
+pub fn test_keep_going_reports_every_unreadable_player_save() {
+    use crate::fixtures;
+
+    let input = fixtures::temp_dir("keep_going");
+    let broken_players = fixtures::write_broken_save_directory(&input, 2);
+    let unreadable: Vec<String> = [0xbad1, 0xbad2]
+        .iter()
+        .map(|player_uid| fixtures::player_sav_name(&Uuid::from_u128(*player_uid)))
+        .collect();
+    unreadable.iter().for_each(|name| {
+        std::fs::write(input.join("Players").join(name), b"not a save").unwrap();
+    });
+    let input_level_sav = std::fs::read(input.join("Level.sav")).unwrap();
+    let args = |keep_going: &[&str]| {
+        Args::parse_from(
+            ["palworld-guild-save-fix", input.to_str().unwrap(), "-q"]
+                .iter()
+                .chain(keep_going),
+        )
+    };
+
+    // The first unreadable save stops the fix before anything is written
+    assert!(run(&args(&[])).is_err());
+    assert_eq!(
+        std::fs::read(input.join("Level.sav")).unwrap(),
+        input_level_sav
+    );
+
+    let error = run(&args(&["--keep-going"])).unwrap_err().to_string();
+    assert!(
+        error.starts_with("2 failures skipped with --keep-going:\n"),
+        "{}",
+        error
+    );
+    unreadable.iter().for_each(|name| {
+        assert!(error.contains(name.as_str()), "{}", error);
+    });
+    // The other players were fixed all the same
+    let level_save = sav::read_save_file_path(&input.join("Level.sav")).unwrap();
+    let mut instance_ids = fixtures::character_instance_ids(&level_save);
+    instance_ids.sort();
+    let mut expected: Vec<Uuid> = broken_players
+        .iter()
+        .map(|(_, instance_id)| *instance_id)
+        .collect();
+    expected.sort();
+    assert_eq!(instance_ids, expected);
+}
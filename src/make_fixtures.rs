@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+
+use crate::{
+    sav::{
+        get_group_save_data_map, get_raw_data, is_group_type_guild, iter_character_entries, PalSave,
+    },
+    save_directory::ensure_empty_output_dir,
+};
+
+/// Name of the guild fixture, as in `assets/`.
+pub const GUILD_FIXTURE_NAME: &str = "guild_0.bin";
+/// Name of the character fixture, as in `assets/`.
+pub const CHARACTER_FIXTURE_NAME: &str = "character_save_parameter.bin";
+
+pub struct Fixtures {
+    /// `None` when the save has no guild
+    pub guild_path: Option<PathBuf>,
+    /// `None` when the save has no character
+    pub character_path: Option<PathBuf>,
+}
+
+/// Writes the `RawData` of the first guild of `GroupSaveDataMap` and of the first player
+/// character of `CharacterSaveParameterMap` (or the first character, when no player has one) to
+/// `output`, named like the fixtures in `assets/`. The blobs are copied byte for byte and not
+/// decoded, so a save of a game version whose layout the tool cannot parse yet can still be
+/// turned into fixtures. Fails when the save has neither a guild nor a character.
+pub fn make_fixtures(level_save: &PalSave, output: &Path, force: bool) -> anyhow::Result<Fixtures> {
+    let guild_raw_data = get_group_save_data_map(level_save)
+        .iter()
+        .find(|entry| is_group_type_guild(entry))
+        .map(get_raw_data)
+        .transpose()?;
+    let characters: Vec<_> = iter_character_entries(level_save)
+        .filter_map(|entry| entry.ok())
+        .collect();
+    let character_raw_data = characters
+        .iter()
+        .find(|(player_uid, _, _)| player_uid.is_some())
+        .or(characters.first())
+        .map(|(_, _, entry)| get_raw_data(entry))
+        .transpose()?;
+    if guild_raw_data.is_none() && character_raw_data.is_none() {
+        return Err(anyhow::anyhow!("Level.sav has no guild and no character"));
+    }
+
+    ensure_empty_output_dir(output, force)?;
+    std::fs::create_dir_all(output)
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+    let write_fixture = |name: &str, raw_data: &[u8]| -> anyhow::Result<PathBuf> {
+        let path = output.join(name);
+        std::fs::write(&path, raw_data)
+            .with_context(|| format!("Failed to write {}", path.display()))?;
+        Ok(path)
+    };
+    let guild_path = guild_raw_data
+        .map(|raw_data| write_fixture(GUILD_FIXTURE_NAME, &raw_data))
+        .transpose()?;
+    if guild_path.is_none() {
+        info!("Level.sav has no guild");
+    }
+    let character_path = character_raw_data
+        .map(|raw_data| write_fixture(CHARACTER_FIXTURE_NAME, &raw_data))
+        .transpose()?;
+    if character_path.is_none() {
+        info!("Level.sav has no character");
+    }
+    Ok(Fixtures {
+        guild_path,
+        character_path,
+    })
+}
+
+#[test]
+pub fn test_make_fixtures() {
+    use uuid::Uuid;
+
+    use crate::{
+        character_bin::export_character_raw_data,
+        character_save_parameter::read_raw_character_save_parameter,
+        fixtures,
+        group_guild::{read_group_guild_save, stream},
+        sav::read_save_file_path,
+    };
+
+    // assets/Level.sav has no guild and no character
+    let dir = fixtures::temp_dir("make_fixtures");
+    let output = dir.join("fixtures");
+    assert!(make_fixtures(&fixtures::read_level_save(), &output, false).is_err());
+    assert!(!output.exists());
+
+    let guild_raw_data = std::fs::read("assets/guild_0.bin").unwrap();
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_guild_raw(
+        &mut level_save,
+        Uuid::from_u128(0x100),
+        guild_raw_data.clone(),
+    );
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    // A pal first, so the player character is picked over it
+    fixtures::add_character_save(&mut level_save, Uuid::nil(), Uuid::from_u128(0x11));
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(1), Uuid::from_u128(0x12));
+    fixtures::set_character_group_id(&mut level_save, 0, Uuid::from_u128(0x200));
+    fixtures::write_save(&dir.join("Level.sav"), &level_save);
+    let level_save = read_save_file_path(&dir.join("Level.sav")).unwrap();
+
+    let made = make_fixtures(&level_save, &output, false).unwrap();
+    let guild_fixture = std::fs::read(made.guild_path.unwrap()).unwrap();
+    assert_eq!(guild_fixture, guild_raw_data);
+    read_group_guild_save(&mut stream(guild_fixture.as_ref())).unwrap();
+    let character_fixture = std::fs::read(made.character_path.unwrap()).unwrap();
+    assert_eq!(
+        character_fixture,
+        export_character_raw_data(&level_save, Uuid::from_u128(0x12)).unwrap()
+    );
+    assert_ne!(
+        character_fixture,
+        export_character_raw_data(&level_save, Uuid::from_u128(0x11)).unwrap()
+    );
+    read_raw_character_save_parameter(&level_save.save.header)(&character_fixture).unwrap();
+
+    // Existing fixtures are only overwritten with --force
+    assert!(make_fixtures(&level_save, &output, false).is_err());
+    make_fixtures(&level_save, &output, true).unwrap();
+}
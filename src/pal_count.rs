@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    group_guild::GameVersion,
+    sav::{
+        get_group_save_data_map, get_guilds_skipping_failures, is_group_type_guild,
+        iter_character_entries, parse_raw_character_save_parameter, PalSave,
+    },
+};
+
+/// The characters whose group id is a guild, split by the `PlayerUId` of their key.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct GuildPalCount {
+    pub guild_id: Uuid,
+    /// `None` when the guild RawData could not be parsed
+    pub guild_name: Option<String>,
+    /// Characters whose key has no `PlayerUId`
+    pub pal_count: usize,
+    pub player_count: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PalCountReport {
+    /// Every guild, in `GroupSaveDataMap` order, with or without characters
+    pub guilds: Vec<GuildPalCount>,
+    /// Characters without a group or in a group that is not a guild
+    pub characters_outside_guilds: usize,
+    /// Characters whose key or RawData could not be decoded
+    pub undecoded_characters: usize,
+}
+
+impl PalCountReport {
+    pub fn pal_count(&self) -> usize {
+        self.guilds.iter().map(|guild| guild.pal_count).sum()
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.guilds.iter().map(|guild| guild.player_count).sum()
+    }
+}
+
+/// Counts the pals and players of every guild by the group id in the RawData of each character.
+/// A character is a pal when its key has no `PlayerUId` (or a nil one).
+pub fn count_pals(level_save: &PalSave, version: GameVersion) -> PalCountReport {
+    let mut report = PalCountReport::default();
+    // group id -> (pals, players)
+    let mut counts: HashMap<Uuid, (usize, usize)> = HashMap::new();
+    let header = &level_save.save.header;
+    iter_character_entries(level_save).for_each(|entry| {
+        let Ok((player_uid, _, entry)) = entry else {
+            report.undecoded_characters += 1;
+            return;
+        };
+        let Ok(character_save_parameter) = parse_raw_character_save_parameter(header)(entry) else {
+            report.undecoded_characters += 1;
+            return;
+        };
+        let (pals, players) = counts.entry(character_save_parameter.group_id).or_default();
+        match player_uid {
+            Some(_) => *players += 1,
+            None => *pals += 1,
+        }
+    });
+
+    let (guilds, _) = get_guilds_skipping_failures(level_save, version);
+    report.guilds = get_group_save_data_map(level_save)
+        .iter()
+        .filter(|entry| is_group_type_guild(entry))
+        .filter_map(|entry| match entry.key {
+            uesave::PropertyValue::Struct(uesave::StructValue::Guid(guild_id)) => Some(guild_id),
+            _ => None,
+        })
+        .map(|guild_id| {
+            let (pal_count, player_count) = counts.remove(&guild_id).unwrap_or_default();
+            GuildPalCount {
+                guild_id,
+                guild_name: guilds
+                    .iter()
+                    .find(|(id, _)| *id == guild_id)
+                    .map(|(_, group_guild_save)| group_guild_save.GuildName.clone()),
+                pal_count,
+                player_count,
+            }
+        })
+        .collect();
+    report.characters_outside_guilds = counts.values().map(|(pals, players)| pals + players).sum();
+    report
+}
+
+pub fn print_pal_count_report(report: &PalCountReport) {
+    report.guilds.iter().for_each(|guild| {
+        println!(
+            "{}({}): {} pals, {} players",
+            guild.guild_name.as_deref().unwrap_or("(unparsed guild)"),
+            guild.guild_id,
+            guild.pal_count,
+            guild.player_count
+        );
+    });
+    println!(
+        "Total: {} pals, {} players in {} guilds",
+        report.pal_count(),
+        report.player_count(),
+        report.guilds.len()
+    );
+    if report.characters_outside_guilds > 0 {
+        println!(
+            "Characters outside guilds: {}",
+            report.characters_outside_guilds
+        );
+    }
+    if report.undecoded_characters > 0 {
+        println!(
+            "Characters that could not be decoded: {}",
+            report.undecoded_characters
+        );
+    }
+}
+
+#[test]
+pub fn test_count_pals() {
+    use crate::fixtures;
+
+    // assets/Level.sav has neither guilds nor characters
+    let mut level_save = fixtures::read_level_save();
+    let report = count_pals(&level_save, GameVersion::V0_1_3);
+    assert!(report.guilds.is_empty());
+    assert_eq!(report.characters_outside_guilds, 0);
+
+    fixtures::add_guild(
+        &mut level_save,
+        Uuid::from_u128(0x100),
+        &fixtures::read_guild(),
+    );
+    fixtures::add_guild_raw(&mut level_save, Uuid::from_u128(0x200), vec![0xff; 7]);
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    // Every character starts in guild 0x100
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(1), Uuid::from_u128(0x11));
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(2), Uuid::from_u128(0x12));
+    (0x21..=0x23).for_each(|instance_id| {
+        fixtures::add_character_save(&mut level_save, Uuid::nil(), Uuid::from_u128(instance_id));
+    });
+    fixtures::add_character_save(&mut level_save, Uuid::nil(), Uuid::from_u128(0x24));
+    fixtures::set_character_group_id(&mut level_save, 5, Uuid::from_u128(0x999));
+
+    let report = count_pals(&level_save, GameVersion::V0_1_3);
+    assert_eq!(
+        report.guilds,
+        vec![
+            GuildPalCount {
+                guild_id: Uuid::from_u128(0x100),
+                guild_name: Some(fixtures::read_guild().GuildName),
+                pal_count: 3,
+                player_count: 2,
+            },
+            GuildPalCount {
+                guild_id: Uuid::from_u128(0x200),
+                guild_name: None,
+                pal_count: 0,
+                player_count: 0,
+            },
+        ]
+    );
+    assert_eq!(report.characters_outside_guilds, 1);
+    assert_eq!(report.undecoded_characters, 0);
+    assert_eq!((report.pal_count(), report.player_count()), (3, 2));
+}
@@ -0,0 +1,50 @@
+use std::{sync::mpsc, time::Duration};
+
+/// Runs `parse` on a worker thread and waits at most `timeout` for it, or runs it in place
+/// without a timeout. Returns `Ok(None)` when the parse ran over. Parsing cannot be cancelled
+/// midway, so such a parse is left to finish in the background and its result is dropped.
+pub fn parse_with_timeout<T: Send + 'static>(
+    timeout: Option<Duration>,
+    parse: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> anyhow::Result<Option<T>> {
+    let Some(timeout) = timeout else {
+        return parse().map(Some);
+    };
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        // The receiver is gone when the parse ran over
+        let _ = sender.send(parse());
+    });
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result.map(Some),
+        Err(mpsc::RecvTimeoutError::Timeout) => Ok(None),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(anyhow::anyhow!("Parsing panicked")),
+    }
+}
+
+#[test]
+pub fn test_parse_with_timeout() {
+    use std::time::Instant;
+
+    assert_eq!(
+        parse_with_timeout(Some(Duration::from_secs(10)), || Ok(1)).unwrap(),
+        Some(1)
+    );
+    assert!(
+        parse_with_timeout(Some(Duration::from_secs(10)), || -> anyhow::Result<()> {
+            Err(anyhow::anyhow!("Invalid magic"))
+        })
+        .is_err()
+    );
+    assert_eq!(parse_with_timeout(None, || Ok(1)).unwrap(), Some(1));
+
+    // A deliberately slow parse, far longer than the timeout
+    let start = Instant::now();
+    let parsed = parse_with_timeout(Some(Duration::from_millis(100)), || {
+        std::thread::sleep(Duration::from_secs(30));
+        Ok(1)
+    })
+    .unwrap();
+    assert_eq!(parsed, None);
+    assert!(start.elapsed() < Duration::from_secs(10));
+}
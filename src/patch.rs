@@ -0,0 +1,593 @@
+use std::{collections::HashMap, io::Read};
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::sav::{
+    get_character_key_ids, get_character_save_parameter_map_mut, get_group_save_data_map,
+    get_group_save_data_map_mut, get_raw_data, iter_character_entries, match_character_key_shape,
+    new_character_save_entry_raw, patch_guild_raw, set_character_instance_id, set_raw_data,
+    PalSave,
+};
+
+/// One change to Level.sav. `RawData` is stored as hex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PatchOp {
+    /// Replace the `RawData` of the group `guild_id` in `GroupSaveDataMap`
+    SetGuildRawData {
+        guild_id: Uuid,
+        #[serde(with = "hex_bytes")]
+        raw_data: Vec<u8>,
+    },
+    /// Overwrite the `RawData` of the group `guild_id` with `bytes` at `offset`, keeping the
+    /// other bytes. Commands do not write it; it is for hand-written patches, see
+    /// [`patch_guild_raw`]
+    PatchGuildRawData {
+        guild_id: Uuid,
+        offset: usize,
+        #[serde(with = "hex_bytes")]
+        bytes: Vec<u8>,
+    },
+    /// Replace the `RawData` of the character `instance_id` in `CharacterSaveParameterMap`
+    SetCharacterRawData {
+        instance_id: Uuid,
+        #[serde(with = "hex_bytes")]
+        raw_data: Vec<u8>,
+    },
+    /// Append a character to `CharacterSaveParameterMap`
+    AppendCharacterSave {
+        player_uid: Uuid,
+        instance_id: Uuid,
+        #[serde(with = "hex_bytes")]
+        raw_data: Vec<u8>,
+    },
+    /// Remove the group `guild_id` from `GroupSaveDataMap`
+    RemoveGuild { guild_id: Uuid },
+    /// Change the key `InstanceId` of the character `instance_id` to `new_instance_id`
+    RekeyCharacter {
+        instance_id: Uuid,
+        new_instance_id: Uuid,
+    },
+    /// Remove the character `instance_id` from `CharacterSaveParameterMap`
+    RemoveCharacterSave { instance_id: Uuid },
+}
+
+impl PatchOp {
+    /// Describes the change for --dry-run, e.g. "Remove guild <id>".
+    pub fn describe(&self) -> String {
+        match self {
+            PatchOp::SetGuildRawData { guild_id, raw_data } => {
+                format!(
+                    "Set RawData of guild {} ({} bytes)",
+                    guild_id,
+                    raw_data.len()
+                )
+            }
+            PatchOp::PatchGuildRawData {
+                guild_id,
+                offset,
+                bytes,
+            } => format!(
+                "Patch {} bytes of RawData of guild {} at offset {}",
+                bytes.len(),
+                guild_id,
+                offset
+            ),
+            PatchOp::SetCharacterRawData {
+                instance_id,
+                raw_data,
+            } => format!(
+                "Set RawData of character {} ({} bytes)",
+                instance_id,
+                raw_data.len()
+            ),
+            PatchOp::AppendCharacterSave {
+                player_uid,
+                instance_id,
+                raw_data,
+            } => format!(
+                "Append character {} of player {} ({} bytes)",
+                instance_id,
+                player_uid,
+                raw_data.len()
+            ),
+            PatchOp::RemoveGuild { guild_id } => format!("Remove guild {}", guild_id),
+            PatchOp::RekeyCharacter {
+                instance_id,
+                new_instance_id,
+            } => format!("Re-key character {} to {}", instance_id, new_instance_id),
+            PatchOp::RemoveCharacterSave { instance_id } => {
+                format!("Remove character {}", instance_id)
+            }
+        }
+    }
+}
+
+/// The changes a command makes to Level.sav, in the order it makes them. Commands collect their
+/// changes here and apply them in one step, which --dry-run turns into printing them. The fix
+/// writes it with `--emit-patch` and `apply-patch` replays it, so finding problems and changing
+/// the save can run in separate processes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Patch {
+    /// [`PATCH_FORMAT_VERSION`] of the tool that wrote the patch
+    #[serde(default = "unversioned")]
+    pub version: u32,
+    pub ops: Vec<PatchOp>,
+}
+
+/// Version of the patch file format. Bump it when older tools would misread a new patch, and
+/// keep reading the older versions in [`read_patch`].
+pub const PATCH_FORMAT_VERSION: u32 = 1;
+
+/// The version of patches written before the format had one
+fn unversioned() -> u32 {
+    1
+}
+
+impl Patch {
+    pub fn new(ops: Vec<PatchOp>) -> Patch {
+        Patch {
+            version: PATCH_FORMAT_VERSION,
+            ops,
+        }
+    }
+}
+
+impl Default for Patch {
+    fn default() -> Self {
+        Patch::new(Vec::new())
+    }
+}
+
+/// Reads a patch written by --emit-patch. The version is checked before the operations, so a
+/// patch of an unknown version fails with that rather than with an unknown operation.
+pub fn read_patch<R: Read>(reader: R) -> anyhow::Result<Patch> {
+    let value: serde_json::Value = serde_json::from_reader(reader)?;
+    let version = match value.get("version") {
+        None => unversioned(),
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .ok_or_else(|| anyhow::anyhow!("Patch version {} is not a number", version))?,
+    };
+    if !(1..=PATCH_FORMAT_VERSION).contains(&version) {
+        return Err(anyhow::anyhow!(
+            "Patch format version {} is not supported, this tool reads versions 1 to {}. Apply it \
+             with the version of the tool that wrote it or a newer one",
+            version,
+            PATCH_FORMAT_VERSION
+        ));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+/// Returns the index of the group `guild_id` in `GroupSaveDataMap`.
+fn guild_entry_index(level_save: &PalSave, guild_id: Uuid) -> anyhow::Result<usize> {
+    get_group_save_data_map(level_save)
+        .iter()
+        .position(|entry| {
+            matches!(
+                entry.key,
+                uesave::PropertyValue::Struct(uesave::StructValue::Guid(id)) if id == guild_id
+            )
+        })
+        .ok_or_else(|| anyhow::anyhow!("Guild {} not found", guild_id))
+}
+
+/// Returns the entry of the group `guild_id` in `GroupSaveDataMap`.
+fn find_guild_entry(level_save: &PalSave, guild_id: Uuid) -> anyhow::Result<&uesave::MapEntry> {
+    let index = guild_entry_index(level_save, guild_id)?;
+    Ok(&get_group_save_data_map(level_save)[index])
+}
+
+/// Like [`find_guild_entry`], for changing the entry.
+fn find_guild_entry_mut(
+    level_save: &mut PalSave,
+    guild_id: Uuid,
+) -> anyhow::Result<&mut uesave::MapEntry> {
+    let index = guild_entry_index(level_save, guild_id)?;
+    Ok(&mut get_group_save_data_map_mut(level_save)[index])
+}
+
+/// Applies the operations of `patch` to `level_save` in order. On error `level_save` may be
+/// partly patched and must not be written.
+pub fn apply_patch(level_save: &mut PalSave, patch: &Patch) -> anyhow::Result<()> {
+    patch.ops.iter().try_for_each(|op| match op {
+        PatchOp::SetGuildRawData { guild_id, raw_data } => {
+            set_raw_data(find_guild_entry_mut(level_save, *guild_id)?, raw_data.clone());
+            Ok(())
+        }
+        PatchOp::PatchGuildRawData {
+            guild_id,
+            offset,
+            bytes,
+        } => patch_guild_raw(find_guild_entry_mut(level_save, *guild_id)?, *offset, bytes),
+        PatchOp::SetCharacterRawData {
+            instance_id,
+            raw_data,
+        } => {
+            let entry = get_character_save_parameter_map_mut(level_save)
+                .iter_mut()
+                .find(|entry| {
+                    matches!(get_character_key_ids(entry), Some((_, id)) if id == *instance_id)
+                })
+                .ok_or_else(|| anyhow::anyhow!("Character {} not found", instance_id))?;
+            set_raw_data(entry, raw_data.clone());
+            Ok(())
+        }
+        PatchOp::AppendCharacterSave {
+            player_uid,
+            instance_id,
+            raw_data,
+        } => {
+            let character_save_parameter_map = get_character_save_parameter_map_mut(level_save);
+            if character_save_parameter_map.iter().any(
+                |entry| matches!(get_character_key_ids(entry), Some((_, id)) if id == *instance_id),
+            ) {
+                return Err(anyhow::anyhow!(
+                    "Character {} already exists, was the patch applied before?",
+                    instance_id
+                ));
+            }
+            let mut entry = new_character_save_entry_raw(*player_uid, *instance_id, raw_data.clone());
+            match_character_key_shape(&mut entry, character_save_parameter_map);
+            character_save_parameter_map.push(entry);
+            Ok(())
+        }
+        PatchOp::RemoveGuild { guild_id } => {
+            let index = guild_entry_index(level_save, *guild_id)?;
+            get_group_save_data_map_mut(level_save).remove(index);
+            Ok(())
+        }
+        PatchOp::RekeyCharacter {
+            instance_id,
+            new_instance_id,
+        } => {
+            let character_save_parameter_map = get_character_save_parameter_map_mut(level_save);
+            if character_save_parameter_map.iter().any(
+                |entry| matches!(get_character_key_ids(entry), Some((_, id)) if id == *new_instance_id),
+            ) {
+                return Err(anyhow::anyhow!(
+                    "Character {} already exists",
+                    new_instance_id
+                ));
+            }
+            let entry = character_save_parameter_map
+                .iter_mut()
+                .find(|entry| {
+                    matches!(get_character_key_ids(entry), Some((_, id)) if id == *instance_id)
+                })
+                .ok_or_else(|| anyhow::anyhow!("Character {} not found", instance_id))?;
+            set_character_instance_id(entry, *new_instance_id)
+        }
+        PatchOp::RemoveCharacterSave { instance_id } => {
+            let character_save_parameter_map = get_character_save_parameter_map_mut(level_save);
+            let index = character_save_parameter_map
+                .iter()
+                .position(|entry| {
+                    matches!(get_character_key_ids(entry), Some((_, id)) if id == *instance_id)
+                })
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Character {} not found, was the patch applied before?",
+                        instance_id
+                    )
+                })?;
+            character_save_parameter_map.remove(index);
+            Ok(())
+        }
+    })
+}
+
+/// The patch that takes `level_save` back from `patch` to what it is now, for --undo-log.
+/// `level_save` must not be patched yet. Appended characters are removed by their InstanceId,
+/// so undoing keeps characters added to the save since. Changed `RawData` is restored whole, so
+/// later changes to the same guild or character are lost. Removing a guild or a character
+/// cannot be undone.
+pub fn inverse_patch(level_save: &PalSave, patch: &Patch) -> anyhow::Result<Patch> {
+    // Current InstanceId -> InstanceId in `level_save`, or None for characters the patch appends
+    let mut renamed: HashMap<Uuid, Option<Uuid>> = HashMap::new();
+    let original_character = move |instance_id: Uuid| {
+        iter_character_entries(level_save)
+            .filter_map(|entry| entry.ok())
+            .find(|(_, id, _)| *id == instance_id)
+            .ok_or_else(|| anyhow::anyhow!("Character {} not found", instance_id))
+    };
+    let original_guild_raw_data =
+        move |guild_id: Uuid| get_raw_data(find_guild_entry(level_save, guild_id)?);
+    let mut ops = patch
+        .ops
+        .iter()
+        .map(|op| -> anyhow::Result<Option<PatchOp>> {
+            match op {
+                PatchOp::SetGuildRawData { guild_id, .. } => Ok(Some(PatchOp::SetGuildRawData {
+                    guild_id: *guild_id,
+                    raw_data: original_guild_raw_data(*guild_id)?.into_owned(),
+                })),
+                PatchOp::PatchGuildRawData {
+                    guild_id,
+                    offset,
+                    bytes,
+                } => {
+                    let raw_data = original_guild_raw_data(*guild_id)?;
+                    let original_bytes = offset
+                        .checked_add(bytes.len())
+                        .and_then(|end| raw_data.get(*offset..end))
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "Offset {} is outside the RawData of guild {}",
+                                offset,
+                                guild_id
+                            )
+                        })?;
+                    Ok(Some(PatchOp::PatchGuildRawData {
+                        guild_id: *guild_id,
+                        offset: *offset,
+                        bytes: original_bytes.to_vec(),
+                    }))
+                }
+                PatchOp::SetCharacterRawData { instance_id, .. } => {
+                    match renamed
+                        .get(instance_id)
+                        .copied()
+                        .unwrap_or(Some(*instance_id))
+                    {
+                        // Removing the appended character undoes this too
+                        None => Ok(None),
+                        Some(original_id) => {
+                            let (_, _, entry) = original_character(original_id)?;
+                            Ok(Some(PatchOp::SetCharacterRawData {
+                                instance_id: *instance_id,
+                                raw_data: get_raw_data(entry)?.into_owned(),
+                            }))
+                        }
+                    }
+                }
+                PatchOp::AppendCharacterSave { instance_id, .. } => {
+                    renamed.insert(*instance_id, None);
+                    Ok(Some(PatchOp::RemoveCharacterSave {
+                        instance_id: *instance_id,
+                    }))
+                }
+                PatchOp::RemoveGuild { guild_id } => Err(anyhow::anyhow!(
+                    "Removing guild {} cannot be undone, restore a backup instead",
+                    guild_id
+                )),
+                PatchOp::RekeyCharacter {
+                    instance_id,
+                    new_instance_id,
+                } => {
+                    let original_id = renamed.remove(instance_id).unwrap_or(Some(*instance_id));
+                    renamed.insert(*new_instance_id, original_id);
+                    Ok(Some(PatchOp::RekeyCharacter {
+                        instance_id: *new_instance_id,
+                        new_instance_id: *instance_id,
+                    }))
+                }
+                PatchOp::RemoveCharacterSave { instance_id } => Err(anyhow::anyhow!(
+                    "Removing character {} cannot be undone, restore a backup instead",
+                    instance_id
+                )),
+            }
+        })
+        .filter_map(Result::transpose)
+        .collect::<anyhow::Result<Vec<PatchOp>>>()?;
+    ops.reverse();
+    Ok(Patch::new(ops))
+}
+
+mod hex_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(
+            &bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>(),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if !hex.is_ascii() || hex.len() % 2 != 0 {
+            return Err(D::Error::custom("RawData is not a hex string"));
+        }
+        hex.as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                // ASCII, checked above
+                let pair = std::str::from_utf8(pair).unwrap();
+                u8::from_str_radix(pair, 16).map_err(D::Error::custom)
+            })
+            .collect()
+    }
+}
+
+#[test]
+pub fn test_patch_round_trip() {
+    let patch = Patch::new(vec![
+        PatchOp::SetGuildRawData {
+            guild_id: Uuid::from_u128(0x100),
+            raw_data: vec![0x00, 0x7f, 0xff],
+        },
+        PatchOp::AppendCharacterSave {
+            player_uid: Uuid::from_u128(1),
+            instance_id: Uuid::from_u128(2),
+            raw_data: Vec::new(),
+        },
+        PatchOp::RemoveGuild {
+            guild_id: Uuid::from_u128(0x200),
+        },
+    ]);
+    let json = serde_json::to_string(&patch).unwrap();
+    assert!(json.starts_with(r#"{"version":1,"#));
+    assert!(json.contains(r#""op":"set_guild_raw_data""#));
+    assert!(json.contains(r#""raw_data":"007fff""#));
+    assert!(json.contains(r#""op":"remove_guild""#));
+    assert_eq!(serde_json::from_str::<Patch>(&json).unwrap(), patch);
+
+    let json = json.replace("007fff", "007ff");
+    assert!(serde_json::from_str::<Patch>(&json).is_err());
+}
+
+#[test]
+pub fn test_append_character_save_matches_existing_keys() {
+    use crate::{
+        fixtures,
+        sav::{get_character_save_parameter_map, get_raw_data},
+    };
+
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(1), Uuid::from_u128(0x11));
+    // As if the game wrote its key Guids with struct ids of its own
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref mut key)) =
+        get_character_save_parameter_map_mut(&mut level_save)[0].key
+    else {
+        panic!()
+    };
+    key.values_mut().enumerate().for_each(|(index, property)| {
+        if let uesave::Property::Struct { struct_id, .. } = property {
+            *struct_id = Uuid::from_u128(0x5700 + index as u128);
+        }
+    });
+    let raw_data = get_raw_data(&get_character_save_parameter_map(&level_save)[0])
+        .unwrap()
+        .into_owned();
+
+    let patch = Patch::new(vec![PatchOp::AppendCharacterSave {
+        player_uid: Uuid::from_u128(2),
+        instance_id: Uuid::from_u128(0x12),
+        raw_data,
+    }]);
+    apply_patch(&mut level_save, &patch).unwrap();
+
+    // (name, struct_type, struct_id) of each struct property of a key
+    let key_shape = |entry: &uesave::MapEntry| -> Vec<(String, String, Uuid)> {
+        let uesave::PropertyValue::Struct(uesave::StructValue::Struct(key)) = &entry.key else {
+            panic!()
+        };
+        key.iter()
+            .filter_map(|(name, property)| match property {
+                uesave::Property::Struct {
+                    struct_type,
+                    struct_id,
+                    ..
+                } => Some((name.clone(), format!("{:?}", struct_type), *struct_id)),
+                _ => None,
+            })
+            .collect()
+    };
+    let map = get_character_save_parameter_map(&level_save);
+    assert_eq!(key_shape(&map[1]), key_shape(&map[0]));
+    assert_eq!(key_shape(&map[1])[0].2, Uuid::from_u128(0x5700));
+    assert_eq!(
+        get_character_key_ids(&map[1]),
+        Some((Uuid::from_u128(2), Uuid::from_u128(0x12)))
+    );
+}
+
+#[test]
+pub fn test_read_patch_checks_version() {
+    let patch = Patch::new(vec![PatchOp::SetCharacterRawData {
+        instance_id: Uuid::from_u128(2),
+        raw_data: vec![0x01],
+    }]);
+    let json = serde_json::to_string(&patch).unwrap();
+    assert_eq!(read_patch(json.as_bytes()).unwrap(), patch);
+
+    // Written before patches had a version
+    let unversioned_json = json.replace(r#""version":1,"#, "");
+    assert_eq!(read_patch(unversioned_json.as_bytes()).unwrap(), patch);
+
+    // From a future tool, with an operation this one does not know
+    let future_json = json
+        .replace(r#""version":1"#, r#""version":2"#)
+        .replace("set_character_raw_data", "set_base_camp_raw_data");
+    let message = format!("{:#}", read_patch(future_json.as_bytes()).unwrap_err());
+    assert!(
+        message
+            .contains("Patch format version 2 is not supported, this tool reads versions 1 to 1"),
+        "{}",
+        message
+    );
+
+    let zero_json = json.replace(r#""version":1"#, r#""version":0"#);
+    assert!(read_patch(zero_json.as_bytes()).is_err());
+}
+
+#[test]
+pub fn test_inverse_patch_undoes_patch() {
+    use crate::{
+        fixtures,
+        sav::{get_character_save_parameter_map, get_raw_data},
+    };
+
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_guild(
+        &mut level_save,
+        Uuid::from_u128(0x100),
+        &fixtures::read_guild(),
+    );
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(1), Uuid::from_u128(0x11));
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(2), Uuid::from_u128(0x12));
+    let raw_data = get_raw_data(&get_character_save_parameter_map(&level_save)[1])
+        .unwrap()
+        .into_owned();
+    let original = level_save.clone();
+
+    let patch = Patch::new(vec![
+        PatchOp::PatchGuildRawData {
+            guild_id: Uuid::from_u128(0x100),
+            offset: 4,
+            bytes: vec![0xff, 0xff],
+        },
+        PatchOp::SetGuildRawData {
+            guild_id: Uuid::from_u128(0x100),
+            raw_data: vec![0x01],
+        },
+        PatchOp::AppendCharacterSave {
+            player_uid: Uuid::from_u128(3),
+            instance_id: Uuid::from_u128(0x13),
+            raw_data: raw_data.clone(),
+        },
+        // Already undone by removing the appended character
+        PatchOp::SetCharacterRawData {
+            instance_id: Uuid::from_u128(0x13),
+            raw_data: vec![0x02],
+        },
+        PatchOp::RekeyCharacter {
+            instance_id: Uuid::from_u128(0x11),
+            new_instance_id: Uuid::from_u128(0x21),
+        },
+        PatchOp::SetCharacterRawData {
+            instance_id: Uuid::from_u128(0x21),
+            raw_data: vec![0x03],
+        },
+    ]);
+    let inverse = inverse_patch(&level_save, &patch).unwrap();
+    assert_eq!(inverse.ops.len(), 5);
+    assert_eq!(
+        inverse.ops[2],
+        PatchOp::RemoveCharacterSave {
+            instance_id: Uuid::from_u128(0x13)
+        }
+    );
+    apply_patch(&mut level_save, &patch).unwrap();
+    assert_ne!(level_save, original);
+    apply_patch(&mut level_save, &inverse).unwrap();
+    assert_eq!(level_save, original);
+
+    // The removed entries are not in the patch
+    let remove_guild = Patch::new(vec![PatchOp::RemoveGuild {
+        guild_id: Uuid::from_u128(0x100),
+    }]);
+    assert!(inverse_patch(&level_save, &remove_guild).is_err());
+    let remove_character = Patch::new(vec![PatchOp::RemoveCharacterSave {
+        instance_id: Uuid::from_u128(0x12),
+    }]);
+    assert!(inverse_patch(&level_save, &remove_character).is_err());
+}
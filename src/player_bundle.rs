@@ -0,0 +1,186 @@
+use std::{
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use uuid::Uuid;
+
+use crate::{
+    group_guild::GameVersion,
+    sav::{
+        get_character_key_ids, get_character_save_parameter_map,
+        get_character_save_parameter_map_mut, get_group_save_data_map_mut, get_raw_data,
+        get_world_save_data_mut, is_group_type_guild, parse_raw_group_guild_save, write_save_file,
+        PalSave,
+    },
+    save_directory::{ensure_empty_output_dir, list_player_sav_paths},
+};
+
+/// Files written by [`extract_player_bundle`].
+#[derive(Debug)]
+pub struct PlayerBundle {
+    /// Copy of the player save, at `Players/<PlayerUId>.sav`
+    pub player_sav_path: PathBuf,
+    /// `Level.sav` with only the player's guild and characters
+    pub level_sav_path: PathBuf,
+    /// `RawData` of the player's guild, in the format of `assets/guild_0.bin`
+    pub guild_path: PathBuf,
+    /// `RawData` of each character of the player, in the format of
+    /// `assets/character_save_parameter.bin`
+    pub character_paths: Vec<PathBuf>,
+}
+
+/// Extracts one player's save, guild and characters from the save in `input` into `output`,
+/// so that an issue can be reproduced without sharing the whole server. `output` is a save
+/// directory itself, which the read-only commands can load.
+pub fn extract_player_bundle(
+    input: &Path,
+    mut level_save: PalSave,
+    game_version: GameVersion,
+    player_uid: Uuid,
+    output: &Path,
+    force: bool,
+) -> anyhow::Result<PlayerBundle> {
+    let player_sav_name = player_uid.simple().to_string();
+    let player_sav_source = list_player_sav_paths(&input.join("Players"), false)?
+        .into_iter()
+        .find(|path| {
+            path.file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(|stem| stem.eq_ignore_ascii_case(&player_sav_name))
+                .unwrap_or(false)
+        })
+        .with_context(|| format!("Player {} has no player save", player_uid))?;
+
+    // Keep only the guild of the player
+    let group_save_data_map = get_group_save_data_map_mut(&mut level_save);
+    let guild_index = group_save_data_map
+        .iter()
+        .position(|entry| {
+            is_group_type_guild(entry)
+                && parse_raw_group_guild_save(entry, game_version)
+                    .map(|group| {
+                        group
+                            .GuildPlayerInfo
+                            .iter()
+                            .any(|player_info| player_info.PlayerUId == player_uid)
+                    })
+                    .unwrap_or(false)
+        })
+        .with_context(|| format!("Player {} is not a member of any guild", player_uid))?;
+    let guild_entry = group_save_data_map.swap_remove(guild_index);
+    group_save_data_map.clear();
+    group_save_data_map.push(guild_entry);
+    let guild_raw_data = get_raw_data(&group_save_data_map[0])?.into_owned();
+
+    // Keep only the characters of the player
+    let has_character_save_parameter_map =
+        get_world_save_data_mut(&mut level_save).contains_key("CharacterSaveParameterMap");
+    let character_raw_data: Vec<Vec<u8>> = if has_character_save_parameter_map {
+        get_character_save_parameter_map_mut(&mut level_save).retain(|entry| {
+            get_character_key_ids(entry)
+                .map(|(uid, _)| uid == player_uid)
+                .unwrap_or(false)
+        });
+        get_character_save_parameter_map(&level_save)
+            .iter()
+            .map(|entry| get_raw_data(entry).map(|data| data.into_owned()))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    } else {
+        Vec::new()
+    };
+    get_world_save_data_mut(&mut level_save)
+        .retain(|name, _| name == "GroupSaveDataMap" || name == "CharacterSaveParameterMap");
+
+    ensure_empty_output_dir(output, force)?;
+    std::fs::create_dir_all(output.join("Players"))
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+    let player_sav_path = output
+        .join("Players")
+        .join(player_sav_source.file_name().unwrap());
+    std::fs::copy(&player_sav_source, &player_sav_path)
+        .with_context(|| format!("Failed to copy {}", player_sav_source.display()))?;
+
+    let level_sav_path = output.join("Level.sav");
+    let mut writer = BufWriter::new(std::fs::File::create(&level_sav_path)?);
+    write_save_file(&mut writer, &level_save)?;
+    drop(writer);
+
+    let guild_path = output.join("guild.bin");
+    std::fs::write(&guild_path, guild_raw_data)?;
+    let character_paths = character_raw_data
+        .iter()
+        .enumerate()
+        .map(|(i, data)| -> anyhow::Result<PathBuf> {
+            let path = output.join(format!("character_save_parameter_{}.bin", i));
+            std::fs::write(&path, data)?;
+            Ok(path)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if character_paths.is_empty() {
+        info!("Player {} has no character save", player_uid);
+    }
+
+    Ok(PlayerBundle {
+        player_sav_path,
+        level_sav_path,
+        guild_path,
+        character_paths,
+    })
+}
+
+#[test]
+pub fn test_extract_player_bundle() {
+    use crate::{
+        fixtures,
+        sav::{get_guilds, read_save_file_path},
+    };
+
+    let input = fixtures::temp_dir("player_bundle_input");
+    let output = fixtures::temp_dir("player_bundle_output");
+    let broken_players = fixtures::write_broken_save_directory(&input, 2);
+    let (player_uid, instance_id) = broken_players[0];
+    // Give the player a character save, next to one of another player
+    let mut level_save = read_save_file_path(&input.join("Level.sav")).unwrap();
+    fixtures::add_character_save(&mut level_save, player_uid, instance_id);
+    fixtures::add_character_save(&mut level_save, broken_players[1].0, broken_players[1].1);
+    fixtures::write_save(&input.join("Level.sav"), &level_save);
+
+    let bundle = extract_player_bundle(
+        &input,
+        level_save,
+        GameVersion::V0_1_3,
+        player_uid,
+        &output,
+        false,
+    )
+    .unwrap();
+
+    assert!(bundle.player_sav_path.is_file());
+    assert_eq!(
+        bundle
+            .player_sav_path
+            .file_name()
+            .unwrap()
+            .to_str()
+            .unwrap(),
+        fixtures::player_sav_name(&player_uid)
+    );
+    assert_eq!(
+        std::fs::read(&bundle.guild_path).unwrap(),
+        std::fs::read("assets/guild_0.bin").unwrap()
+    );
+    assert_eq!(bundle.character_paths.len(), 1);
+    assert!(bundle.character_paths[0].is_file());
+
+    let level_save = read_save_file_path(&bundle.level_sav_path).unwrap();
+    assert_eq!(
+        get_guilds(&level_save, GameVersion::V0_1_3).unwrap().len(),
+        1
+    );
+    assert_eq!(
+        fixtures::character_instance_ids(&level_save),
+        vec![instance_id]
+    );
+}
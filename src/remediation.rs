@@ -0,0 +1,135 @@
+use std::path::Path;
+
+use crate::fix_report::FixReport;
+
+const TOOL: &str = "palworld-guild-save-fix";
+
+/// Renders the problems of `report` that the fix leaves alone as a Markdown checklist, with the
+/// commands an admin can run on `save_dir` to look into or resolve each of them.
+pub fn render_remediation(report: &FixReport, save_dir: &Path) -> String {
+    let save_dir = save_dir.display();
+    let guildless_players = report.guildless_player_uids.iter().map(|player_uid| {
+        format!(
+            "Player {}: have them join or create a guild in game, then run `{} {}` again",
+            player_uid, TOOL, save_dir
+        )
+    });
+    let instance_id_collisions =
+        report
+            .instance_id_collisions
+            .iter()
+            .map(|(player_uid, instance_id, owner_uid)| {
+                let owner = if owner_uid.is_nil() {
+                    "a pal".to_string()
+                } else {
+                    format!(
+                        "player {} (`{} extract-player-bundle {} {} owner-{}`)",
+                        owner_uid, TOOL, save_dir, owner_uid, owner_uid
+                    )
+                };
+                format!(
+                    "Player {} has id {} of {}: after verifying your backup, compare with \
+                     `{} extract-player-bundle {} {} player-{}` and fix the character by hand",
+                    player_uid, instance_id, owner, TOOL, save_dir, player_uid, player_uid
+                )
+            });
+    let unparsed_guilds = report.unparsed_guild_ids.iter().map(|guild_id| {
+        format!(
+            "Guild {}: retry with `--game-version <TAG>`, or report it with the output of \
+             `{} header-info {}/Level.sav`",
+            guild_id, TOOL, save_dir
+        )
+    });
+    let timed_out_player_savs = report.timed_out_player_savs.iter().map(|name| {
+        format!(
+            "{}: run the fix again with a larger `--parse-timeout`, or check \
+             `{} header-info {}/Players/{}`",
+            name, TOOL, save_dir, name
+        )
+    });
+    let duplicate_instance_id_savs =
+        report
+            .duplicate_instance_id_savs
+            .iter()
+            .map(|(name, player_uid, instance_id)| {
+                format!(
+                    "{}: player {} shares id {} with another player save; remove the copied \
+                     save from Players, then run `{} {}` again",
+                    name, player_uid, instance_id, TOOL, save_dir
+                )
+            });
+    let empty_guilds = report.empty_guild_ids.iter().map(|guild_id| {
+        format!(
+            "Guild {}: has no members; disband it in game if it is not needed",
+            guild_id
+        )
+    });
+    let sections: Vec<(&str, Vec<String>)> = vec![
+        ("Players without a guild", guildless_players.collect()),
+        ("InstanceId collisions", instance_id_collisions.collect()),
+        ("Guilds that could not be parsed", unparsed_guilds.collect()),
+        (
+            "Player saves that timed out",
+            timed_out_player_savs.collect(),
+        ),
+        (
+            "Player saves sharing an InstanceId",
+            duplicate_instance_id_savs.collect(),
+        ),
+        ("Empty guilds", empty_guilds.collect()),
+    ];
+
+    let mut remediation = String::from("# Manual steps\n");
+    sections
+        .iter()
+        .filter(|(_, steps)| !steps.is_empty())
+        .for_each(|(title, steps)| {
+            remediation.push_str(&format!("\n## {}\n\n", title));
+            steps.iter().for_each(|step| {
+                remediation.push_str(&format!("- [ ] {}\n", step));
+            });
+        });
+    if sections.iter().all(|(_, steps)| steps.is_empty()) {
+        remediation.push_str("\nNothing needs manual attention.\n");
+    }
+    remediation
+}
+
+#[test]
+pub fn test_render_remediation() {
+    use uuid::Uuid;
+
+    let save_dir = Path::new("save");
+    let report = FixReport::default();
+    assert!(render_remediation(&report, save_dir).contains("Nothing needs manual attention"));
+
+    let report = FixReport {
+        guildless_player_uids: vec![Uuid::from_u128(1)],
+        instance_id_collisions: vec![
+            (Uuid::from_u128(2), Uuid::from_u128(3), Uuid::from_u128(4)),
+            (Uuid::from_u128(5), Uuid::from_u128(6), Uuid::nil()),
+        ],
+        unparsed_guild_ids: vec![Uuid::from_u128(0x100)],
+        timed_out_player_savs: vec!["slow.sav".into()],
+        empty_guild_ids: vec![Uuid::from_u128(0x200)],
+        ..Default::default()
+    };
+    let remediation = render_remediation(&report, save_dir);
+    [
+        "## Players without a guild",
+        "## InstanceId collisions",
+        "## Guilds that could not be parsed",
+        "## Player saves that timed out",
+        "## Empty guilds",
+    ]
+    .iter()
+    .for_each(|title| assert!(remediation.contains(title), "{}", title));
+    assert_eq!(remediation.matches("- [ ] ").count(), 6);
+    assert!(remediation.contains(&format!(
+        "extract-player-bundle save {} player-{}",
+        Uuid::from_u128(2),
+        Uuid::from_u128(2)
+    )));
+    assert!(remediation.contains("header-info save/Level.sav"));
+    assert!(!remediation.contains("Nothing needs manual attention"));
+}
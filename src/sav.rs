@@ -1,145 +1,299 @@
 use std::{
-    io::{BufReader, Cursor, Read, Write},
+    borrow::Cow,
+    collections::HashMap,
+    io::{BufReader, BufWriter, Cursor, Read, Write},
+    path::{Path, PathBuf},
     sync::Arc,
 };
 
+use anyhow::Context;
 use byteorder::{LittleEndian, ReadBytesExt};
 use indexmap::IndexMap;
+use serde::Serialize;
 use uesave::Save;
-use winnow::Parser;
+use uuid::Uuid;
 
 use crate::{
-    character_save_parameter::{read_raw_character_save_parameter, CharacterSaveParameter},
-    group_guild::{read_group_guild_save, stream, GroupGuildSave},
+    character_save_parameter::{
+        read_raw_character_save_parameter, write_raw_character_save_parameter,
+        CharacterSaveParameter,
+    },
+    group_guild::{
+        read_group_guild_save_consumed, write_group_guild_save, GameVersion, GroupGuildSave,
+    },
+    hex_dump::hex_dump,
 };
 
 pub(crate) static SAVE_TYPES: once_cell::sync::Lazy<Arc<uesave::Types>> =
-    once_cell::sync::Lazy::new(|| {
-        let mut types = uesave::Types::new();
-        types.add(
-            ".worldSaveData.CharacterSaveParameterMap.Key".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.FoliageGridSaveDataMap.Key".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.FoliageGridSaveDataMap.ModelMap.InstanceDataMap.Key".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.MapObjectSpawnerInStageSaveData.Key".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.ItemContainerSaveData.Key".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.CharacterContainerSaveData.Key".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.CharacterContainerSaveData.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.GroupSaveDataMap.Key".into(),
-            uesave::StructType::Guid,
-        );
-        types.add(
-            ".worldSaveData.GroupSaveDataMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.WorkSaveData.WorkAssignMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-        ".worldSaveData.MapObjectSpawnerInStageSaveData.SpawnerDataMapByLevelObjectInstanceId.ItemMap.Value".into(),
+    once_cell::sync::Lazy::new(|| base_save_types().into());
+
+/// Struct types of map keys and values, which the save format does not record.
+pub const BASE_SAVE_TYPES: &[(&str, uesave::StructType)] = &[
+    (
+        ".worldSaveData.CharacterSaveParameterMap.Key",
         uesave::StructType::Struct(None),
-    );
-        types.add(
-            ".worldSaveData.DungeonSaveData.MapObjectSaveData.Model.EffectMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.DungeonSaveData.MapObjectSaveData.ConcreteModel.ModuleMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.FoliageGridSaveDataMap.ModelMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.FoliageGridSaveDataMap.ModelMap.InstanceDataMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.MapObjectSaveData.Model.EffectMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.MapObjectSaveData.ConcreteModel.ModuleMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.CharacterSaveParameterMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.FoliageGridSaveDataMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.MapObjectSpawnerInStageSaveData.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-        ".worldSaveData.MapObjectSpawnerInStageSaveData.SpawnerDataMapByLevelObjectInstanceId.Key"
-            .into(),
+    ),
+    (
+        ".worldSaveData.FoliageGridSaveDataMap.Key",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.FoliageGridSaveDataMap.ModelMap.InstanceDataMap.Key",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.MapObjectSpawnerInStageSaveData.Key",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.ItemContainerSaveData.Key",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.CharacterContainerSaveData.Key",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.CharacterContainerSaveData.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.GroupSaveDataMap.Key",
         uesave::StructType::Guid,
-    );
-        types.add(
-        ".worldSaveData.MapObjectSpawnerInStageSaveData.SpawnerDataMapByLevelObjectInstanceId.Value".into(),
+    ),
+    (
+        ".worldSaveData.GroupSaveDataMap.Value",
         uesave::StructType::Struct(None),
-    );
-        types.add(
-            ".worldSaveData.BaseCampSaveData.Key".into(),
-            uesave::StructType::Guid,
-        );
-        types.add(
-            ".worldSaveData.BaseCampSaveData.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.BaseCampSaveData.ModuleMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.ItemContainerSaveData.Value".into(),
-            uesave::StructType::Struct(None),
-        );
-        types.add(
-            ".worldSaveData.EnemyCampSaveData.EnemyCampStatusMap.Value".into(),
-            uesave::StructType::Struct(None),
-        );
+    ),
+    (
+        ".worldSaveData.WorkSaveData.WorkAssignMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.MapObjectSpawnerInStageSaveData.SpawnerDataMapByLevelObjectInstanceId.ItemMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.DungeonSaveData.MapObjectSaveData.Model.EffectMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.DungeonSaveData.MapObjectSaveData.ConcreteModel.ModuleMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.FoliageGridSaveDataMap.ModelMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.FoliageGridSaveDataMap.ModelMap.InstanceDataMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.MapObjectSaveData.Model.EffectMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.MapObjectSaveData.ConcreteModel.ModuleMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.CharacterSaveParameterMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.FoliageGridSaveDataMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.MapObjectSpawnerInStageSaveData.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.MapObjectSpawnerInStageSaveData.SpawnerDataMapByLevelObjectInstanceId.Key",
+        uesave::StructType::Guid,
+    ),
+    (
+        ".worldSaveData.MapObjectSpawnerInStageSaveData.SpawnerDataMapByLevelObjectInstanceId.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.BaseCampSaveData.Key",
+        uesave::StructType::Guid,
+    ),
+    (
+        ".worldSaveData.BaseCampSaveData.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.BaseCampSaveData.ModuleMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.ItemContainerSaveData.Value",
+        uesave::StructType::Struct(None),
+    ),
+    (
+        ".worldSaveData.EnemyCampSaveData.EnemyCampStatusMap.Value",
+        uesave::StructType::Struct(None),
+    ),
+];
+
+fn base_save_types() -> uesave::Types {
+    let mut types = uesave::Types::new();
+    BASE_SAVE_TYPES.iter().for_each(|(path, struct_type)| {
+        types.add(path.to_string(), struct_type.clone());
+    });
+    types
+}
 
-        types.into()
+/// Returns the base types plus `extra_types`. An extra entry replaces a base entry with the
+/// same path, so a new game version can be read without a rebuild.
+pub fn save_types(extra_types: &[(String, uesave::StructType)]) -> uesave::Types {
+    let mut types = base_save_types();
+    extra_types.iter().for_each(|(path, struct_type)| {
+        types.add(path.clone(), struct_type.clone());
     });
+    types
+}
+
+/// Parses a `<path>=<struct>` type registration, e.g. `.worldSaveData.GroupSaveDataMap.Key=Guid`.
+pub fn parse_extra_type(s: &str) -> Result<(String, uesave::StructType), String> {
+    let (path, struct_type) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected <path>=<struct>, got {}", s))?;
+    let struct_type = match struct_type {
+        "Guid" => uesave::StructType::Guid,
+        "Struct" => uesave::StructType::Struct(None),
+        _ => {
+            return Err(format!(
+                "unsupported struct type {} (expected Guid or Struct)",
+                struct_type
+            ))
+        }
+    };
+    Ok((path.to_string(), struct_type))
+}
+
+/// How the GVAS data of a .sav file is compressed, stored in the byte after the `PlZ` magic.
+/// The bytes are the ASCII digits `'0'`, `'1'` and `'2'` (0x30, 0x31 and 0x32), not 0, 1 and 2,
+/// so messages name the type and show the byte, e.g. `DoubleZlib (0x32)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompressionType {
+    /// 0x30 (`'0'`): not compressed, for inspecting the file with other tools
+    None,
+    /// 0x31 (`'1'`): zlib
+    Zlib,
+    /// 0x32 (`'2'`): zlib twice, what the game writes
+    DoubleZlib,
+}
+
+impl CompressionType {
+    pub const ALL: [CompressionType; 3] = [
+        CompressionType::None,
+        CompressionType::Zlib,
+        CompressionType::DoubleZlib,
+    ];
+
+    /// `None` for a byte that is not a known compression type.
+    pub fn from_byte(byte: u8) -> Option<CompressionType> {
+        CompressionType::ALL
+            .into_iter()
+            .find(|compression_type| compression_type.byte() == byte)
+    }
+
+    pub fn byte(self) -> u8 {
+        match self {
+            CompressionType::None => b'0',
+            CompressionType::Zlib => b'1',
+            CompressionType::DoubleZlib => b'2',
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CompressionType::None => "None",
+            CompressionType::Zlib => "Zlib",
+            CompressionType::DoubleZlib => "DoubleZlib",
+        }
+    }
+}
+
+impl std::fmt::Display for CompressionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:#x})", self.name(), self.byte())
+    }
+}
+
+/// Names the compression type byte of a .sav prefix, which may not be a known one, e.g.
+/// `DoubleZlib (0x32)` or `unknown (0x02)`.
+pub fn describe_compression_byte(byte: u8) -> String {
+    match CompressionType::from_byte(byte) {
+        Some(compression_type) => compression_type.to_string(),
+        None => format!("unknown ({:#04x})", byte),
+    }
+}
+
+/// Parses a compression type given as its byte (`0x30`, `0x31` or `0x32`) or its name (`None`,
+/// `Zlib` or `DoubleZlib`, in any case).
+pub fn parse_compression_type(s: &str) -> Result<CompressionType, String> {
+    if let Some(compression_type) = CompressionType::ALL
+        .into_iter()
+        .find(|compression_type| compression_type.name().eq_ignore_ascii_case(s))
+    {
+        return Ok(compression_type);
+    }
+    let byte = u8::from_str_radix(s.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("invalid compression type {}", s))?;
+    CompressionType::from_byte(byte).ok_or_else(|| {
+        format!(
+            "unsupported compression type {} (expected 0x30 (None), 0x31 (Zlib) or 0x32 (DoubleZlib))",
+            s
+        )
+    })
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct PalSave {
-    pub compression_type: u8,
+    pub compression_type: CompressionType,
     pub save: Save,
 }
 
 pub fn read_save_file<R: Read>(reader: R) -> anyhow::Result<PalSave> {
-    let mut reader = BufReader::new(reader);
+    read_save_file_with_types(reader, &SAVE_TYPES)
+}
+
+pub fn read_save_file_with_extra_types<R: Read>(
+    reader: R,
+    extra_types: &[(String, uesave::StructType)],
+) -> anyhow::Result<PalSave> {
+    if extra_types.is_empty() {
+        return read_save_file(reader);
+    }
+    read_save_file_with_types(reader, &save_types(extra_types))
+}
+
+/// The fields in front of the GVAS data of a .sav file.
+/// Some tools write both lengths as 0, so they are informational only: reading never relies on
+/// them, and writing always stores the real lengths.
+#[derive(Debug, Serialize)]
+pub struct SavPrefix {
+    pub decompressed_length: u32,
+    pub compressed_length: u32,
+    /// The byte as written, which may not be a [`CompressionType`]
+    pub compression_type: u8,
+}
+
+impl SavPrefix {
+    /// Returns `false` when the writing tool left the length fields zeroed.
+    pub fn has_lengths(&self) -> bool {
+        self.decompressed_length != 0 || self.compressed_length != 0
+    }
+}
 
-    let _decompresed_length = reader.read_u32::<LittleEndian>()?;
-    let _compressed_length = reader.read_u32::<LittleEndian>()?;
+pub fn read_sav_prefix<R: Read>(reader: &mut R) -> anyhow::Result<SavPrefix> {
+    let decompressed_length = reader.read_u32::<LittleEndian>()?;
+    let compressed_length = reader.read_u32::<LittleEndian>()?;
 
     const PLZ_MAGIC: [u8; 3] = [b'P', b'l', b'Z'];
     let mut magic = [0u8; 3];
@@ -149,46 +303,164 @@ pub fn read_save_file<R: Read>(reader: R) -> anyhow::Result<PalSave> {
     }
 
     let compression_type = reader.read_u8()?;
-    let save = match compression_type {
-        0x30 => Save::read_with_types(&mut reader, &SAVE_TYPES)?,
-        0x31 => {
-            let mut reader = flate2::bufread::ZlibDecoder::new(reader);
-            Save::read_with_types(&mut reader, &SAVE_TYPES)?
+    Ok(SavPrefix {
+        decompressed_length,
+        compressed_length,
+        compression_type,
+    })
+}
+
+fn decompress(data: &[u8], compression_type: CompressionType) -> anyhow::Result<Vec<u8>> {
+    let mut decompressed = Vec::new();
+    match compression_type {
+        CompressionType::None => decompressed.extend_from_slice(data),
+        CompressionType::Zlib => {
+            flate2::read::ZlibDecoder::new(data).read_to_end(&mut decompressed)?;
         }
-        0x32 => {
-            let mut reader =
-                flate2::read::ZlibDecoder::new(flate2::bufread::ZlibDecoder::new(reader));
-            Save::read_with_types(&mut reader, &SAVE_TYPES)?
+        CompressionType::DoubleZlib => {
+            flate2::read::ZlibDecoder::new(flate2::read::ZlibDecoder::new(data))
+                .read_to_end(&mut decompressed)?;
         }
-        _ => return Err(anyhow::anyhow!("Invalid compression method")),
-    };
-    Ok(PalSave {
-        compression_type,
-        save,
+    }
+    Ok(decompressed)
+}
+
+/// The compression type of a .sav prefix byte, or an error naming the byte.
+fn compression_type_of(byte: u8) -> anyhow::Result<CompressionType> {
+    CompressionType::from_byte(byte).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Invalid compression type {}",
+            describe_compression_byte(byte)
+        )
     })
 }
 
+fn read_save_file_with_types<R: Read>(reader: R, types: &uesave::Types) -> anyhow::Result<PalSave> {
+    let mut reader = BufReader::new(reader);
+
+    // The data is read until the end of the zlib streams, not by the length fields, which may be 0
+    let prefix = read_sav_prefix(&mut reader)?;
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    let read = |compression_type: CompressionType| -> anyhow::Result<Save> {
+        let decompressed = decompress(&data, compression_type)?;
+        Ok(Save::read_with_types(
+            &mut Cursor::new(decompressed),
+            types,
+        )?)
+    };
+    let error = match compression_type_of(prefix.compression_type) {
+        Ok(compression_type) => match read(compression_type) {
+            Ok(save) => {
+                return Ok(PalSave {
+                    compression_type,
+                    save,
+                })
+            }
+            Err(error) => error,
+        },
+        Err(error) => error,
+    };
+
+    // Some tools write the wrong compression type. Another type is only taken when it decompresses
+    // to the recorded length (if there is one) and the result parses
+    CompressionType::ALL
+        .into_iter()
+        .filter(|compression_type| compression_type.byte() != prefix.compression_type)
+        .find_map(|compression_type| {
+            let decompressed = decompress(&data, compression_type).ok()?;
+            if prefix.has_lengths() && decompressed.len() != prefix.decompressed_length as usize {
+                return None;
+            }
+            let save = Save::read_with_types(&mut Cursor::new(decompressed), types).ok()?;
+            info!(
+                "Save is labeled compression type {} but is compressed with {}, reading it as such",
+                describe_compression_byte(prefix.compression_type),
+                compression_type
+            );
+            Some(PalSave {
+                compression_type,
+                save,
+            })
+        })
+        .ok_or(error)
+}
+
+/// Default of --max-shrink, in percent
+pub const DEFAULT_MAX_SHRINK_PERCENT: u8 = 10;
+
+/// Returns the length of the GVAS data of a .sav file: the recorded length, or, when the writing
+/// tool zeroed the length fields, the length of the decompressed data.
+pub fn read_uncompressed_length<R: Read>(reader: R) -> anyhow::Result<usize> {
+    let mut reader = BufReader::new(reader);
+    let prefix = read_sav_prefix(&mut reader)?;
+    if prefix.has_lengths() {
+        return Ok(prefix.decompressed_length as usize);
+    }
+    let mut data = Vec::new();
+    reader.read_to_end(&mut data)?;
+    Ok(decompress(&data, compression_type_of(prefix.compression_type)?)?.len())
+}
+
+/// Returns the length of the GVAS data `pal_save` is written as.
+pub fn uncompressed_length(pal_save: &PalSave) -> anyhow::Result<usize> {
+    let mut uncompressed_save = Vec::new();
+    pal_save.save.write(&mut uncompressed_save)?;
+    Ok(uncompressed_save.len())
+}
+
+/// Opens and reads a save file, naming the file in any error.
+pub fn read_save_file_path(path: &Path) -> anyhow::Result<PalSave> {
+    read_save_file_path_with_extra_types(path, &[])
+}
+
+pub fn read_save_file_path_with_extra_types(
+    path: &Path,
+    extra_types: &[(String, uesave::StructType)],
+) -> anyhow::Result<PalSave> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    read_save_file_with_extra_types(file, extra_types)
+        .with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// The .sav prefix stores both lengths as `u32`, so a save cannot exceed 4 GiB.
+fn length_field(length: usize) -> anyhow::Result<u32> {
+    u32::try_from(length)
+        .map_err(|_| anyhow::anyhow!("Save exceeds 4 GiB length-field limit ({} bytes)", length))
+}
+
 pub fn write_save_file<W: Write>(writer: &mut W, pal_save: &PalSave) -> anyhow::Result<()> {
+    write_save_file_with_compression(writer, pal_save, pal_save.compression_type)
+}
+
+/// Writes a save with `compression_type` instead of the one it was read with.
+/// The game itself writes [`CompressionType::DoubleZlib`].
+pub fn write_save_file_with_compression<W: Write>(
+    writer: &mut W,
+    pal_save: &PalSave,
+    compression_type: CompressionType,
+) -> anyhow::Result<()> {
     let mut uncompressed_save = Vec::new();
     pal_save.save.write(&mut uncompressed_save)?;
 
-    let uncompressed_length = uncompressed_save.len() as u32;
+    let uncompressed_length = length_field(uncompressed_save.len())?;
 
     let mut compressor = Cursor::new(Vec::new());
-    let compressed_length = match pal_save.compression_type {
-        0x30 => {
+    let compressed_length = match compression_type {
+        CompressionType::None => {
             compressor.write_all(&uncompressed_save)?;
             uncompressed_length
         }
-        0x31 => {
+        CompressionType::Zlib => {
             let mut encoder =
                 flate2::write::ZlibEncoder::new(&mut compressor, flate2::Compression::default());
             encoder.write_all(&uncompressed_save)?;
             encoder.finish()?;
 
-            compressor.get_ref().len() as u32
+            length_field(compressor.get_ref().len())?
         }
-        0x32 => {
+        CompressionType::DoubleZlib => {
             let mut buffer = Cursor::new(Vec::new());
             let mut encoder =
                 flate2::write::ZlibEncoder::new(&mut buffer, flate2::Compression::default());
@@ -196,7 +468,7 @@ pub fn write_save_file<W: Write>(writer: &mut W, pal_save: &PalSave) -> anyhow::
             encoder.finish()?;
 
             let mut buffer = buffer.into_inner();
-            let compressed_length = buffer.len() as u32;
+            let compressed_length = length_field(buffer.len())?;
 
             let mut encoder =
                 flate2::write::ZlibEncoder::new(&mut compressor, flate2::Compression::default());
@@ -204,7 +476,6 @@ pub fn write_save_file<W: Write>(writer: &mut W, pal_save: &PalSave) -> anyhow::
 
             compressed_length
         }
-        _ => return Err(anyhow::anyhow!("Invalid compression method")),
     };
 
     let compressed = compressor.into_inner();
@@ -212,37 +483,201 @@ pub fn write_save_file<W: Write>(writer: &mut W, pal_save: &PalSave) -> anyhow::
     writer.write_all(&uncompressed_length.to_le_bytes())?;
     writer.write_all(&compressed_length.to_le_bytes())?;
     writer.write_all(&[b'P', b'l', b'Z'])?;
-    writer.write_all(&[pal_save.compression_type])?;
+    writer.write_all(&[compression_type.byte()])?;
     writer.write_all(&compressed)?;
 
     Ok(())
 }
 
-pub fn get_world_save_data(pal_save: &PalSave) -> &IndexMap<String, uesave::Property> {
-    let uesave::Property::Struct {
-        value: uesave::StructValue::Struct(world_save_data),
-        ..
-    } = pal_save.save.root.properties.get("worldSaveData").unwrap()
-    else {
-        panic!()
+/// Resolves the symlinks and junctions of an existing save `path`, so that it is written and
+/// backed up where the file really is. Renaming a new save onto a link would replace the link
+/// instead of the save it points to, and the backup would land beside the link. The resolved path
+/// is printed when it differs from `path`. A path that does not exist yet is returned as is.
+pub fn resolve_save_path(path: &Path) -> anyhow::Result<PathBuf> {
+    if std::fs::symlink_metadata(path).is_err() {
+        return Ok(path.to_path_buf());
+    }
+    let resolved = std::fs::canonicalize(path)
+        .with_context(|| format!("Failed to resolve {}", path.display()))?;
+    if without_verbatim_prefix(&resolved) != std::env::current_dir()?.join(path) {
+        info!(
+            "{} resolves to {}",
+            path.display(),
+            without_verbatim_prefix(&resolved).display()
+        );
+    }
+    Ok(resolved)
+}
+
+/// `canonicalize` returns `\\?\C:\...` paths on Windows. This is the path as it is usually
+/// written, to compare and print.
+fn without_verbatim_prefix(path: &Path) -> PathBuf {
+    let Some(path_str) = path.to_str() else {
+        return path.to_path_buf();
     };
-    world_save_data
+    if let Some(share) = path_str.strip_prefix(r"\\?\UNC\") {
+        PathBuf::from(format!(r"\\{}", share))
+    } else if let Some(local) = path_str.strip_prefix(r"\\?\") {
+        PathBuf::from(local)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+/// Writes a save file to `path`. The save is written next to it and renamed into place, so a
+/// failed write never leaves a partial save behind. When `path` is a link, the file it points
+/// to is replaced, see [`resolve_save_path`].
+pub fn write_save_file_path(
+    path: &Path,
+    pal_save: &PalSave,
+    compression_type: CompressionType,
+) -> anyhow::Result<()> {
+    let path = &resolve_save_path(path)?;
+    let temp_path = path.with_extension("sav.tmp");
+    let file = std::fs::File::create(&temp_path)
+        .with_context(|| format!("Failed to create {}", temp_path.display()))?;
+    let mut writer = BufWriter::new(file);
+    write_save_file_with_compression(&mut writer, pal_save, compression_type)?;
+    writer.into_inner()?.sync_all()?;
+    std::fs::rename(&temp_path, path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Paths of struct properties from the root to the world data, in the order they are tried:
+/// `worldSaveData` as the game writes it, then the variants seen in saves rewritten by community
+/// tools, with another capitalization or wrapped in one more struct.
+pub const WORLD_SAVE_DATA_PATHS: &[&[&str]] = &[
+    &["worldSaveData"],
+    &["WorldSaveData"],
+    &["SaveData", "worldSaveData"],
+];
+
+fn struct_properties(property: &uesave::Property) -> Option<&IndexMap<String, uesave::Property>> {
+    match property {
+        uesave::Property::Struct {
+            value: uesave::StructValue::Struct(properties),
+            ..
+        } => Some(properties),
+        _ => None,
+    }
+}
+
+fn struct_properties_mut(
+    property: &mut uesave::Property,
+) -> Option<&mut IndexMap<String, uesave::Property>> {
+    match property {
+        uesave::Property::Struct {
+            value: uesave::StructValue::Struct(properties),
+            ..
+        } => Some(properties),
+        _ => None,
+    }
+}
+
+/// Returns the first of [`WORLD_SAVE_DATA_PATHS`] that leads to a struct in `pal_save`. Fails
+/// with the top-level property names when none does.
+pub fn find_world_save_data_path(pal_save: &PalSave) -> anyhow::Result<&'static [&'static str]> {
+    let properties = &pal_save.save.root.properties;
+    WORLD_SAVE_DATA_PATHS
+        .iter()
+        .copied()
+        .find(|path| {
+            path.iter()
+                .try_fold(properties, |properties, name| {
+                    properties.get(*name).and_then(struct_properties)
+                })
+                .is_some()
+        })
+        .ok_or_else(|| {
+            let paths: Vec<String> = WORLD_SAVE_DATA_PATHS
+                .iter()
+                .map(|path| path.join("."))
+                .collect();
+            let names: Vec<&str> = properties.keys().map(|name| name.as_str()).collect();
+            anyhow::anyhow!(
+                "No world data struct found at {}. Top-level properties: {}",
+                paths.join(", "),
+                names.join(", ")
+            )
+        })
+}
+
+/// Fails when [`get_world_save_data`] would not find the world data of a Level.sav, and reports
+/// where it was found when it is not at the usual `worldSaveData`.
+pub fn check_world_save_data(level_save: &PalSave) -> anyhow::Result<()> {
+    let path = find_world_save_data_path(level_save)?;
+    if path != WORLD_SAVE_DATA_PATHS[0] {
+        info!("World data found at {}", path.join("."));
+    }
+    Ok(())
+}
+
+/// Default of --max-depth. Structs in Palworld saves nest far less deep.
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+/// Fails when structs (including map keys and values and array elements) nest more than
+/// `max_depth` deep in `pal_save`. The walk keeps its own stack, so it cannot overflow, and the
+/// recursive walks run after it, such as serializing to JSON, are bounded by it. Reading the
+/// save recurses in uesave before this can run, so that is not bounded.
+pub fn check_struct_depth(pal_save: &PalSave, max_depth: usize) -> anyhow::Result<()> {
+    let mut stack: Vec<(String, usize, &IndexMap<String, uesave::Property>)> =
+        vec![(String::new(), 0, &pal_save.save.root.properties)];
+    while let Some((path, depth, properties)) = stack.pop() {
+        if depth > max_depth {
+            anyhow::bail!(
+                "Structs at {} nest {} deep, more than --max-depth {}",
+                path,
+                depth,
+                max_depth
+            );
+        }
+        properties.iter().for_each(|(name, property)| {
+            let nested: Vec<&uesave::StructValue> = match property {
+                uesave::Property::Struct { value, .. } => vec![value],
+                uesave::Property::Map { value, .. } => value
+                    .iter()
+                    .flat_map(|entry| [&entry.key, &entry.value])
+                    .filter_map(|value| match value {
+                        uesave::PropertyValue::Struct(value) => Some(value),
+                        _ => None,
+                    })
+                    .collect(),
+                uesave::Property::Array {
+                    value: uesave::ValueArray::Struct { value, .. },
+                    ..
+                } => value.iter().collect(),
+                _ => Vec::new(),
+            };
+            nested.into_iter().for_each(|value| {
+                if let uesave::StructValue::Struct(properties) = value {
+                    stack.push((format!("{}.{}", path, name), depth + 1, properties));
+                }
+            });
+        });
+    }
+    Ok(())
+}
+
+/// Returns the `worldSaveData` struct, wherever [`find_world_save_data_path`] finds it. Other
+/// top-level properties (e.g. `Version`, `Timestamp`) stay untouched in
+/// `pal_save.save.root.properties` and are written back as-is.
+pub fn get_world_save_data(pal_save: &PalSave) -> &IndexMap<String, uesave::Property> {
+    let path = find_world_save_data_path(pal_save).unwrap_or_else(|e| panic!("{:#}", e));
+    path.iter()
+        .try_fold(&pal_save.save.root.properties, |properties, name| {
+            properties.get(*name).and_then(struct_properties)
+        })
+        .unwrap()
 }
 
 pub fn get_world_save_data_mut(pal_save: &mut PalSave) -> &mut IndexMap<String, uesave::Property> {
-    let uesave::Property::Struct {
-        value: uesave::StructValue::Struct(world_save_data),
-        ..
-    } = pal_save
-        .save
-        .root
-        .properties
-        .get_mut("worldSaveData")
+    let path = find_world_save_data_path(pal_save).unwrap_or_else(|e| panic!("{:#}", e));
+    path.iter()
+        .try_fold(&mut pal_save.save.root.properties, |properties, name| {
+            properties.get_mut(*name).and_then(struct_properties_mut)
+        })
         .unwrap()
-    else {
-        panic!()
-    };
-    world_save_data
 }
 
 pub fn get_group_save_data_map(pal_save: &PalSave) -> &Vec<uesave::MapEntry> {
@@ -257,6 +692,18 @@ pub fn get_group_save_data_map(pal_save: &PalSave) -> &Vec<uesave::MapEntry> {
     group_save_data_map
 }
 
+pub fn get_group_save_data_map_mut(pal_save: &mut PalSave) -> &mut Vec<uesave::MapEntry> {
+    let world_save_data = get_world_save_data_mut(pal_save);
+    let uesave::Property::Map {
+        value: group_save_data_map,
+        ..
+    } = world_save_data.get_mut("GroupSaveDataMap").unwrap()
+    else {
+        panic!()
+    };
+    group_save_data_map
+}
+
 pub fn is_group_type_guild(entry: &uesave::MapEntry) -> bool {
     let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref value)) = entry.value else {
         panic!()
@@ -267,20 +714,203 @@ pub fn is_group_type_guild(entry: &uesave::MapEntry) -> bool {
     }
 }
 
-pub fn parse_raw_group_guild_save(entry: &uesave::MapEntry) -> GroupGuildSave {
+/// Returns the bytes of the `RawData` property of a map entry value.
+/// `RawData` is read as `ByteArray::Byte`; a `ByteArray::Label` array (e.g. from an edited JSON)
+/// is converted when every label is a byte value.
+pub fn get_raw_data(entry: &uesave::MapEntry) -> anyhow::Result<Cow<'_, [u8]>> {
     let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref value)) = entry.value else {
-        panic!()
+        return Err(anyhow::anyhow!("Map entry value is not a struct"));
+    };
+    match value.get("RawData") {
+        Some(uesave::Property::Array {
+            value: uesave::ValueArray::Base(uesave::ValueVec::Byte(uesave::ByteArray::Byte(data))),
+            ..
+        }) => Ok(Cow::Borrowed(data.as_slice())),
+        Some(uesave::Property::Array {
+            value:
+                uesave::ValueArray::Base(uesave::ValueVec::Byte(uesave::ByteArray::Label(labels))),
+            ..
+        }) => labels
+            .iter()
+            .map(|label| {
+                label
+                    .parse::<u8>()
+                    .map_err(|_| anyhow::anyhow!("RawData label {} is not a byte", label))
+            })
+            .collect::<anyhow::Result<Vec<u8>>>()
+            .map(Cow::Owned),
+        Some(uesave::Property::Array { array_type, .. }) => Err(anyhow::anyhow!(
+            "Unsupported RawData array of {:?}",
+            array_type
+        )),
+        Some(_) => Err(anyhow::anyhow!("RawData is not an array")),
+        None => Err(anyhow::anyhow!("Map entry has no RawData")),
+    }
+}
+
+/// Picks the guild `RawData` layout for a save from its header.
+/// Every known save uses the same layout, so this returns the latest one. It lives here rather
+/// than on [`GameVersion`] so `group_guild` builds without uesave, as the fuzz targets need.
+pub fn detect_game_version(_header: &uesave::Header) -> GameVersion {
+    GameVersion::V0_1_3
+}
+
+pub fn parse_raw_group_guild_save(
+    entry: &uesave::MapEntry,
+    version: GameVersion,
+) -> anyhow::Result<GroupGuildSave> {
+    let data = get_raw_data(entry)?;
+    parse_group_guild_save(&data, version)
+}
+
+/// Parses guild `RawData`, which the guild must take up entirely: bytes after it would be
+/// dropped when the guild is written back. On failure the error shows the bytes around where
+/// parsing stopped.
+pub fn parse_group_guild_save(data: &[u8], version: GameVersion) -> anyhow::Result<GroupGuildSave> {
+    let (result, consumed) = read_group_guild_save_consumed(data, version);
+    let group_guild_save = result.map_err(|e| {
+        anyhow::anyhow!(
+            "Failed to parse guild RawData at byte {}: {}\n{}",
+            consumed,
+            e,
+            hex_dump(data, consumed)
+        )
+    })?;
+    if consumed != data.len() {
+        return Err(anyhow::anyhow!(
+            "Failed to parse guild RawData at byte {}: {} bytes left after the guild\n{}",
+            consumed,
+            data.len() - consumed,
+            hex_dump(data, consumed)
+        ));
+    }
+    Ok(group_guild_save)
+}
+
+/// Replaces the guild `RawData` of `entry` with the serialized `group_guild_save`.
+pub fn write_raw_group_guild_save(entry: &mut uesave::MapEntry, group_guild_save: &GroupGuildSave) {
+    set_raw_data(entry, write_group_guild_save(group_guild_save));
+}
+
+/// Overwrites the guild `RawData` of `entry` with `bytes` at `offset`, leaving every other byte
+/// as it is. Prefer [`write_raw_group_guild_save`], which keeps the lengths and counts in the
+/// guild consistent. Use this to change a fixed-size field at a known offset, e.g. the `unknown`
+/// byte, when re-serializing the guild is too risky: a game version the parser does not know
+/// yet, or a guild that does not come back byte for byte from [`write_group_guild_save`]. It
+/// cannot grow or shrink the data, so it is no use for strings or lists.
+pub fn patch_guild_raw(
+    entry: &mut uesave::MapEntry,
+    offset: usize,
+    bytes: &[u8],
+) -> anyhow::Result<()> {
+    let patch = |data: &mut [u8]| -> anyhow::Result<()> {
+        let length = data.len();
+        offset
+            .checked_add(bytes.len())
+            .and_then(|end| data.get_mut(offset..end))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Bytes {}..{} are outside the {} bytes of the guild RawData",
+                    offset,
+                    offset.saturating_add(bytes.len()),
+                    length
+                )
+            })?
+            .copy_from_slice(bytes);
+        Ok(())
+    };
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref mut value)) = entry.value
+    else {
+        return Err(anyhow::anyhow!("Map entry value is not a struct"));
     };
-    let uesave::Property::Array {
-        value: uesave::ValueArray::Base(uesave::ValueVec::Byte(uesave::ByteArray::Byte(ref data))),
+    if let Some(uesave::Property::Array {
+        value: uesave::ValueArray::Base(uesave::ValueVec::Byte(uesave::ByteArray::Byte(data))),
         ..
-    } = &value["RawData"]
+    }) = value.get_mut("RawData")
+    {
+        return patch(data);
+    }
+    // A `ByteArray::Label` array is converted, like `get_raw_data` does
+    let mut data = get_raw_data(entry)?.into_owned();
+    patch(&mut data)?;
+    set_raw_data(entry, data);
+    Ok(())
+}
+
+/// Replaces the `RawData` of a map entry value with `raw_data`, as a `ByteArray::Byte`.
+pub fn set_raw_data(entry: &mut uesave::MapEntry, raw_data: Vec<u8>) {
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref mut value)) = entry.value
     else {
         panic!()
     };
-    let binding = data.clone();
-    let mut stream = stream(binding.as_slice());
-    read_group_guild_save.parse_next(&mut stream).unwrap()
+    match value.get_mut("RawData") {
+        Some(uesave::Property::Array {
+            value: uesave::ValueArray::Base(uesave::ValueVec::Byte(data)),
+            ..
+        }) => *data = uesave::ByteArray::Byte(raw_data),
+        _ => {
+            value.insert(
+                "RawData".into(),
+                uesave::Property::Array {
+                    array_type: uesave::PropertyType::ByteProperty,
+                    id: None,
+                    value: uesave::ValueArray::Base(uesave::ValueVec::Byte(
+                        uesave::ByteArray::Byte(raw_data),
+                    )),
+                },
+            );
+        }
+    }
+}
+
+/// Parses every guild in `GroupSaveDataMap`, keyed by the group id.
+pub fn get_guilds(
+    pal_save: &PalSave,
+    version: GameVersion,
+) -> anyhow::Result<Vec<(Uuid, GroupGuildSave)>> {
+    get_group_save_data_map(pal_save)
+        .iter()
+        .filter(|entry| is_group_type_guild(entry))
+        .map(|entry| -> anyhow::Result<(Uuid, GroupGuildSave)> {
+            let uesave::PropertyValue::Struct(uesave::StructValue::Guid(guild_id)) = entry.key
+            else {
+                return Err(anyhow::anyhow!("Group key is not a Guid"));
+            };
+            Ok((guild_id, parse_raw_group_guild_save(entry, version)?))
+        })
+        .collect()
+}
+
+/// Like [`get_guilds`], but parses each guild independently so one corrupt guild does not
+/// prevent using the others. Returns the parsed guilds and the errors of the failed ones.
+pub fn get_guilds_skipping_failures(
+    pal_save: &PalSave,
+    version: GameVersion,
+) -> (Vec<(Uuid, GroupGuildSave)>, Vec<(Uuid, anyhow::Error)>) {
+    let mut guilds = Vec::new();
+    let mut failures = Vec::new();
+    get_group_save_data_map(pal_save)
+        .iter()
+        .filter(|entry| is_group_type_guild(entry))
+        .for_each(|entry| {
+            let guild_id = match entry.key {
+                uesave::PropertyValue::Struct(uesave::StructValue::Guid(guild_id)) => guild_id,
+                _ => Uuid::nil(),
+            };
+            match parse_raw_group_guild_save(entry, version) {
+                Ok(group_guild_save) => guilds.push((guild_id, group_guild_save)),
+                Err(error) => failures.push((guild_id, error)),
+            }
+        });
+    (guilds, failures)
+}
+
+/// Returns the entries of a `worldSaveData` map, or none when the save does not have it.
+pub fn get_map_entries<'a>(pal_save: &'a PalSave, name: &str) -> &'a [uesave::MapEntry] {
+    match get_world_save_data(pal_save).get(name) {
+        Some(uesave::Property::Map { value, .. }) => value,
+        _ => &[],
+    }
 }
 
 pub fn get_character_save_parameter_map(pal_save: &PalSave) -> &Vec<uesave::MapEntry> {
@@ -295,6 +925,9 @@ pub fn get_character_save_parameter_map(pal_save: &PalSave) -> &Vec<uesave::MapE
     character_save_parameter_map
 }
 
+/// Entries must stay in file order, which the game may depend on: change entries in place,
+/// remove them with `retain` or `remove` and append new ones at the end. Never rebuild the map
+/// through a `HashMap`.
 pub fn get_character_save_parameter_map_mut(pal_save: &mut PalSave) -> &mut Vec<uesave::MapEntry> {
     let world_save_data = get_world_save_data_mut(pal_save);
     let uesave::Property::Map {
@@ -309,29 +942,260 @@ pub fn get_character_save_parameter_map_mut(pal_save: &mut PalSave) -> &mut Vec<
     character_save_parameter_map
 }
 
+/// Decodes the `(PlayerUId, InstanceId)` of a `CharacterSaveParameterMap` key. The `PlayerUId`
+/// is `None` for pals, whose key has a nil `PlayerUId` or none at all.
+pub fn decode_character_key(entry: &uesave::MapEntry) -> anyhow::Result<(Option<Uuid>, Uuid)> {
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref key)) = entry.key else {
+        return Err(anyhow::anyhow!("Character key is not a struct"));
+    };
+    let guid = |name: &str| match key.get(name) {
+        Some(uesave::Property::Struct {
+            value: uesave::StructValue::Guid(guid),
+            ..
+        }) => Ok(Some(*guid)),
+        Some(_) => Err(anyhow::anyhow!("Character key {} is not a Guid", name)),
+        None => Ok(None),
+    };
+    let player_uid = guid("PlayerUId")?.filter(|player_uid| !player_uid.is_nil());
+    let instance_id = guid("InstanceId")?.context("Character key has no InstanceId")?;
+    Ok((player_uid, instance_id))
+}
+
+/// Replaces the `InstanceId` of a `CharacterSaveParameterMap` key, leaving its `RawData` alone.
+pub fn set_character_instance_id(
+    entry: &mut uesave::MapEntry,
+    instance_id: Uuid,
+) -> anyhow::Result<()> {
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref mut key)) = entry.key else {
+        return Err(anyhow::anyhow!("Character key is not a struct"));
+    };
+    match key.get_mut("InstanceId") {
+        Some(uesave::Property::Struct {
+            value: uesave::StructValue::Guid(guid),
+            ..
+        }) => {
+            *guid = instance_id;
+            Ok(())
+        }
+        Some(_) => Err(anyhow::anyhow!("Character key InstanceId is not a Guid")),
+        None => Err(anyhow::anyhow!("Character key has no InstanceId")),
+    }
+}
+
+/// Returns the `(PlayerUId, InstanceId)` in `SaveData.IndividualId` of a player save, or `None`
+/// when `pal_save` has none and so is not a player save.
+pub fn get_player_individual_id(pal_save: &PalSave) -> Option<(Uuid, Uuid)> {
+    let Some(uesave::Property::Struct {
+        value: uesave::StructValue::Struct(save_data),
+        ..
+    }) = pal_save.save.root.properties.get("SaveData")
+    else {
+        return None;
+    };
+    let Some(uesave::Property::Struct {
+        value: uesave::StructValue::Struct(individual_id),
+        ..
+    }) = save_data.get("IndividualId")
+    else {
+        return None;
+    };
+    let guid = |name: &str| match individual_id.get(name) {
+        Some(uesave::Property::Struct {
+            value: uesave::StructValue::Guid(guid),
+            ..
+        }) => Some(*guid),
+        _ => None,
+    };
+    Some((guid("PlayerUId")?, guid("InstanceId")?))
+}
+
+/// Returns the `(PlayerUId, InstanceId)` of a `CharacterSaveParameterMap` key, or `None` if it
+/// cannot be decoded (see [`decode_character_key`]). Pals have a nil `PlayerUId`.
+pub fn get_character_key_ids(entry: &uesave::MapEntry) -> Option<(Uuid, Uuid)> {
+    let (player_uid, instance_id) = decode_character_key(entry).ok()?;
+    Some((player_uid.unwrap_or_default(), instance_id))
+}
+
+/// Iterates `CharacterSaveParameterMap` as `(PlayerUId, InstanceId, entry)`, in map order, with
+/// the `PlayerUId` of [`decode_character_key`].
+/// A key that cannot be decoded yields an error without ending the iteration. A save without
+/// the map has no entries.
+pub fn iter_character_entries(
+    pal_save: &PalSave,
+) -> impl Iterator<Item = anyhow::Result<(Option<Uuid>, Uuid, &uesave::MapEntry)>> {
+    get_map_entries(pal_save, "CharacterSaveParameterMap")
+        .iter()
+        .enumerate()
+        .map(|(index, entry)| {
+            decode_character_key(entry)
+                .map(|(player_uid, instance_id)| (player_uid, instance_id, entry))
+                .with_context(|| format!("Failed to decode character key {}", index))
+        })
+}
+
+/// Maps the PlayerUId of every character key with a non-empty `DebugName` to that name, the first
+/// one in map order when a player has several characters.
+pub fn get_character_debug_names(pal_save: &PalSave) -> HashMap<Uuid, String> {
+    let mut debug_names = HashMap::new();
+    iter_character_entries(pal_save).for_each(|entry| {
+        let Ok((Some(player_uid), _, entry)) = entry else {
+            return;
+        };
+        let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref key)) = entry.key else {
+            return;
+        };
+        let Some(uesave::Property::Str { value, .. }) = key.get("DebugName") else {
+            return;
+        };
+        if !value.is_empty() {
+            debug_names
+                .entry(player_uid)
+                .or_insert_with(|| value.to_string());
+        }
+    });
+    debug_names
+}
+
+/// Maps the InstanceId of every character to its PlayerUId, `None` for pals. Keys that cannot be
+/// decoded are logged and skipped. The map is allocated for every entry up front, since
+/// collecting a `filter_map` gives it no size hint and large servers would rehash it many times.
+pub fn get_character_owners(pal_save: &PalSave) -> HashMap<Uuid, Option<Uuid>> {
+    let mut character_owners =
+        HashMap::with_capacity(get_map_entries(pal_save, "CharacterSaveParameterMap").len());
+    character_owners.extend(
+        iter_character_entries(pal_save).filter_map(|entry| match entry {
+            Ok((player_uid, instance_id, _)) => Some((instance_id, player_uid)),
+            Err(error) => {
+                info!("{:#}, skipping", error);
+                None
+            }
+        }),
+    );
+    character_owners
+}
+
+/// Builds a `CharacterSaveParameterMap` entry for the character `instance_id` of `player_uid`.
+pub fn new_character_save_entry(
+    header: &uesave::Header,
+    player_uid: Uuid,
+    instance_id: Uuid,
+    character_save_parameter: &CharacterSaveParameter,
+) -> uesave::MapEntry {
+    new_character_save_entry_raw(
+        player_uid,
+        instance_id,
+        write_raw_character_save_parameter(header)(character_save_parameter),
+    )
+}
+
+/// Like [`new_character_save_entry`], with an already serialized `RawData`.
+pub fn new_character_save_entry_raw(
+    player_uid: Uuid,
+    instance_id: Uuid,
+    raw_data: Vec<u8>,
+) -> uesave::MapEntry {
+    let key = {
+        let mut key: IndexMap<String, uesave::Property> = IndexMap::new();
+        key.insert(
+            "PlayerUId".into(),
+            uesave::Property::Struct {
+                id: None,
+                value: uesave::StructValue::Guid(player_uid),
+                struct_type: uesave::StructType::Guid,
+                struct_id: Uuid::nil(),
+            },
+        );
+        key.insert(
+            "InstanceId".into(),
+            uesave::Property::Struct {
+                id: None,
+                value: uesave::StructValue::Guid(instance_id),
+                struct_type: uesave::StructType::Guid,
+                struct_id: Uuid::nil(),
+            },
+        );
+        key.insert(
+            "DebugName".into(),
+            uesave::Property::Str {
+                id: None,
+                value: "".into(),
+            },
+        );
+        uesave::PropertyValue::Struct(uesave::StructValue::Struct(key))
+    };
+    let value = {
+        let mut value: IndexMap<String, uesave::Property> = IndexMap::new();
+        value.insert(
+            "RawData".into(),
+            uesave::Property::Array {
+                array_type: uesave::PropertyType::ByteProperty,
+                id: None,
+                value: uesave::ValueArray::Base(uesave::ValueVec::Byte(uesave::ByteArray::Byte(
+                    raw_data,
+                ))),
+            },
+        );
+        uesave::PropertyValue::Struct(uesave::StructValue::Struct(value))
+    };
+    uesave::MapEntry { key, value }
+}
+
+/// Gives the Guid properties of the synthesized key of `entry` the `struct_type` and `struct_id`
+/// they have in the first key of `existing`, so new keys match those the game wrote instead of
+/// assuming a nil `struct_id`. Properties the existing key does not have as a Guid are kept.
+pub fn match_character_key_shape(entry: &mut uesave::MapEntry, existing: &[uesave::MapEntry]) {
+    let Some(uesave::PropertyValue::Struct(uesave::StructValue::Struct(existing_key))) =
+        existing.first().map(|existing_entry| &existing_entry.key)
+    else {
+        return;
+    };
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref mut key)) = entry.key else {
+        return;
+    };
+    key.iter_mut().for_each(|(name, property)| {
+        if let (
+            uesave::Property::Struct {
+                struct_type,
+                struct_id,
+                ..
+            },
+            Some(uesave::Property::Struct {
+                value: uesave::StructValue::Guid(_),
+                struct_type: existing_struct_type,
+                struct_id: existing_struct_id,
+                ..
+            }),
+        ) = (property, existing_key.get(name))
+        {
+            *struct_type = existing_struct_type.clone();
+            *struct_id = *existing_struct_id;
+        }
+    });
+}
+
 pub fn parse_raw_character_save_parameter<'a>(
     header: &'a uesave::Header,
-) -> impl Fn(&'a uesave::MapEntry) -> CharacterSaveParameter {
+) -> impl Fn(&'a uesave::MapEntry) -> anyhow::Result<CharacterSaveParameter> {
     move |entry: &uesave::MapEntry| {
-        let data = {
-            let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref value)) = entry.value
-            else {
-                panic!()
-            };
-            let uesave::Property::Array {
-                value:
-                    uesave::ValueArray::Base(uesave::ValueVec::Byte(uesave::ByteArray::Byte(ref data))),
-                ..
-            } = &value["RawData"]
-            else {
-                panic!()
-            };
-            data
-        };
-        read_raw_character_save_parameter(&header)(data)
+        let data = get_raw_data(entry)?;
+        read_raw_character_save_parameter(&header)(&data)
     }
 }
 
+/// Returns the first player character of the group `group_id` whose `RawData` parses, or
+/// `None` when the group has none.
+pub fn find_player_character_in_group(
+    pal_save: &PalSave,
+    group_id: Uuid,
+) -> Option<CharacterSaveParameter> {
+    let header = &pal_save.save.header;
+    iter_character_entries(pal_save)
+        .filter_map(|entry| entry.ok())
+        .filter(|(player_uid, _, _)| player_uid.is_some_and(|player_uid| !player_uid.is_nil()))
+        .filter_map(|(_, _, entry)| parse_raw_character_save_parameter(header)(entry).ok())
+        .find(|character_save_parameter| character_save_parameter.group_id == group_id)
+}
+
 #[test]
 pub fn test_read_write_save_file() {
     use std::io::Cursor;
@@ -344,3 +1208,508 @@ pub fn test_read_write_save_file() {
     let re_pal_save = read_save_file(Cursor::new(&mut re_save)).unwrap();
     assert_eq!(pal_save, re_pal_save);
 }
+
+#[test]
+pub fn test_read_write_save_file_preserves_top_level_properties() {
+    use std::io::Cursor;
+
+    let mut save = std::fs::read("assets/Level.sav").unwrap();
+    let pal_save = read_save_file(Cursor::new(&mut save)).unwrap();
+    let siblings: Vec<(&String, &uesave::Property)> = pal_save
+        .save
+        .root
+        .properties
+        .iter()
+        .filter(|(name, _)| *name != "worldSaveData")
+        .collect();
+    assert!(!siblings.is_empty());
+
+    let mut re_save = Vec::new();
+    write_save_file(&mut re_save, &pal_save).unwrap();
+    let re_pal_save = read_save_file(Cursor::new(&mut re_save)).unwrap();
+    let re_siblings: Vec<(&String, &uesave::Property)> = re_pal_save
+        .save
+        .root
+        .properties
+        .iter()
+        .filter(|(name, _)| *name != "worldSaveData")
+        .collect();
+    assert_eq!(siblings, re_siblings);
+}
+
+#[test]
+pub fn test_get_world_save_data_under_alternate_paths() {
+    use crate::fixtures;
+
+    let level_save = fixtures::read_level_save();
+    assert_eq!(
+        find_world_save_data_path(&level_save).unwrap(),
+        ["worldSaveData"]
+    );
+    let group_count = get_group_save_data_map(&level_save).len();
+    let world_save_data = level_save
+        .save
+        .root
+        .properties
+        .get("worldSaveData")
+        .unwrap()
+        .clone();
+
+    // Under another name
+    let mut renamed = level_save.clone();
+    renamed.save.root.properties.shift_remove("worldSaveData");
+    renamed
+        .save
+        .root
+        .properties
+        .insert("WorldSaveData".to_string(), world_save_data.clone());
+    assert_eq!(
+        find_world_save_data_path(&renamed).unwrap(),
+        ["WorldSaveData"]
+    );
+    assert_eq!(get_group_save_data_map(&renamed).len(), group_count);
+
+    // Wrapped in one more struct
+    let mut nested = level_save.clone();
+    nested.save.root.properties.shift_remove("worldSaveData");
+    let mut wrapper = world_save_data.clone();
+    let uesave::Property::Struct {
+        value: uesave::StructValue::Struct(ref mut properties),
+        ..
+    } = wrapper
+    else {
+        panic!()
+    };
+    *properties = IndexMap::from([("worldSaveData".to_string(), world_save_data)]);
+    nested
+        .save
+        .root
+        .properties
+        .insert("SaveData".to_string(), wrapper);
+    assert_eq!(
+        find_world_save_data_path(&nested).unwrap(),
+        ["SaveData", "worldSaveData"]
+    );
+    get_group_save_data_map_mut(&mut nested).pop();
+    assert_eq!(get_group_save_data_map(&nested).len(), group_count - 1);
+
+    // Missing, listing what there is instead
+    let mut missing = level_save.clone();
+    missing.save.root.properties.shift_remove("worldSaveData");
+    let message = format!("{:#}", check_world_save_data(&missing).unwrap_err());
+    assert!(
+        message.contains("No world data struct found"),
+        "{}",
+        message
+    );
+    assert!(
+        missing
+            .save
+            .root
+            .properties
+            .keys()
+            .all(|name| message.contains(name.as_str())),
+        "{}",
+        message
+    );
+}
+
+#[test]
+pub fn test_get_raw_data_byte_array_variants() {
+    let entry = |raw_data: uesave::Property| {
+        let mut value = IndexMap::new();
+        value.insert("RawData".to_string(), raw_data);
+        uesave::MapEntry {
+            key: uesave::PropertyValue::Struct(uesave::StructValue::Guid(uuid::Uuid::nil())),
+            value: uesave::PropertyValue::Struct(uesave::StructValue::Struct(value)),
+        }
+    };
+    let labels = |labels: &[&str]| uesave::Property::Array {
+        array_type: uesave::PropertyType::ByteProperty,
+        id: None,
+        value: uesave::ValueArray::Base(uesave::ValueVec::Byte(uesave::ByteArray::Label(
+            labels.iter().map(|label| label.to_string()).collect(),
+        ))),
+    };
+
+    let bytes = entry(uesave::Property::Array {
+        array_type: uesave::PropertyType::ByteProperty,
+        id: None,
+        value: uesave::ValueArray::Base(uesave::ValueVec::Byte(uesave::ByteArray::Byte(vec![
+            1, 2, 3,
+        ]))),
+    });
+    assert_eq!(get_raw_data(&bytes).unwrap().as_ref(), &[1, 2, 3]);
+
+    let byte_labels = entry(labels(&["1", "2", "3"]));
+    assert_eq!(get_raw_data(&byte_labels).unwrap().as_ref(), &[1, 2, 3]);
+
+    let enum_labels = entry(labels(&["EPalGroupType::Guild"]));
+    assert!(get_raw_data(&enum_labels).is_err());
+
+    let not_array = entry(uesave::Property::Str {
+        id: None,
+        value: "RawData".into(),
+    });
+    assert!(get_raw_data(&not_array).is_err());
+    assert!(parse_raw_group_guild_save(&not_array, GameVersion::V0_1_3).is_err());
+}
+
+#[test]
+pub fn test_patch_guild_raw() {
+    use crate::fixtures;
+
+    let raw_data = std::fs::read("assets/guild_0.bin").unwrap();
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_guild_raw(&mut level_save, Uuid::from_u128(0x100), raw_data.clone());
+    let entry = get_group_save_data_map_mut(&mut level_save)
+        .iter_mut()
+        .find(|entry| {
+            matches!(
+                entry.key,
+                uesave::PropertyValue::Struct(uesave::StructValue::Guid(id))
+                    if id == Uuid::from_u128(0x100)
+            )
+        })
+        .unwrap();
+
+    let offset = raw_data.len() / 2;
+    patch_guild_raw(entry, offset, &[!raw_data[offset]]).unwrap();
+    let patched = get_raw_data(entry).unwrap().into_owned();
+    assert_eq!(patched.len(), raw_data.len());
+    let changed: Vec<usize> = (0..raw_data.len())
+        .filter(|index| patched[*index] != raw_data[*index])
+        .collect();
+    assert_eq!(changed, vec![offset]);
+    assert_eq!(patched[offset], !raw_data[offset]);
+
+    // Nothing is written past the end
+    assert!(patch_guild_raw(entry, raw_data.len() - 1, &[0, 0]).is_err());
+    assert!(patch_guild_raw(entry, usize::MAX, &[0]).is_err());
+    assert_eq!(get_raw_data(entry).unwrap().as_ref(), patched.as_slice());
+}
+
+#[test]
+pub fn test_check_struct_depth() {
+    use crate::fixtures;
+
+    let mut level_save = fixtures::read_level_save();
+    check_struct_depth(&level_save, DEFAULT_MAX_DEPTH).unwrap();
+    let error = check_struct_depth(&level_save, 1).unwrap_err().to_string();
+    assert!(error.ends_with("more than --max-depth 1"), "{}", error);
+
+    // Nested far deeper than any save, as a crafted one could be
+    let nested = (0..1_000).fold(
+        uesave::Property::Int { id: None, value: 0 },
+        |property, _| {
+            let mut properties = IndexMap::new();
+            properties.insert("Inner".to_string(), property);
+            fixtures::struct_property(properties)
+        },
+    );
+    level_save
+        .save
+        .root
+        .properties
+        .insert("Nested".into(), nested);
+    let error = check_struct_depth(&level_save, DEFAULT_MAX_DEPTH)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.contains("nest 65 deep, more than --max-depth 64"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+pub fn test_read_save_file_path_error_names_file() {
+    let error = read_save_file_path(Path::new("assets/missing.sav")).unwrap_err();
+    assert!(error.to_string().contains("assets/missing.sav"));
+
+    let error = read_save_file_path(Path::new("assets/guild_0.bin")).unwrap_err();
+    assert!(error.to_string().contains("assets/guild_0.bin"));
+}
+
+#[test]
+pub fn test_read_save_file_with_zeroed_lengths() {
+    use std::io::Cursor;
+
+    let save = std::fs::read("assets/Level.sav").unwrap();
+    let pal_save = read_save_file(Cursor::new(&save)).unwrap();
+    let mut zeroed = save.clone();
+    zeroed[..8].fill(0);
+    let prefix = read_sav_prefix(&mut Cursor::new(&zeroed)).unwrap();
+    assert!(!prefix.has_lengths());
+
+    let zeroed_pal_save = read_save_file(Cursor::new(&zeroed)).unwrap();
+    assert_eq!(zeroed_pal_save, pal_save);
+
+    for compression_type in CompressionType::ALL {
+        let mut re_save = Vec::new();
+        write_save_file_with_compression(&mut re_save, &zeroed_pal_save, compression_type).unwrap();
+        let prefix = read_sav_prefix(&mut Cursor::new(&re_save)).unwrap();
+        let mut uncompressed = Vec::new();
+        zeroed_pal_save.save.write(&mut uncompressed).unwrap();
+        assert_eq!(prefix.decompressed_length as usize, uncompressed.len());
+        assert!(prefix.compressed_length > 0);
+    }
+}
+
+#[test]
+pub fn test_read_save_file_with_mislabeled_compression_type() {
+    use std::io::Cursor;
+
+    let pal_save = read_save_file(std::fs::File::open("assets/Level.sav").unwrap()).unwrap();
+    for (compression_type, label) in [
+        (CompressionType::Zlib, b'0'),
+        (CompressionType::Zlib, b'2'),
+        (CompressionType::DoubleZlib, b'1'),
+        (CompressionType::None, b'1'),
+        // The digit's value instead of the ASCII digit
+        (CompressionType::DoubleZlib, 2),
+    ] {
+        let mut save = Vec::new();
+        write_save_file_with_compression(&mut save, &pal_save, compression_type).unwrap();
+        // The compression type follows both lengths and the magic
+        save[11] = label;
+        let re_pal_save = read_save_file(Cursor::new(&save)).unwrap();
+        assert_eq!(re_pal_save.compression_type, compression_type);
+        assert_eq!(re_pal_save.save, pal_save.save);
+    }
+
+    // Data that no compression type reads still fails
+    let mut save = Vec::new();
+    write_save_file_with_compression(&mut save, &pal_save, CompressionType::Zlib).unwrap();
+    save.truncate(64);
+    assert!(read_save_file(Cursor::new(&save)).is_err());
+}
+
+#[test]
+pub fn test_write_save_file_with_compression() {
+    use std::io::Cursor;
+
+    let mut save = std::fs::read("assets/Level.sav").unwrap();
+    let pal_save = read_save_file(Cursor::new(&mut save)).unwrap();
+    assert_eq!(pal_save.compression_type, CompressionType::DoubleZlib);
+
+    for compression_type in [CompressionType::None, CompressionType::Zlib] {
+        let mut re_save = Vec::new();
+        write_save_file_with_compression(&mut re_save, &pal_save, compression_type).unwrap();
+        let re_pal_save = read_save_file(Cursor::new(&mut re_save)).unwrap();
+        assert_eq!(re_pal_save.compression_type, compression_type);
+        assert_eq!(pal_save.save, re_pal_save.save);
+    }
+}
+
+#[test]
+pub fn test_compression_type_bytes_and_names() {
+    for (byte, compression_type, display) in [
+        (0x30, CompressionType::None, "None (0x30)"),
+        (0x31, CompressionType::Zlib, "Zlib (0x31)"),
+        (0x32, CompressionType::DoubleZlib, "DoubleZlib (0x32)"),
+    ] {
+        assert_eq!(CompressionType::from_byte(byte), Some(compression_type));
+        assert_eq!(compression_type.byte(), byte);
+        assert_eq!(compression_type.to_string(), display);
+        assert_eq!(describe_compression_byte(byte), display);
+        assert_eq!(
+            parse_compression_type(&format!("{:#x}", byte)),
+            Ok(compression_type)
+        );
+        assert_eq!(
+            parse_compression_type(compression_type.name()),
+            Ok(compression_type)
+        );
+    }
+    // The digits themselves are not compression types
+    assert_eq!(CompressionType::from_byte(2), None);
+    assert_eq!(describe_compression_byte(2), "unknown (0x02)");
+    assert_eq!(
+        parse_compression_type("doublezlib"),
+        Ok(CompressionType::DoubleZlib)
+    );
+    assert!(parse_compression_type("2").is_err());
+    assert!(parse_compression_type("zlib2").is_err());
+}
+
+#[test]
+pub fn test_read_save_file_with_extra_types() {
+    let save = std::fs::read("assets/Level.sav").unwrap();
+    let pal_save = read_save_file(save.as_slice()).unwrap();
+
+    let unused = parse_extra_type(".worldSaveData.UnusedMap.Key=Guid").unwrap();
+    let re_pal_save = read_save_file_with_extra_types(save.as_slice(), &[unused]).unwrap();
+    assert_eq!(pal_save, re_pal_save);
+
+    // Overriding a registered path changes how the save is read
+    let group_key = parse_extra_type(".worldSaveData.GroupSaveDataMap.Key=Struct").unwrap();
+    let result = read_save_file_with_extra_types(save.as_slice(), &[group_key]);
+    assert!(!matches!(result, Ok(ref re_pal_save) if *re_pal_save == pal_save));
+
+    assert!(parse_extra_type(".worldSaveData.GroupSaveDataMap.Key").is_err());
+    assert!(parse_extra_type(".worldSaveData.GroupSaveDataMap.Key=Vector").is_err());
+}
+
+#[test]
+pub fn test_length_field_rejects_lengths_over_4_gib() {
+    assert_eq!(length_field(u32::MAX as usize).unwrap(), u32::MAX);
+    if let Some(length) = (u32::MAX as usize).checked_add(1) {
+        let error = length_field(length).unwrap_err();
+        assert!(error.to_string().contains("4 GiB length-field limit"));
+    }
+}
+
+#[test]
+pub fn test_parse_group_guild_save_dumps_bytes_around_failure() {
+    let mut data = std::fs::read("assets/guild_0.bin").unwrap();
+    // Length of the FString after the 16-byte UnknownUuid
+    data[16..20].copy_from_slice(&i32::MAX.to_le_bytes());
+
+    let error = parse_group_guild_save(&data, GameVersion::V0_1_3)
+        .unwrap_err()
+        .to_string();
+    assert!(error.starts_with("Failed to parse guild RawData at byte "));
+    let lines: Vec<&str> = error.lines().collect();
+    let row = lines
+        .iter()
+        .position(|line| line.starts_with("00000010"))
+        .unwrap();
+    assert!(lines[row].contains("ff ff ff 7f"));
+    assert!(lines.iter().any(|line| line.trim_start() == "^^"));
+}
+
+#[test]
+pub fn test_parse_group_guild_save_rejects_trailing_bytes() {
+    let data = std::fs::read("assets/guild_0.bin").unwrap();
+    assert!(parse_group_guild_save(&data, GameVersion::V0_1_3).is_ok());
+
+    let mut longer = data.clone();
+    longer.extend_from_slice(&[0xff; 3]);
+    let error = parse_group_guild_save(&longer, GameVersion::V0_1_3)
+        .unwrap_err()
+        .to_string();
+    assert!(
+        error.starts_with(&format!(
+            "Failed to parse guild RawData at byte {}: 3 bytes left after the guild",
+            data.len()
+        )),
+        "{}",
+        error
+    );
+    assert!(error.lines().any(|line| line.trim_start() == "^^"));
+}
+
+#[test]
+pub fn test_iter_character_entries() {
+    use crate::fixtures;
+
+    let mut level_save = fixtures::read_level_save();
+    // assets/Level.sav has no CharacterSaveParameterMap
+    assert_eq!(iter_character_entries(&level_save).count(), 0);
+
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(1), Uuid::from_u128(2));
+    fixtures::add_character_save(&mut level_save, Uuid::nil(), Uuid::from_u128(3));
+    let entries = iter_character_entries(&level_save)
+        .collect::<anyhow::Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        (entries[0].0, entries[0].1),
+        (Some(Uuid::from_u128(1)), Uuid::from_u128(2))
+    );
+    assert_eq!((entries[1].0, entries[1].1), (None, Uuid::from_u128(3)));
+
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref mut key)) =
+        get_character_save_parameter_map_mut(&mut level_save)[0].key
+    else {
+        panic!()
+    };
+    key.shift_remove("InstanceId");
+    let entries: Vec<_> = iter_character_entries(&level_save).collect();
+    assert_eq!(entries.len(), 2);
+    assert!(format!("{:#}", entries[0].as_ref().unwrap_err()).contains("has no InstanceId"));
+    assert!(entries[1].is_ok());
+}
+
+#[test]
+pub fn test_get_character_owners() {
+    use crate::fixtures;
+
+    let mut level_save = fixtures::read_level_save();
+    assert!(get_character_owners(&level_save).is_empty());
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    (1..=100u128).for_each(|index| {
+        fixtures::add_character_save(
+            &mut level_save,
+            Uuid::from_u128(index % 3),
+            Uuid::from_u128(0x1000 + index),
+        );
+    });
+
+    // The same as collecting the entries
+    let collected: HashMap<Uuid, Option<Uuid>> = iter_character_entries(&level_save)
+        .filter_map(|entry| entry.ok())
+        .map(|(player_uid, instance_id, _)| (instance_id, player_uid))
+        .collect();
+    let character_owners = get_character_owners(&level_save);
+    assert_eq!(character_owners, collected);
+    assert_eq!(character_owners.len(), 100);
+    assert!(character_owners.capacity() >= 100);
+    (0..=0x2000u128).for_each(|instance_id| {
+        let instance_id = Uuid::from_u128(instance_id);
+        assert_eq!(
+            character_owners.get(&instance_id),
+            collected.get(&instance_id)
+        );
+    });
+}
+
+#[test]
+pub fn test_decode_character_key_without_player_uid() {
+    use crate::fixtures;
+
+    let mut level_save = fixtures::read_level_save();
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(1), Uuid::from_u128(2));
+    fixtures::add_character_save(&mut level_save, Uuid::nil(), Uuid::from_u128(3));
+    // A pal key with only an InstanceId
+    let uesave::PropertyValue::Struct(uesave::StructValue::Struct(ref mut key)) =
+        get_character_save_parameter_map_mut(&mut level_save)[1].key
+    else {
+        panic!()
+    };
+    key.shift_remove("PlayerUId");
+
+    let entries = get_character_save_parameter_map(&level_save);
+    assert_eq!(
+        decode_character_key(&entries[0]).unwrap(),
+        (Some(Uuid::from_u128(1)), Uuid::from_u128(2))
+    );
+    assert_eq!(
+        decode_character_key(&entries[1]).unwrap(),
+        (None, Uuid::from_u128(3))
+    );
+    assert_eq!(
+        get_character_key_ids(&entries[1]),
+        Some((Uuid::nil(), Uuid::from_u128(3)))
+    );
+}
+
+#[test]
+pub fn test_get_player_individual_id() {
+    use crate::fixtures;
+
+    let level_save = fixtures::read_level_save();
+    let player_save = fixtures::player_save(&level_save, Uuid::from_u128(1), Uuid::from_u128(2));
+    assert_eq!(
+        get_player_individual_id(&player_save),
+        Some((Uuid::from_u128(1), Uuid::from_u128(2)))
+    );
+    assert_eq!(get_player_individual_id(&level_save), None);
+    assert_eq!(
+        get_player_individual_id(&fixtures::level_meta_save(&level_save, "Host")),
+        None
+    );
+}
@@ -0,0 +1,122 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use walkdir::WalkDir;
+
+/// Copies `Level.sav`, `LevelMeta.sav` if present and the `Players` directory of a save to
+/// `output`.
+/// `output` is created if needed and must be empty unless `force` is set.
+pub fn copy_save_directory(input: &Path, output: &Path, force: bool) -> anyhow::Result<()> {
+    ensure_empty_output_dir(output, force)?;
+    std::fs::create_dir_all(output.join("Players"))
+        .with_context(|| format!("Failed to create {}", output.display()))?;
+
+    std::fs::copy(input.join("Level.sav"), output.join("Level.sav"))
+        .with_context(|| format!("Failed to copy {}", input.join("Level.sav").display()))?;
+    let level_meta_sav = input.join("LevelMeta.sav");
+    if level_meta_sav.exists() {
+        std::fs::copy(&level_meta_sav, output.join("LevelMeta.sav"))
+            .with_context(|| format!("Failed to copy {}", level_meta_sav.display()))?;
+    }
+    let players = input.join("Players");
+    for entry in WalkDir::new(&players).min_depth(1) {
+        let entry = entry.with_context(|| format!("Failed to read {}", players.display()))?;
+        let target = output
+            .join("Players")
+            .join(entry.path().strip_prefix(&players)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(&target)
+                .with_context(|| format!("Failed to create {}", target.display()))?;
+        } else if entry.file_type().is_file() {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Fails if `output` exists and is not empty, unless `force` is set.
+pub fn ensure_empty_output_dir(output: &Path, force: bool) -> anyhow::Result<()> {
+    if output.exists() {
+        let is_empty = std::fs::read_dir(output)
+            .with_context(|| format!("Failed to read {}", output.display()))?
+            .next()
+            .is_none();
+        if !is_empty && !force {
+            return Err(anyhow::anyhow!(
+                "Output directory {} is not empty (use --force to overwrite)",
+                output.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns `true` for `<PlayerUId>.sav`, where the UID is 32 hex digits.
+/// Other saves in `Players` (e.g. `<PlayerUId>_dps.sav`) are not player saves.
+pub fn is_player_sav_name(path: &Path) -> bool {
+    let is_sav = path.extension().map(|ext| ext == "sav").unwrap_or(false);
+    let is_uid = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(|stem| stem.len() == 32 && stem.chars().all(|c| c.is_ascii_hexdigit()))
+        .unwrap_or(false);
+    is_sav && is_uid
+}
+
+/// Lists player saves in `players`, sorted by path. Subdirectories are searched when
+/// `recursive` is set. `.sav` files that are not named like a player save are skipped with a
+/// warning.
+pub fn list_player_sav_paths(players: &Path, recursive: bool) -> anyhow::Result<Vec<PathBuf>> {
+    let max_depth = if recursive { usize::MAX } else { 1 };
+    let mut player_sav_paths = Vec::new();
+    for entry in WalkDir::new(players).min_depth(1).max_depth(max_depth) {
+        let entry = entry.with_context(|| format!("Failed to read {}", players.display()))?;
+        let path = entry.into_path();
+        if !path.is_file() || path.extension().map(|ext| ext != "sav").unwrap_or(true) {
+            continue;
+        }
+        if !is_player_sav_name(&path) {
+            info!(
+                "Warning: {} does not look like a player save, skipping",
+                path.display()
+            );
+            continue;
+        }
+        player_sav_paths.push(path);
+    }
+    player_sav_paths.sort();
+    Ok(player_sav_paths)
+}
+
+#[test]
+pub fn test_list_player_sav_paths_recursive() {
+    use crate::fixtures;
+
+    let dir = fixtures::temp_dir("recursive_players");
+    let broken_players = fixtures::write_broken_save_directory(&dir, 2);
+    let players = dir.join("Players");
+    let nested_name = fixtures::player_sav_name(&broken_players[1].0);
+    std::fs::create_dir_all(players.join("shard0")).unwrap();
+    std::fs::rename(
+        players.join(&nested_name),
+        players.join("shard0").join(&nested_name),
+    )
+    .unwrap();
+    std::fs::write(
+        players.join("0123456789ABCDEF0123456789ABCDEF_dps.sav"),
+        b"",
+    )
+    .unwrap();
+    std::fs::write(players.join("shard0").join("notaplayer.sav"), b"").unwrap();
+
+    let top_level = list_player_sav_paths(&players, false).unwrap();
+    assert_eq!(
+        top_level,
+        vec![players.join(fixtures::player_sav_name(&broken_players[0].0))]
+    );
+
+    let recursive = list_player_sav_paths(&players, true).unwrap();
+    assert_eq!(recursive.len(), 2);
+    assert!(recursive.contains(&players.join("shard0").join(&nested_name)));
+}
@@ -0,0 +1,85 @@
+use std::path::Path;
+
+use anyhow::Context;
+use sha2::{Digest, Sha256};
+
+/// Lowercase hex SHA-256 of `bytes`.
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Writes the SHA-256 of `bytes` to `hash_path` as a `sha256sum` line naming `file_name`, so it
+/// can also be checked with `sha256sum -c`.
+pub fn write_hash_file(hash_path: &Path, file_name: &str, bytes: &[u8]) -> anyhow::Result<()> {
+    std::fs::write(hash_path, format!("{}  {}\n", sha256_hex(bytes), file_name))
+        .with_context(|| format!("Failed to write {}", hash_path.display()))
+}
+
+/// Reads the hash of a file written by [`write_hash_file`], or of a bare hex digest.
+pub fn read_hash_file(hash_path: &Path) -> anyhow::Result<String> {
+    let content = std::fs::read_to_string(hash_path)
+        .with_context(|| format!("Failed to read {}", hash_path.display()))?;
+    let hash = content.split_whitespace().next().unwrap_or_default();
+    if hash.len() != 64 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(anyhow::anyhow!(
+            "{} does not start with a SHA-256 hex digest",
+            hash_path.display()
+        ));
+    }
+    Ok(hash.to_ascii_lowercase())
+}
+
+/// Checks that the SHA-256 of the file at `path` is the one in `hash_path`, and returns it.
+pub fn verify_hash(path: &Path, hash_path: &Path) -> anyhow::Result<String> {
+    let expected = read_hash_file(hash_path)?;
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let actual = sha256_hex(&bytes);
+    if actual != expected {
+        return Err(anyhow::anyhow!(
+            "SHA-256 of {} is {}, but {} expects {}",
+            path.display(),
+            actual,
+            hash_path.display(),
+            expected
+        ));
+    }
+    Ok(actual)
+}
+
+#[test]
+pub fn test_hash_round_trip() {
+    use crate::fixtures;
+
+    assert_eq!(
+        sha256_hex(b"abc"),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+
+    let dir = fixtures::temp_dir("hash_round_trip");
+    let level_sav_path = dir.join("Level.sav");
+    let hash_path = dir.join("Level.sav.sha256");
+    let level_sav = std::fs::read("assets/Level.sav").unwrap();
+    std::fs::write(&level_sav_path, &level_sav).unwrap();
+    write_hash_file(&hash_path, "Level.sav", &level_sav).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&hash_path).unwrap(),
+        format!("{}  Level.sav\n", sha256_hex(&level_sav))
+    );
+    assert_eq!(
+        verify_hash(&level_sav_path, &hash_path).unwrap(),
+        sha256_hex(&level_sav)
+    );
+
+    // A single flipped byte is caught
+    let mut corrupted = level_sav.clone();
+    *corrupted.last_mut().unwrap() ^= 1;
+    std::fs::write(&level_sav_path, &corrupted).unwrap();
+    assert!(verify_hash(&level_sav_path, &hash_path).is_err());
+
+    std::fs::write(&hash_path, "not a hash\n").unwrap();
+    assert!(read_hash_file(&hash_path).is_err());
+}
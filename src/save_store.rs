@@ -0,0 +1,272 @@
+use std::{collections::BTreeMap, io::Cursor, path::PathBuf};
+
+use anyhow::Context;
+
+use crate::{
+    sav::{
+        check_world_save_data, read_save_file_path_with_extra_types,
+        read_save_file_with_extra_types, read_uncompressed_length, resolve_save_path,
+        write_save_file_path, write_save_file_with_compression, CompressionType, PalSave,
+    },
+    save_directory::list_player_sav_paths,
+};
+
+/// Where a save (Level.sav and the player saves) is read from and written to. The fix logic
+/// only goes through this, so it can run on a directory, in memory for tests, or on other
+/// storage such as cloud-hosted saves. Player saves are read from several threads at once.
+pub trait SaveStore: Sync {
+    fn read_level(&self, extra_types: &[(String, uesave::StructType)]) -> anyhow::Result<PalSave>;
+
+    /// Reads LevelMeta.sav, or returns `None` when the save has none.
+    fn read_level_meta(
+        &self,
+        extra_types: &[(String, uesave::StructType)],
+    ) -> anyhow::Result<Option<PalSave>>;
+
+    /// Returns the names of the player saves, which [`SaveStore::read_player`] accepts.
+    fn list_players(&self) -> anyhow::Result<Vec<String>>;
+
+    /// Returns the unparsed bytes of a player save, so it can be parsed elsewhere.
+    fn read_player_bytes(&self, name: &str) -> anyhow::Result<Vec<u8>>;
+
+    fn read_player(
+        &self,
+        name: &str,
+        extra_types: &[(String, uesave::StructType)],
+    ) -> anyhow::Result<PalSave> {
+        read_save_file_with_extra_types(Cursor::new(self.read_player_bytes(name)?), extra_types)
+    }
+
+    /// Returns the length of the GVAS data of the current Level.sav, which a write is compared
+    /// against before overwriting it.
+    fn level_uncompressed_length(&self) -> anyhow::Result<usize>;
+
+    fn write_level(
+        &mut self,
+        pal_save: &PalSave,
+        compression_type: CompressionType,
+    ) -> anyhow::Result<()>;
+
+    /// Returns the .sav bytes of the current Level.sav, e.g. to hash what was just written.
+    fn read_level_bytes(&self) -> anyhow::Result<Vec<u8>>;
+
+    /// Keeps a copy of the current Level.sav before it is overwritten.
+    fn backup_level(&mut self) -> anyhow::Result<()>;
+}
+
+/// A save directory with `Level.sav` and `Players/*.sav`.
+#[derive(Debug)]
+pub struct FsSaveStore {
+    pub dir: PathBuf,
+    /// Also look for player saves in subdirectories of `Players`
+    pub recursive_players: bool,
+}
+
+impl FsSaveStore {
+    fn level_sav_path(&self) -> PathBuf {
+        self.dir.join("Level.sav")
+    }
+
+    fn level_meta_sav_path(&self) -> PathBuf {
+        self.dir.join("LevelMeta.sav")
+    }
+}
+
+impl SaveStore for FsSaveStore {
+    fn read_level(&self, extra_types: &[(String, uesave::StructType)]) -> anyhow::Result<PalSave> {
+        let level_save = read_save_file_path_with_extra_types(&self.level_sav_path(), extra_types)?;
+        check_world_save_data(&level_save)?;
+        Ok(level_save)
+    }
+
+    fn read_level_meta(
+        &self,
+        extra_types: &[(String, uesave::StructType)],
+    ) -> anyhow::Result<Option<PalSave>> {
+        let level_meta_sav_path = self.level_meta_sav_path();
+        if !level_meta_sav_path.exists() {
+            return Ok(None);
+        }
+        read_save_file_path_with_extra_types(&level_meta_sav_path, extra_types).map(Some)
+    }
+
+    fn list_players(&self) -> anyhow::Result<Vec<String>> {
+        let players = self.dir.join("Players");
+        list_player_sav_paths(&players, self.recursive_players)?
+            .iter()
+            .map(|path| -> anyhow::Result<String> {
+                let name = path
+                    .strip_prefix(&players)?
+                    .to_str()
+                    .with_context(|| format!("{} is not a valid UTF-8 path", path.display()))?;
+                Ok(name.to_string())
+            })
+            .collect()
+    }
+
+    fn read_player_bytes(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        let path = self.dir.join("Players").join(name);
+        std::fs::read(&path).with_context(|| format!("Failed to open {}", path.display()))
+    }
+
+    fn read_player(
+        &self,
+        name: &str,
+        extra_types: &[(String, uesave::StructType)],
+    ) -> anyhow::Result<PalSave> {
+        read_save_file_path_with_extra_types(&self.dir.join("Players").join(name), extra_types)
+    }
+
+    fn level_uncompressed_length(&self) -> anyhow::Result<usize> {
+        let level_sav_path = self.level_sav_path();
+        let file = std::fs::File::open(&level_sav_path)
+            .with_context(|| format!("Failed to open {}", level_sav_path.display()))?;
+        read_uncompressed_length(file)
+            .with_context(|| format!("Failed to read {}", level_sav_path.display()))
+    }
+
+    fn write_level(
+        &mut self,
+        pal_save: &PalSave,
+        compression_type: CompressionType,
+    ) -> anyhow::Result<()> {
+        write_save_file_path(&self.level_sav_path(), pal_save, compression_type)
+    }
+
+    fn read_level_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        let level_sav_path = self.level_sav_path();
+        std::fs::read(&level_sav_path)
+            .with_context(|| format!("Failed to open {}", level_sav_path.display()))
+    }
+
+    fn backup_level(&mut self) -> anyhow::Result<()> {
+        // Beside the Level.sav that is written, when the one in `dir` is a link to it
+        let level_sav_path = resolve_save_path(&self.level_sav_path())?;
+        let backup_path = level_sav_path.with_extension("sav.bak");
+        std::fs::copy(&level_sav_path, &backup_path)
+            .with_context(|| format!("Failed to copy {}", level_sav_path.display()))?;
+        info!("Level.sav backed up to {}", backup_path.display());
+        Ok(())
+    }
+}
+
+/// A save kept in memory as .sav bytes, as read from a .tar.gz backup or built by tests.
+#[derive(Debug, Default, Clone)]
+pub struct MemSaveStore {
+    pub level: Vec<u8>,
+    pub level_meta: Option<Vec<u8>>,
+    /// Player saves by name
+    pub players: BTreeMap<String, Vec<u8>>,
+    pub level_backup: Option<Vec<u8>>,
+}
+
+#[cfg(test)]
+impl MemSaveStore {
+    /// Serializes `level_save` and `player_saves` into a new store.
+    pub fn new(level_save: &PalSave, player_saves: &[(String, PalSave)]) -> anyhow::Result<Self> {
+        let write = |pal_save: &PalSave| -> anyhow::Result<Vec<u8>> {
+            let mut bytes = Vec::new();
+            write_save_file_with_compression(&mut bytes, pal_save, pal_save.compression_type)?;
+            Ok(bytes)
+        };
+        Ok(MemSaveStore {
+            level: write(level_save)?,
+            level_meta: None,
+            players: player_saves
+                .iter()
+                .map(|(name, pal_save)| -> anyhow::Result<(String, Vec<u8>)> {
+                    Ok((name.clone(), write(pal_save)?))
+                })
+                .collect::<anyhow::Result<_>>()?,
+            level_backup: None,
+        })
+    }
+}
+
+impl SaveStore for MemSaveStore {
+    fn read_level(&self, extra_types: &[(String, uesave::StructType)]) -> anyhow::Result<PalSave> {
+        let level_save = read_save_file_with_extra_types(Cursor::new(&self.level), extra_types)?;
+        check_world_save_data(&level_save)?;
+        Ok(level_save)
+    }
+
+    fn read_level_meta(
+        &self,
+        extra_types: &[(String, uesave::StructType)],
+    ) -> anyhow::Result<Option<PalSave>> {
+        self.level_meta
+            .as_ref()
+            .map(|bytes| read_save_file_with_extra_types(Cursor::new(bytes), extra_types))
+            .transpose()
+    }
+
+    fn list_players(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.players.keys().cloned().collect())
+    }
+
+    fn read_player_bytes(&self, name: &str) -> anyhow::Result<Vec<u8>> {
+        self.players
+            .get(name)
+            .cloned()
+            .with_context(|| format!("No player save {}", name))
+    }
+
+    fn level_uncompressed_length(&self) -> anyhow::Result<usize> {
+        read_uncompressed_length(Cursor::new(&self.level))
+    }
+
+    fn write_level(
+        &mut self,
+        pal_save: &PalSave,
+        compression_type: CompressionType,
+    ) -> anyhow::Result<()> {
+        let mut level = Vec::new();
+        write_save_file_with_compression(&mut level, pal_save, compression_type)?;
+        self.level = level;
+        Ok(())
+    }
+
+    fn read_level_bytes(&self) -> anyhow::Result<Vec<u8>> {
+        Ok(self.level.clone())
+    }
+
+    fn backup_level(&mut self) -> anyhow::Result<()> {
+        self.level_backup = Some(self.level.clone());
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+#[test]
+pub fn test_fs_save_store_writes_through_symlinked_level_sav() {
+    use crate::fixtures;
+
+    let dir = fixtures::temp_dir("symlinked_level_sav");
+    std::fs::create_dir_all(dir.join("real")).unwrap();
+    std::fs::create_dir_all(dir.join("link")).unwrap();
+    std::fs::copy("assets/Level.sav", dir.join("real/Level.sav")).unwrap();
+    std::os::unix::fs::symlink("../real/Level.sav", dir.join("link/Level.sav")).unwrap();
+    let mut store = FsSaveStore {
+        dir: dir.join("link"),
+        recursive_players: false,
+    };
+
+    store.backup_level().unwrap();
+    assert!(dir.join("real/Level.sav.bak").exists());
+    assert!(!dir.join("link/Level.sav.bak").exists());
+
+    let level_save = store.read_level(&[]).unwrap();
+    store
+        .write_level(&level_save, level_save.compression_type)
+        .unwrap();
+    assert!(std::fs::symlink_metadata(dir.join("link/Level.sav"))
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(
+        crate::sav::read_save_file_path(&dir.join("real/Level.sav")).unwrap(),
+        level_save
+    );
+    // The temporary file is renamed onto the real Level.sav, not left beside the link
+    assert_eq!(std::fs::read_dir(dir.join("link")).unwrap().count(), 1);
+}
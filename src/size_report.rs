@@ -0,0 +1,148 @@
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    color::Colors,
+    sav::{get_character_key_ids, get_map_entries, get_raw_data, PalSave},
+};
+
+/// Default number of the largest `RawData` blobs to report.
+pub const DEFAULT_TOP: usize = 10;
+
+#[derive(Debug, Serialize)]
+pub struct RawDataSize {
+    /// `Player` or `Pal` for characters, the group type (e.g. `Guild`) for groups
+    pub kind: String,
+    /// InstanceId of a character or id of a group
+    pub id: Uuid,
+    pub size: usize,
+}
+
+/// How many bytes of Level.sav the `RawData` of characters and groups take.
+#[derive(Debug, Serialize)]
+pub struct SizeReport {
+    pub character_count: usize,
+    pub character_raw_data_size: usize,
+    pub group_count: usize,
+    pub group_raw_data_size: usize,
+    /// The largest blobs, largest first
+    pub largest: Vec<RawDataSize>,
+}
+
+/// Sums the `RawData` lengths of `CharacterSaveParameterMap` and `GroupSaveDataMap` without
+/// parsing them, and keeps the `top` largest.
+pub fn compute_size_report(pal_save: &PalSave, top: usize) -> anyhow::Result<SizeReport> {
+    let characters = get_map_entries(pal_save, "CharacterSaveParameterMap")
+        .iter()
+        .map(|entry| -> anyhow::Result<RawDataSize> {
+            let (player_uid, instance_id) = get_character_key_ids(entry).unwrap_or_default();
+            Ok(RawDataSize {
+                kind: if player_uid.is_nil() { "Pal" } else { "Player" }.into(),
+                id: instance_id,
+                size: get_raw_data(entry)?.len(),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let groups = get_map_entries(pal_save, "GroupSaveDataMap")
+        .iter()
+        .map(|entry| -> anyhow::Result<RawDataSize> {
+            let id = match entry.key {
+                uesave::PropertyValue::Struct(uesave::StructValue::Guid(id)) => id,
+                _ => Uuid::nil(),
+            };
+            let kind = match &entry.value {
+                uesave::PropertyValue::Struct(uesave::StructValue::Struct(value)) => {
+                    match value.get("GroupType") {
+                        Some(uesave::Property::Enum { value, .. }) => value
+                            .strip_prefix("EPalGroupType::")
+                            .unwrap_or(value)
+                            .to_string(),
+                        _ => "Unknown".into(),
+                    }
+                }
+                _ => "Unknown".into(),
+            };
+            Ok(RawDataSize {
+                kind,
+                id,
+                size: get_raw_data(entry)?.len(),
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let character_count = characters.len();
+    let character_raw_data_size = characters.iter().map(|blob| blob.size).sum();
+    let group_count = groups.len();
+    let group_raw_data_size = groups.iter().map(|blob| blob.size).sum();
+    let mut largest: Vec<RawDataSize> = characters.into_iter().chain(groups).collect();
+    // Stable, so blobs of the same size stay in map order
+    largest.sort_by(|a, b| b.size.cmp(&a.size));
+    largest.truncate(top);
+
+    Ok(SizeReport {
+        character_count,
+        character_raw_data_size,
+        group_count,
+        group_raw_data_size,
+        largest,
+    })
+}
+
+pub fn print_size_report(report: &SizeReport, colors: Colors) {
+    println!(
+        "Character RawData: {} bytes in {} characters",
+        report.character_raw_data_size, report.character_count
+    );
+    println!(
+        "Group RawData: {} bytes in {} groups",
+        report.group_raw_data_size, report.group_count
+    );
+    println!("{}", colors.heading("Largest RawData:"));
+    println!(
+        "{}",
+        colors.heading(format!("{:>10}  {:<12}  Id", "Bytes", "Kind"))
+    );
+    report.largest.iter().for_each(|blob| {
+        println!("{:>10}  {:<12}  {}", blob.size, blob.kind, blob.id);
+    });
+}
+
+#[test]
+pub fn test_compute_size_report() {
+    use crate::fixtures;
+
+    let mut level_save = fixtures::read_level_save();
+    let report = compute_size_report(&level_save, DEFAULT_TOP).unwrap();
+    assert_eq!(report.character_count, 0);
+    assert_eq!(report.character_raw_data_size, 0);
+    assert_eq!(report.group_count, 5);
+    assert_eq!(report.group_raw_data_size, 140);
+    assert_eq!(
+        report
+            .largest
+            .iter()
+            .map(|blob| (blob.kind.as_str(), blob.size))
+            .collect::<Vec<_>>(),
+        vec![
+            ("Organization", 29),
+            ("Organization", 29),
+            ("Organization", 29),
+            ("Organization", 29),
+            ("Neutral", 24),
+        ]
+    );
+
+    fixtures::add_guild_raw(&mut level_save, Uuid::from_u128(0x100), vec![0; 1000]);
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    fixtures::add_character_save(&mut level_save, Uuid::from_u128(1), Uuid::from_u128(2));
+    let report = compute_size_report(&level_save, 2).unwrap();
+    assert_eq!(report.character_count, 1);
+    assert!(report.character_raw_data_size > 0);
+    assert_eq!(report.group_count, 6);
+    assert_eq!(report.group_raw_data_size, 1140);
+    assert_eq!(report.largest.len(), 2);
+    assert_eq!(report.largest[0].kind, "Player");
+    assert_eq!(report.largest[0].id, Uuid::from_u128(2));
+    assert_eq!(report.largest[1].kind, "Guild");
+    assert_eq!(report.largest[1].size, 1000);
+}
@@ -0,0 +1,294 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    color::Colors,
+    group_guild::GroupGuildSave,
+    sav::{iter_character_entries, PalSave},
+};
+
+/// Default maximum number of members of a guild in game
+pub const DEFAULT_MEMBER_CAP: usize = 20;
+
+/// Percentage of guild members by which the number of player characters may differ before the
+/// difference is reported as a large discrepancy
+pub const CHARACTER_COUNT_TOLERANCE_PERCENT: usize = 10;
+
+#[derive(Debug, Serialize)]
+pub struct GuildSummary {
+    pub guild_id: Uuid,
+    pub name: String,
+    pub member_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GuildStats {
+    pub guild_count: usize,
+    /// Number of guilds by member count
+    pub member_count_histogram: BTreeMap<usize, usize>,
+    pub average_base_camp_level: f64,
+    pub largest_guild: Option<GuildSummary>,
+    pub smallest_guild: Option<GuildSummary>,
+    pub distinct_player_count: usize,
+    pub member_cap: usize,
+    /// Guilds with more members than `member_cap`, which the game cannot produce
+    pub guilds_over_member_cap: Vec<GuildSummary>,
+    /// Entries of `CharacterSaveParameterMap` with a `PlayerUId`, one per player in a healthy save
+    pub player_character_count: usize,
+    /// Whether `player_character_count` differs from `distinct_player_count` by more than
+    /// [`CHARACTER_COUNT_TOLERANCE_PERCENT`], which hints at widespread corruption rather than a
+    /// few broken players
+    pub character_count_discrepancy: bool,
+}
+
+impl GuildStats {
+    /// Number of findings printed as warnings: guilds over the member cap and a character count
+    /// discrepancy.
+    pub fn warning_count(&self) -> usize {
+        self.guilds_over_member_cap.len() + usize::from(self.character_count_discrepancy)
+    }
+}
+
+/// Counts the entries of `CharacterSaveParameterMap` whose key has a `PlayerUId`. Pals and
+/// entries whose key cannot be decoded are not counted.
+pub fn count_player_characters(level_save: &PalSave) -> usize {
+    iter_character_entries(level_save)
+        .filter(|entry| matches!(entry, Ok((Some(_), _, _))))
+        .count()
+}
+
+pub fn compute_guild_stats(
+    groups: &[(Uuid, GroupGuildSave)],
+    player_character_count: usize,
+    member_cap: usize,
+) -> GuildStats {
+    let summary = |(guild_id, group): &(Uuid, GroupGuildSave)| GuildSummary {
+        guild_id: *guild_id,
+        name: group.GuildName.clone(),
+        member_count: group.GuildPlayerInfo.len(),
+    };
+
+    let mut member_count_histogram = BTreeMap::new();
+    groups.iter().for_each(|(_, group)| {
+        *member_count_histogram
+            .entry(group.GuildPlayerInfo.len())
+            .or_insert(0) += 1;
+    });
+    let average_base_camp_level = if groups.is_empty() {
+        0.0
+    } else {
+        groups
+            .iter()
+            .map(|(_, group)| group.BaseCampLevel as f64)
+            .sum::<f64>()
+            / groups.len() as f64
+    };
+    let distinct_players: HashSet<Uuid> = groups
+        .iter()
+        .flat_map(|(_, group)| {
+            group
+                .GuildPlayerInfo
+                .iter()
+                .map(|player_info| player_info.PlayerUId)
+        })
+        .collect();
+
+    GuildStats {
+        guild_count: groups.len(),
+        member_count_histogram,
+        average_base_camp_level,
+        largest_guild: groups
+            .iter()
+            .max_by_key(|(_, group)| group.GuildPlayerInfo.len())
+            .map(summary),
+        smallest_guild: groups
+            .iter()
+            .min_by_key(|(_, group)| group.GuildPlayerInfo.len())
+            .map(summary),
+        distinct_player_count: distinct_players.len(),
+        member_cap,
+        player_character_count,
+        character_count_discrepancy: player_character_count.abs_diff(distinct_players.len()) * 100
+            > distinct_players.len() * CHARACTER_COUNT_TOLERANCE_PERCENT,
+        guilds_over_member_cap: groups
+            .iter()
+            .filter(|(_, group)| group.GuildPlayerInfo.len() > member_cap)
+            .map(summary)
+            .collect(),
+    }
+}
+
+pub fn print_guild_stats(stats: &GuildStats, colors: Colors) {
+    println!("Guilds: {}", stats.guild_count);
+    println!("Distinct players: {}", stats.distinct_player_count);
+    let character_count = format!(
+        "Player characters: {} (expected {} from guild members)",
+        stats.player_character_count, stats.distinct_player_count
+    );
+    if stats.character_count_discrepancy {
+        println!("{}", colors.warning(character_count));
+        println!(
+            "- The counts differ by more than {}%; many players may be missing their character \
+             or the save may be corrupted",
+            CHARACTER_COUNT_TOLERANCE_PERCENT
+        );
+    } else {
+        println!("{}", character_count);
+    }
+    println!(
+        "Average base camp level: {:.2}",
+        stats.average_base_camp_level
+    );
+    if let Some(guild) = &stats.largest_guild {
+        println!(
+            "Largest guild: {}({}) with {} members",
+            guild.name, guild.guild_id, guild.member_count
+        );
+    }
+    if let Some(guild) = &stats.smallest_guild {
+        println!(
+            "Smallest guild: {}({}) with {} members",
+            guild.name, guild.guild_id, guild.member_count
+        );
+    }
+    println!("{}", colors.heading("Member count distribution:"));
+    stats
+        .member_count_histogram
+        .iter()
+        .for_each(|(member_count, guild_count)| {
+            println!("- {} members: {} guilds", member_count, guild_count);
+        });
+    if !stats.guilds_over_member_cap.is_empty() {
+        println!(
+            "{}",
+            colors.warning(format!(
+                "Guilds over the member cap of {}: {}",
+                stats.member_cap,
+                stats.guilds_over_member_cap.len()
+            ))
+        );
+        stats.guilds_over_member_cap.iter().for_each(|guild| {
+            println!(
+                "- {}({}) has {} members, {} over the cap",
+                guild.name,
+                guild.guild_id,
+                guild.member_count,
+                guild.member_count - stats.member_cap
+            );
+        });
+    }
+}
+
+#[test]
+pub fn test_compute_guild_stats() {
+    use crate::{fixtures, group_guild::GameVersion, sav::get_guilds};
+
+    let mut level_save = fixtures::read_level_save();
+    let stats = compute_guild_stats(
+        &get_guilds(&level_save, GameVersion::V0_1_3).unwrap(),
+        count_player_characters(&level_save),
+        DEFAULT_MEMBER_CAP,
+    );
+    assert_eq!(stats.guild_count, 0);
+    assert!(stats.member_count_histogram.is_empty());
+    assert!(stats.largest_guild.is_none());
+
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    let mut small_guild = group_guild_save.clone();
+    small_guild.GuildPlayerInfo.truncate(1);
+    small_guild.BaseCampLevel = 1;
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x101), &small_guild);
+
+    let stats = compute_guild_stats(
+        &get_guilds(&level_save, GameVersion::V0_1_3).unwrap(),
+        count_player_characters(&level_save),
+        DEFAULT_MEMBER_CAP,
+    );
+    assert_eq!(stats.guild_count, 2);
+    assert_eq!(
+        stats.member_count_histogram,
+        BTreeMap::from([(1, 1), (5, 1)])
+    );
+    assert_eq!(stats.average_base_camp_level, 8.0);
+    assert_eq!(
+        stats.largest_guild.unwrap().guild_id,
+        Uuid::from_u128(0x100)
+    );
+    assert_eq!(
+        stats.smallest_guild.unwrap().guild_id,
+        Uuid::from_u128(0x101)
+    );
+    assert_eq!(stats.distinct_player_count, 5);
+}
+
+#[test]
+pub fn test_compute_guild_stats_reports_guilds_over_member_cap() {
+    use crate::{fixtures, group_guild::GameVersion, sav::get_guilds};
+
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    let mut small_guild = group_guild_save.clone();
+    small_guild.GuildPlayerInfo.truncate(3);
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x101), &small_guild);
+
+    let groups = get_guilds(&level_save, GameVersion::V0_1_3).unwrap();
+    assert!(compute_guild_stats(&groups, 5, DEFAULT_MEMBER_CAP)
+        .guilds_over_member_cap
+        .is_empty());
+
+    let stats = compute_guild_stats(&groups, 5, 3);
+    assert_eq!(stats.member_cap, 3);
+    assert_eq!(stats.guilds_over_member_cap.len(), 1);
+    assert_eq!(
+        stats.guilds_over_member_cap[0].guild_id,
+        Uuid::from_u128(0x100)
+    );
+    assert_eq!(stats.guilds_over_member_cap[0].member_count, 5);
+}
+
+#[test]
+pub fn test_compute_guild_stats_reports_character_count_discrepancy() {
+    use crate::{fixtures, group_guild::GameVersion, sav::get_guilds};
+
+    let mut level_save = fixtures::read_level_save();
+    let group_guild_save = fixtures::read_guild();
+    fixtures::add_guild(&mut level_save, Uuid::from_u128(0x100), &group_guild_save);
+    fixtures::add_character_save_parameter_map(&mut level_save);
+    let groups = get_guilds(&level_save, GameVersion::V0_1_3).unwrap();
+
+    // Only one of the five members has a character, plus a pal that is not counted
+    let player_uid = group_guild_save.GuildPlayerInfo[0].PlayerUId;
+    fixtures::add_character_save(&mut level_save, player_uid, Uuid::from_u128(1));
+    fixtures::add_character_save(&mut level_save, Uuid::nil(), Uuid::from_u128(2));
+    let stats = compute_guild_stats(
+        &groups,
+        count_player_characters(&level_save),
+        DEFAULT_MEMBER_CAP,
+    );
+    assert_eq!(stats.player_character_count, 1);
+    assert_eq!(stats.distinct_player_count, 5);
+    assert!(stats.character_count_discrepancy);
+
+    // Every member has a character
+    group_guild_save.GuildPlayerInfo[1..]
+        .iter()
+        .enumerate()
+        .for_each(|(index, player_info)| {
+            fixtures::add_character_save(
+                &mut level_save,
+                player_info.PlayerUId,
+                Uuid::from_u128(0x10 + index as u128),
+            );
+        });
+    let stats = compute_guild_stats(
+        &groups,
+        count_player_characters(&level_save),
+        DEFAULT_MEMBER_CAP,
+    );
+    assert_eq!(stats.player_character_count, 5);
+    assert!(!stats.character_count_discrepancy);
+}
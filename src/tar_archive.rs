@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use flate2::read::GzDecoder;
+
+use crate::save_store::MemSaveStore;
+
+/// Whether `path` names a gzipped tar archive, by its extension.
+pub fn is_tar_gz(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    name.ends_with(".tar.gz") || name.ends_with(".tgz")
+}
+
+/// Reads the save in a `.tar.gz` backup into memory without extracting it: its `Level.sav`, and
+/// the `LevelMeta.sav` and `Players/*.sav` beside it. The save can be at any depth in the
+/// archive, e.g. under `SaveGames/0/<world>/`, but there must be only one `Level.sav`.
+pub fn read_tar_gz_save_store(path: &Path) -> anyhow::Result<MemSaveStore> {
+    let file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(BufReader::new(file)));
+    // Entries can only be read in order, so every .sav is kept until the Level.sav is found
+    let mut savs: BTreeMap<PathBuf, Vec<u8>> = BTreeMap::new();
+    let entries = archive
+        .entries()
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    for entry in entries {
+        let mut entry = entry.with_context(|| format!("Failed to read {}", path.display()))?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let entry_path = entry
+            .path()
+            .with_context(|| format!("Failed to read {}", path.display()))?
+            .into_owned();
+        if entry_path
+            .extension()
+            .map_or(true, |extension| extension != "sav")
+        {
+            continue;
+        }
+        let mut bytes = Vec::new();
+        entry.read_to_end(&mut bytes).with_context(|| {
+            format!(
+                "Failed to read {} in {}",
+                entry_path.display(),
+                path.display()
+            )
+        })?;
+        savs.insert(entry_path, bytes);
+    }
+
+    let level_sav_paths: Vec<PathBuf> = savs
+        .keys()
+        .filter(|sav_path| sav_path.file_name() == Some("Level.sav".as_ref()))
+        .cloned()
+        .collect();
+    let level_sav_path = match level_sav_paths.as_slice() {
+        [] => anyhow::bail!("{} has no Level.sav", path.display()),
+        [level_sav_path] => level_sav_path.clone(),
+        _ => anyhow::bail!(
+            "{} has {} Level.sav files, extract the world to read:\n{}",
+            path.display(),
+            level_sav_paths.len(),
+            level_sav_paths
+                .iter()
+                .map(|level_sav_path| format!("- {}", level_sav_path.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ),
+    };
+    let save_dir = level_sav_path.parent().unwrap_or(Path::new(""));
+    let players_dir = save_dir.join("Players");
+    let level_meta = savs.remove(&save_dir.join("LevelMeta.sav"));
+    let level = savs.remove(&level_sav_path).unwrap();
+    let players = savs
+        .into_iter()
+        .filter(|(sav_path, _)| sav_path.parent() == Some(players_dir.as_path()))
+        .map(|(sav_path, bytes)| {
+            let name = sav_path.file_name().unwrap().to_string_lossy().into_owned();
+            (name, bytes)
+        })
+        .collect();
+    Ok(MemSaveStore {
+        level,
+        level_meta,
+        players,
+        level_backup: None,
+    })
+}
+
+#[test]
+pub fn test_read_tar_gz_save_store() {
+    use flate2::{write::GzEncoder, Compression};
+
+    use crate::{
+        fixtures,
+        sav::{write_save_file, PalSave},
+        save_store::SaveStore,
+    };
+
+    let (level_save, player_saves, _) = fixtures::broken_save(1);
+    let (player_uid, player_save) = &player_saves[0];
+    let sav_bytes = |pal_save: &PalSave| {
+        let mut bytes = Vec::new();
+        write_save_file(&mut bytes, pal_save).unwrap();
+        bytes
+    };
+    let write_archive = |path: &Path, files: &[(String, Vec<u8>)]| {
+        let file = std::fs::File::create(path).unwrap();
+        let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+        files.iter().for_each(|(name, bytes)| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(bytes.len() as u64);
+            header.set_mode(0o644);
+            builder
+                .append_data(&mut header, name, bytes.as_slice())
+                .unwrap();
+        });
+        builder.into_inner().unwrap().finish().unwrap();
+    };
+    let dir = fixtures::temp_dir("tar_archive");
+    let player_sav_name = fixtures::player_sav_name(player_uid);
+    let archive_path = dir.join("backup.tar.gz");
+    write_archive(
+        &archive_path,
+        &[
+            ("backup/world/Level.sav".into(), sav_bytes(&level_save)),
+            (
+                format!("backup/world/Players/{}", player_sav_name),
+                sav_bytes(player_save),
+            ),
+            (
+                "backup/world/WorldOption.txt".into(),
+                b"not a save".to_vec(),
+            ),
+        ],
+    );
+    assert!(is_tar_gz(&archive_path));
+
+    let store = read_tar_gz_save_store(&archive_path).unwrap();
+    assert_eq!(store.read_level(&[]).unwrap(), level_save);
+    assert!(store.read_level_meta(&[]).unwrap().is_none());
+    assert_eq!(store.list_players().unwrap(), vec![player_sav_name.clone()]);
+    assert_eq!(
+        &store.read_player(&player_sav_name, &[]).unwrap(),
+        player_save
+    );
+
+    // Two worlds cannot be told apart
+    let two_worlds_path = dir.join("two_worlds.tgz");
+    write_archive(
+        &two_worlds_path,
+        &[
+            ("a/Level.sav".into(), sav_bytes(&level_save)),
+            ("b/Level.sav".into(), sav_bytes(&level_save)),
+        ],
+    );
+    let error = read_tar_gz_save_store(&two_worlds_path)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("2 Level.sav files"), "{}", error);
+}
@@ -0,0 +1,428 @@
+use std::{collections::HashMap, fmt};
+
+use indexmap::IndexMap;
+use uuid::Uuid;
+
+use crate::character_save_parameter::CharacterSaveParameter;
+
+/// The character save template shipped with the tool.
+pub const DEFAULT_TEMPLATE: &str =
+    include_str!("../templates/PalIndividualCharacterSaveParameter.json");
+
+#[derive(Debug)]
+pub enum TemplateError {
+    /// The template is not valid uesave property JSON. `excerpt` is the line where parsing
+    /// stopped, when it is known
+    Parse {
+        error: serde_json::Error,
+        excerpt: Option<String>,
+    },
+    /// The template has no `SaveParameter` property
+    MissingSaveParameter,
+    /// `SaveParameter` is not a struct of properties. `shape` is what it is instead, e.g. `Str`
+    BadSaveParameterShape { shape: String },
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TemplateError::Parse { error, excerpt } => {
+                let location = format!(" at line {} column {}", error.line(), error.column());
+                let message = error.to_string();
+                match excerpt {
+                    Some(excerpt) => write!(
+                        f,
+                        "Failed to parse template at line {}, column {}: {}\n{}\n{}^",
+                        error.line(),
+                        error.column(),
+                        message.strip_suffix(&location).unwrap_or(&message),
+                        excerpt,
+                        " ".repeat(error.column().saturating_sub(1))
+                    ),
+                    None => write!(f, "Failed to parse template: {}", error),
+                }
+            }
+            TemplateError::MissingSaveParameter => {
+                write!(f, "Template has no SaveParameter property")
+            }
+            TemplateError::BadSaveParameterShape { shape } => write!(
+                f,
+                "Template SaveParameter must be a Struct property of properties, but it is {}",
+                shape
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TemplateError::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// A validated character save template, whose `SaveParameter` is a struct.
+#[derive(Debug, Clone)]
+pub struct Template {
+    properties: IndexMap<String, uesave::Property>,
+}
+
+/// Parses a template: a JSON object of uesave properties with a `SaveParameter` struct, like
+/// [`DEFAULT_TEMPLATE`]. A leading UTF-8 BOM, which some editors save, is ignored.
+pub fn load_template(source: &str) -> Result<Template, TemplateError> {
+    let source = source.strip_prefix('\u{feff}').unwrap_or(source);
+    let properties: IndexMap<String, uesave::Property> =
+        serde_json::from_str(source).map_err(|error| TemplateError::Parse {
+            excerpt: (error.line() > 0)
+                .then(|| source.lines().nth(error.line() - 1))
+                .flatten()
+                .map(|line| line.to_string()),
+            error,
+        })?;
+    match properties.get("SaveParameter") {
+        Some(uesave::Property::Struct {
+            value: uesave::StructValue::Struct(_),
+            ..
+        }) => Ok(Template { properties }),
+        Some(property) => Err(TemplateError::BadSaveParameterShape {
+            shape: describe_property_shape(property),
+        }),
+        None => Err(TemplateError::MissingSaveParameter),
+    }
+}
+
+/// Names the kind of `property` by its uesave JSON tag, e.g. `a Str property`, or `a Struct
+/// property holding a Guid` for a struct that is not a struct of properties.
+fn describe_property_shape(property: &uesave::Property) -> String {
+    let tag = |value: serde_json::Result<serde_json::Value>| {
+        value
+            .ok()
+            .as_ref()
+            .and_then(|value| value.as_object())
+            .and_then(|object| object.keys().next().cloned())
+            .unwrap_or_else(|| "unknown".to_string())
+    };
+    match property {
+        uesave::Property::Struct { value, .. } => format!(
+            "a Struct property holding a {}",
+            tag(serde_json::to_value(value))
+        ),
+        _ => format!("a {} property", tag(serde_json::to_value(property))),
+    }
+}
+
+impl Template {
+    /// Creates a character save for a player named `nickname` in the guild `group_id`.
+    pub fn create_character_save(&self, nickname: &str, group_id: Uuid) -> CharacterSaveParameter {
+        let mut character_save = self.properties.clone();
+        let Some(uesave::Property::Struct {
+            value: uesave::StructValue::Struct(properties),
+            ..
+        }) = character_save.get_mut("SaveParameter")
+        else {
+            unreachable!("checked by load_template")
+        };
+        properties.insert(
+            "NickName".into(),
+            uesave::Property::Str {
+                id: None,
+                value: nickname.into(),
+            },
+        );
+        CharacterSaveParameter {
+            properties: character_save,
+            group_id,
+        }
+    }
+
+    /// Like [`Template::create_character_save`], but starts from the `SaveParameter` of
+    /// `sibling`, a player character of the same save, so fields the template lacks but the
+    /// current game version writes are kept. The fields of the template replace those of
+    /// `sibling`, and its [`SIBLING_IDENTITY_FIELDS`] are removed.
+    pub fn create_character_save_from_sibling(
+        &self,
+        nickname: &str,
+        group_id: Uuid,
+        sibling: &CharacterSaveParameter,
+    ) -> CharacterSaveParameter {
+        let mut character_save = self.create_character_save(nickname, group_id);
+        let Some(uesave::Property::Struct {
+            value: uesave::StructValue::Struct(sibling_properties),
+            ..
+        }) = sibling.properties.get("SaveParameter")
+        else {
+            return character_save;
+        };
+        let Some(uesave::Property::Struct {
+            value: uesave::StructValue::Struct(properties),
+            ..
+        }) = character_save.properties.get_mut("SaveParameter")
+        else {
+            unreachable!("checked by load_template")
+        };
+        // In the order of the sibling, which is the order of the game
+        let mut seeded = sibling_properties.clone();
+        seeded.retain(|name, _| !SIBLING_IDENTITY_FIELDS.contains(&name.as_str()));
+        properties.iter().for_each(|(name, property)| {
+            seeded.insert(name.clone(), property.clone());
+        });
+        *properties = seeded;
+        character_save
+    }
+}
+
+/// `SaveParameter` fields that identify a character or the player and containers it belongs
+/// to. A character seeded from a sibling must not share them with it.
+pub const SIBLING_IDENTITY_FIELDS: &[&str] = &[
+    "NickName",
+    "OwnerPlayerUId",
+    "OldOwnerPlayerUIds",
+    "LastNickNameModifierPlayerUid",
+    "ItemContainerId",
+    "EquipItemContainerId",
+    "SlotID",
+];
+
+/// Where the name of a new character came from, in order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NicknameSource {
+    /// `HostPlayerName` in LevelMeta.sav
+    LevelMeta,
+    /// `PlayerName` in the guild
+    Guild,
+    /// `DebugName` in the key of another character of the player
+    DebugName,
+    /// Made up from the PlayerUId, when no name was found
+    PlayerUId,
+}
+
+/// Picks the name of the new character of `player_uid` from the first of these that is not empty:
+/// its LevelMeta.sav name, its guild name and the `DebugName` of another character key of theirs.
+/// Without any, it is `Player` and the first group of the PlayerUId, as in
+/// [`disambiguate_nicknames`].
+pub fn pick_nickname(
+    player_uid: Uuid,
+    level_meta_name: Option<&str>,
+    guild_name: &str,
+    debug_name: Option<&str>,
+) -> (String, NicknameSource) {
+    [
+        (level_meta_name, NicknameSource::LevelMeta),
+        (Some(guild_name), NicknameSource::Guild),
+        (debug_name, NicknameSource::DebugName),
+    ]
+    .into_iter()
+    .find_map(|(name, source)| {
+        name.filter(|name| !name.is_empty())
+            .map(|name| (name.to_string(), source))
+    })
+    .unwrap_or_else(|| {
+        (
+            format!("Player ({})", &player_uid.simple().to_string()[..8]),
+            NicknameSource::PlayerUId,
+        )
+    })
+}
+
+/// Appends the first group of the PlayerUId to every nickname shared by several players, e.g.
+/// `Player (1a2b3c4d)`, so their characters can be told apart in game.
+pub fn disambiguate_nicknames(nicknames: &mut [(Uuid, String)]) {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    nicknames.iter().for_each(|(_, nickname)| {
+        *counts.entry(nickname.clone()).or_insert(0) += 1;
+    });
+    nicknames.iter_mut().for_each(|(player_uid, nickname)| {
+        if counts[nickname.as_str()] > 1 {
+            *nickname = format!("{} ({})", nickname, &player_uid.simple().to_string()[..8]);
+        }
+    });
+}
+
+#[test]
+pub fn test_load_template() {
+    let template = load_template(DEFAULT_TEMPLATE).unwrap();
+    let character_save = template.create_character_save("Player", Uuid::nil());
+    let Some(uesave::Property::Struct {
+        value: uesave::StructValue::Struct(properties),
+        ..
+    }) = character_save.properties.get("SaveParameter")
+    else {
+        panic!()
+    };
+    assert!(matches!(
+        &properties["NickName"],
+        uesave::Property::Str { value, .. } if value == "Player"
+    ));
+
+    let mut properties: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(DEFAULT_TEMPLATE).unwrap();
+    properties.remove("SaveParameter");
+    let source = serde_json::to_string(&properties).unwrap();
+    assert!(matches!(
+        load_template(&source),
+        Err(TemplateError::MissingSaveParameter)
+    ));
+
+    assert!(matches!(
+        load_template("{"),
+        Err(TemplateError::Parse { .. })
+    ));
+}
+
+#[test]
+pub fn test_load_template_save_parameter_shape() {
+    let source = r#"{ "SaveParameter": { "Str": { "value": "Default player" } } }"#;
+    let error = load_template(source).unwrap_err();
+    assert!(matches!(
+        &error,
+        TemplateError::BadSaveParameterShape { shape } if shape == "a Str property"
+    ));
+    assert_eq!(
+        error.to_string(),
+        "Template SaveParameter must be a Struct property of properties, but it is a Str property"
+    );
+
+    let source = r#"{
+        "SaveParameter": {
+            "Struct": {
+                "value": { "Guid": "00000000-0000-0000-0000-000000000000" },
+                "struct_type": "Guid",
+                "struct_id": "00000000-0000-0000-0000-000000000000"
+            }
+        }
+    }"#;
+    assert!(matches!(
+        load_template(source),
+        Err(TemplateError::BadSaveParameterShape { shape })
+            if shape == "a Struct property holding a Guid"
+    ));
+}
+
+#[test]
+pub fn test_load_template_file_with_bom() {
+    use crate::fixtures;
+
+    let dir = fixtures::temp_dir("load_template_file_with_bom");
+    let path = dir.join("template.json");
+    std::fs::write(&path, format!("\u{feff}{}", DEFAULT_TEMPLATE)).unwrap();
+    let source = std::fs::read_to_string(&path).unwrap();
+    assert!(source.starts_with('\u{feff}'));
+    assert!(load_template(&source).is_ok());
+}
+
+#[test]
+pub fn test_load_template_syntax_error_location() {
+    // A trailing comma, which some editors leave behind
+    let source = "{\n  \"IsPlayer\": { \"Bool\": { \"value\": true } },\n}\n";
+    let error = load_template(source).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Failed to parse template at line 3, column 1: trailing comma\n}\n^"
+    );
+}
+
+#[test]
+pub fn test_create_character_save_from_sibling() {
+    let template = load_template(DEFAULT_TEMPLATE).unwrap();
+    let guild_id = Uuid::from_u128(0x100);
+    let save_parameter = |character_save: &CharacterSaveParameter| {
+        let Some(uesave::Property::Struct {
+            value: uesave::StructValue::Struct(properties),
+            ..
+        }) = character_save.properties.get("SaveParameter")
+        else {
+            panic!()
+        };
+        properties.clone()
+    };
+
+    // A sibling from a newer game version, with a field the template lacks
+    let mut sibling = template.create_character_save("Sibling", guild_id);
+    let Some(uesave::Property::Struct {
+        value: uesave::StructValue::Struct(properties),
+        ..
+    }) = sibling.properties.get_mut("SaveParameter")
+    else {
+        panic!()
+    };
+    properties.insert(
+        "NewGameField".into(),
+        uesave::Property::Int { id: None, value: 7 },
+    );
+    properties.insert(
+        "OwnerPlayerUId".into(),
+        uesave::Property::Struct {
+            id: None,
+            value: uesave::StructValue::Guid(Uuid::from_u128(1)),
+            struct_type: uesave::StructType::Guid,
+            struct_id: Uuid::nil(),
+        },
+    );
+    properties.insert(
+        "Support".into(),
+        uesave::Property::Int { id: None, value: 1 },
+    );
+
+    let character_save = template.create_character_save_from_sibling("Player", guild_id, &sibling);
+    assert_eq!(character_save.group_id, guild_id);
+    let properties = save_parameter(&character_save);
+    assert!(matches!(
+        properties["NewGameField"],
+        uesave::Property::Int { value: 7, .. }
+    ));
+    assert!(!properties.contains_key("OwnerPlayerUId"));
+    assert!(matches!(
+        &properties["NickName"],
+        uesave::Property::Str { value, .. } if value == "Player"
+    ));
+    // Fields of the template win over the sibling's
+    let from_template = save_parameter(&template.create_character_save("Player", guild_id));
+    assert!(matches!(
+        properties["Support"],
+        uesave::Property::Int { value: 100, .. }
+    ));
+    assert!(from_template
+        .keys()
+        .all(|name| properties.contains_key(name)));
+    assert!(!from_template.contains_key("NewGameField"));
+}
+
+#[test]
+pub fn test_pick_nickname() {
+    let player_uid = Uuid::from_u128(0x1a2b3c4d << 96);
+    assert_eq!(
+        pick_nickname(player_uid, Some("Host"), "Guild", Some("Debug")),
+        ("Host".to_string(), NicknameSource::LevelMeta)
+    );
+    assert_eq!(
+        pick_nickname(player_uid, None, "Guild", Some("Debug")),
+        ("Guild".to_string(), NicknameSource::Guild)
+    );
+    assert_eq!(
+        pick_nickname(player_uid, Some(""), "", Some("Debug")),
+        ("Debug".to_string(), NicknameSource::DebugName)
+    );
+    assert_eq!(
+        pick_nickname(player_uid, None, "", Some("")),
+        ("Player (1a2b3c4d)".to_string(), NicknameSource::PlayerUId)
+    );
+}
+
+#[test]
+pub fn test_disambiguate_nicknames() {
+    let mut nicknames = vec![
+        (Uuid::from_u128(0x1a2b3c4d << 96), "Player".to_string()),
+        (Uuid::from_u128(0x5e6f7081 << 96), "Player".to_string()),
+        (Uuid::from_u128(0x99999999 << 96), "Other".to_string()),
+    ];
+    disambiguate_nicknames(&mut nicknames);
+    assert_eq!(
+        nicknames
+            .iter()
+            .map(|(_, nickname)| nickname.as_str())
+            .collect::<Vec<_>>(),
+        vec!["Player (1a2b3c4d)", "Player (5e6f7081)", "Other"]
+    );
+}
@@ -0,0 +1,39 @@
+use std::io::{Read, Write};
+
+use crate::{
+    json::{write_json, JsonStyle},
+    sav::{CompressionType, PalSave},
+};
+
+/// Writes the whole `Save` in uesave's JSON format, so it can be edited with uesave-based tools.
+/// The compression type is not part of the format.
+pub fn write_uesave_json<W: Write>(
+    writer: W,
+    pal_save: &PalSave,
+    style: JsonStyle,
+) -> anyhow::Result<()> {
+    write_json(writer, &pal_save.save, style)
+}
+
+pub fn read_uesave_json<R: Read>(
+    reader: R,
+    compression_type: CompressionType,
+) -> anyhow::Result<PalSave> {
+    let save: uesave::Save = serde_json::from_reader(reader)?;
+    Ok(PalSave {
+        compression_type,
+        save,
+    })
+}
+
+#[test]
+pub fn test_read_write_uesave_json() {
+    use crate::sav::read_save_file;
+
+    let pal_save = read_save_file(std::fs::File::open("assets/Level.sav").unwrap()).unwrap();
+
+    let mut json = Vec::new();
+    write_uesave_json(&mut json, &pal_save, JsonStyle::Pretty).unwrap();
+    let re_pal_save = read_uesave_json(json.as_slice(), pal_save.compression_type).unwrap();
+    assert_eq!(pal_save, re_pal_save);
+}
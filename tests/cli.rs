@@ -0,0 +1,12 @@
+use std::process::Command;
+
+#[test]
+pub fn test_quiet_stdout_is_json_only() {
+    let output = Command::new(env!("CARGO_BIN_EXE_palworld-guild-save-fix"))
+        .args(["--quiet", "to-uesave-json", "assets/Level.sav"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap();
+}